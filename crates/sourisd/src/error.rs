@@ -2,7 +2,9 @@ use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
 };
-use sourisdb::{store::StoreSerError, types::integer::IntegerSerError, values::ValueSerError};
+use sourisdb::{
+    store::StoreSerError, types::integer::IntegerSerError, values::ValueSerError, MAX_DB_NAME_LEN,
+};
 use std::{
     error::Error,
     fmt::{Display, Formatter},
@@ -19,6 +21,10 @@ pub enum SourisError {
     ValueError(ValueSerError),
     InvalidDatabaseName,
     IntegerSerError(IntegerSerError),
+    DatabaseAlreadyExists,
+    UnsupportedContentEncoding(String),
+    DecompressedBodyTooLarge,
+    InvalidCompressedBody,
 }
 
 impl From<IOError> for SourisError {
@@ -64,9 +70,17 @@ impl Display for SourisError {
             Self::ValueError(e) => write!(f, "Error with value: {e}"),
             Self::InvalidDatabaseName => write!(
                 f,
-                "Invalid database name - database names must be ASCII and not equal to `meta`"
+                "Invalid database name - database names must be 1 to {MAX_DB_NAME_LEN} ASCII letters, digits, `-` or `_`, and not equal to `meta`"
             ),
             Self::IntegerSerError(e) => write!(f, "Error deserialising integer: {e:?}"),
+            Self::DatabaseAlreadyExists => write!(f, "A database with that name already exists"),
+            Self::UnsupportedContentEncoding(e) => {
+                write!(f, "Unsupported content encoding: {e:?}")
+            }
+            Self::DecompressedBodyTooLarge => {
+                write!(f, "Request body is too large once decompressed")
+            }
+            Self::InvalidCompressedBody => write!(f, "Unable to decompress request body"),
         }
     }
 }
@@ -77,7 +91,10 @@ impl IntoResponse for SourisError {
 
         let code = match self {
             Self::DatabaseNotFound | Self::KeyNotFound => StatusCode::NOT_FOUND,
-            Self::InvalidDatabaseName => StatusCode::BAD_REQUEST,
+            Self::InvalidDatabaseName | Self::InvalidCompressedBody => StatusCode::BAD_REQUEST,
+            Self::DatabaseAlreadyExists => StatusCode::CONFLICT,
+            Self::UnsupportedContentEncoding(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::DecompressedBodyTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         };
 
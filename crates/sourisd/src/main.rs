@@ -22,7 +22,10 @@ use tower_http::trace::TraceLayer;
 use tracing_subscriber::{prelude::*, EnvFilter};
 
 use crate::v1_routes::{
-    db::{add_db, add_db_with_content, clear_db, get_all_dbs, get_db, remove_db},
+    db::{
+        add_db, add_db_with_content, clear_db, copy_db, get_all_dbs, get_db, get_keys, remove_db,
+        rename_db,
+    },
     state::SourisState,
     value::{add_kv, get_value, rm_key},
 };
@@ -124,10 +127,13 @@ async fn main() {
 
     let v1_router = Router::new()
         .route("/get_db", get(get_db))
+        .route("/get_keys", get(get_keys))
         .route("/get_all_db_names", get(get_all_dbs))
         .route("/add_db", post(add_db))
         .route("/add_db_with_content", put(add_db_with_content))
         .route("/rm_db", post(remove_db))
+        .route("/rename_db", post(rename_db))
+        .route("/copy_db", post(copy_db))
         .route("/clear_db", post(clear_db))
         .route("/add_kv", put(add_kv))
         .route("/rm_kv", post(rm_key))
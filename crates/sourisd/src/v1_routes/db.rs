@@ -1,15 +1,19 @@
 use axum::{
     body::Bytes,
     extract::{Query, State},
-    http::StatusCode,
+    http::{header::CONTENT_ENCODING, HeaderMap, StatusCode},
     Json,
 };
+use miniz_oxide::inflate::{decompress_to_vec_zlib_with_limit, TINFLStatus};
 use serde::Deserialize;
 
-use sourisdb::store::Store;
+use sourisdb::store::{Store, StoreSerError};
 
 use crate::{error::SourisError, v1_routes::state::SourisState};
 
+///The largest an upload can be once it has been decompressed - this applies both to the `deflate` content encoding and to the store's own compression, so that a small upload can't inflate to fill all of memory.
+const MAX_DECOMPRESSED_UPLOAD_LEN: usize = 256 * 1024 * 1024;
+
 #[derive(Deserialize)]
 pub struct NewDB {
     pub db_name: String,
@@ -21,6 +25,20 @@ pub struct DbByName {
     pub db_name: String,
 }
 
+#[derive(Deserialize)]
+pub struct CopyDB {
+    pub src_name: String,
+    pub dst_name: String,
+    pub overwrite_existing: bool,
+}
+
+#[derive(Deserialize)]
+pub struct RenameDB {
+    pub old_name: String,
+    pub new_name: String,
+    pub overwrite_existing: bool,
+}
+
 pub async fn add_db(
     State(state): State<SourisState>,
     Query(NewDB {
@@ -37,12 +55,38 @@ pub async fn add_db_with_content(
         db_name: name,
         overwrite_existing,
     }): Query<NewDB>,
+    headers: HeaderMap,
     body: Bytes,
 ) -> Result<StatusCode, SourisError> {
-    let store = Store::deser(body.as_ref())?;
-    Ok(state
+    let store = deser_upload(&headers, body.as_ref(), MAX_DECOMPRESSED_UPLOAD_LEN)?;
+    state
         .new_db_with_contents(name, overwrite_existing, store)
-        .await)
+        .await
+}
+
+///Deserialises an uploaded store, decompressing it first if the client compressed it - see `AsyncClient::with_upload_compression_threshold`.
+fn deser_upload(headers: &HeaderMap, body: &[u8], limit: usize) -> Result<Store, SourisError> {
+    let deser = |body: &[u8]| match Store::deser_with_max_len(body, limit) {
+        Err(StoreSerError::DecompressedTooLarge(_)) => Err(SourisError::DecompressedBodyTooLarge),
+        res => Ok(res?),
+    };
+
+    match headers.get(CONTENT_ENCODING).map(|e| e.to_str()) {
+        None => deser(body),
+        Some(Ok("deflate")) => {
+            let body = decompress_to_vec_zlib_with_limit(body, limit).map_err(|e| {
+                if e.status == TINFLStatus::HasMoreOutput {
+                    SourisError::DecompressedBodyTooLarge
+                } else {
+                    SourisError::InvalidCompressedBody
+                }
+            })?;
+            deser(&body)
+        }
+        Some(encoding) => Err(SourisError::UnsupportedContentEncoding(
+            encoding.unwrap_or("non-ASCII encoding").to_string(),
+        )),
+    }
 }
 
 pub async fn clear_db(
@@ -61,6 +105,31 @@ pub async fn remove_db(
     Ok(StatusCode::OK)
 }
 
+pub async fn rename_db(
+    State(state): State<SourisState>,
+    Query(RenameDB {
+        old_name,
+        new_name,
+        overwrite_existing,
+    }): Query<RenameDB>,
+) -> Result<StatusCode, SourisError> {
+    state
+        .rename_db(old_name, new_name, overwrite_existing)
+        .await?;
+    Ok(StatusCode::OK)
+}
+
+pub async fn copy_db(
+    State(state): State<SourisState>,
+    Query(CopyDB {
+        src_name,
+        dst_name,
+        overwrite_existing,
+    }): Query<CopyDB>,
+) -> Result<StatusCode, SourisError> {
+    state.copy_db(src_name, dst_name, overwrite_existing).await
+}
+
 #[axum::debug_handler]
 pub async fn get_db(
     State(state): State<SourisState>,
@@ -69,6 +138,78 @@ pub async fn get_db(
     state.get_db(name).await
 }
 
+pub async fn get_keys(
+    State(state): State<SourisState>,
+    Query(DbByName { db_name: name }): Query<DbByName>,
+) -> Result<Json<Vec<String>>, SourisError> {
+    Ok(Json(state.get_keys(name).await?))
+}
+
 pub async fn get_all_dbs(State(state): State<SourisState>) -> Json<Vec<String>> {
     Json(state.get_all_db_names().await)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use miniz_oxide::deflate::compress_to_vec_zlib;
+    use sourisdb::{types::integer::Integer, values::Value};
+
+    fn deflate_headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_ENCODING, HeaderValue::from_static("deflate"));
+        headers
+    }
+
+    #[test]
+    fn test_deser_upload() {
+        let mut store = Store::default();
+        store.insert("key".into(), Value::String("a".repeat(1_000)));
+        let bytes = store.ser().unwrap();
+        let compressed = compress_to_vec_zlib(&bytes, 6);
+
+        assert_eq!(
+            deser_upload(&HeaderMap::new(), &bytes, MAX_DECOMPRESSED_UPLOAD_LEN).unwrap(),
+            store
+        );
+        assert_eq!(
+            deser_upload(&deflate_headers(), &compressed, MAX_DECOMPRESSED_UPLOAD_LEN).unwrap(),
+            store
+        );
+
+        assert!(matches!(
+            deser_upload(&deflate_headers(), &compressed, bytes.len() - 1),
+            Err(SourisError::DecompressedBodyTooLarge)
+        ));
+        assert!(matches!(
+            deser_upload(&deflate_headers(), b"not deflate", bytes.len()),
+            Err(SourisError::InvalidCompressedBody)
+        ));
+
+        //the store's own compression is limited too
+        let mut bomb = b"SOURISDB".to_vec();
+        bomb.push(1);
+        bomb.extend(Integer::from(1_usize << 44).ser().1);
+        bomb.push(0);
+        assert!(matches!(
+            deser_upload(&HeaderMap::new(), &bomb, MAX_DECOMPRESSED_UPLOAD_LEN),
+            Err(SourisError::DecompressedBodyTooLarge)
+        ));
+        let mut large = Store::default();
+        large.insert("key".into(), Value::Binary(vec![0; 1_000_000]));
+        let bytes = large.ser().unwrap();
+        assert!(bytes.len() < 100_000);
+        assert!(matches!(
+            deser_upload(&HeaderMap::new(), &bytes, 100_000),
+            Err(SourisError::DecompressedBodyTooLarge)
+        ));
+
+        let mut gzip = HeaderMap::new();
+        gzip.insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        assert!(matches!(
+            deser_upload(&gzip, &compressed, bytes.len()),
+            Err(SourisError::UnsupportedContentEncoding(e)) if e == "gzip"
+        ));
+    }
+}
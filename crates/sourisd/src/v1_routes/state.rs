@@ -23,6 +23,15 @@ mod meta {
 use crate::error::SourisError;
 use meta::{DB_FILE_NAMES_KEY, META_DB_FILE_NAME};
 
+///Checks that `name` can be used for a database, using the same rules as the clients.
+fn check_db_name(name: &str) -> Result<(), SourisError> {
+    if sourisdb::is_valid_db_name(name) {
+        Ok(())
+    } else {
+        Err(SourisError::InvalidDatabaseName)
+    }
+}
+
 #[derive(Clone, Debug)]
 #[allow(clippy::module_name_repetitions)]
 pub struct SourisState {
@@ -37,9 +46,7 @@ impl SourisState {
         name: String,
         overwrite_existing: bool,
     ) -> Result<StatusCode, SourisError> {
-        if name == "meta" || !name.is_ascii() {
-            return Err(SourisError::InvalidDatabaseName);
-        }
+        check_db_name(&name)?;
 
         let mut dbs = self.dbs.lock().await;
 
@@ -69,24 +76,26 @@ impl SourisState {
         name: String,
         overwrite_existing: bool,
         contents: Store,
-    ) -> StatusCode {
+    ) -> Result<StatusCode, SourisError> {
+        check_db_name(&name)?;
+
         let mut stores = self.dbs.lock().await;
 
         let mut contained = false;
         if stores.contains_key(&name) {
             contained = true;
             if !overwrite_existing {
-                return StatusCode::OK;
+                return Ok(StatusCode::OK);
             }
         }
 
         stores.insert(name, contents);
 
-        if contained {
+        Ok(if contained {
             StatusCode::OK
         } else {
             StatusCode::CREATED
-        }
+        })
     }
 
     ///returns whether it cleared a database
@@ -112,6 +121,87 @@ impl SourisState {
 
         dbs.remove(&name);
 
+        self.remove_db_file(&name).await
+    }
+
+    ///moves a database to a new name, replacing any database already there if `overwrite_existing` is set
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub async fn rename_db(
+        &self,
+        old_name: String,
+        new_name: String,
+        overwrite_existing: bool,
+    ) -> Result<(), SourisError> {
+        check_db_name(&new_name)?;
+
+        let mut dbs = self.dbs.lock().await;
+        if !dbs.contains_key(&old_name) {
+            return Err(SourisError::DatabaseNotFound);
+        }
+        if old_name == new_name {
+            return Ok(());
+        }
+        if dbs.contains_key(&new_name) && !overwrite_existing {
+            return Err(SourisError::DatabaseAlreadyExists);
+        }
+
+        //write out the new file and point the meta database at it before touching the old one, so that if anything fails (or the server stops) the database can still be found
+        let Some(store) = dbs.get(&old_name) else {
+            unreachable!("just checked that the key exists")
+        };
+        let file_name = self.base_location.join(format!("{new_name}.sdb"));
+        write_to_file(&store.ser()?, file_name, &self.base_location).await?;
+
+        let names = dbs
+            .keys()
+            .filter(|name| **name != old_name && **name != new_name)
+            .chain([&new_name]);
+        self.write_meta(names).await?;
+
+        let Some(store) = dbs.remove(&old_name) else {
+            unreachable!("just checked that the key exists")
+        };
+        dbs.insert(new_name, store);
+
+        //the meta database no longer mentions the old file, so it is never read even if it can't be removed
+        if let Err(e) = self.remove_db_file(&old_name).await {
+            error!(?e, "Error removing renamed database file");
+        }
+        Ok(())
+    }
+
+    ///copies a database to a new name, returning whether a new database was created
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub async fn copy_db(
+        &self,
+        src_name: String,
+        dst_name: String,
+        overwrite_existing: bool,
+    ) -> Result<StatusCode, SourisError> {
+        check_db_name(&dst_name)?;
+
+        let mut dbs = self.dbs.lock().await;
+        let Some(store) = dbs.get(&src_name).cloned() else {
+            return Err(SourisError::DatabaseNotFound);
+        };
+        if src_name == dst_name {
+            return Ok(StatusCode::OK);
+        }
+
+        let existed = dbs.contains_key(&dst_name);
+        if existed && !overwrite_existing {
+            return Err(SourisError::DatabaseAlreadyExists);
+        }
+        dbs.insert(dst_name, store);
+
+        Ok(if existed {
+            StatusCode::OK
+        } else {
+            StatusCode::CREATED
+        })
+    }
+
+    async fn remove_db_file(&self, name: &str) -> Result<(), SourisError> {
         let file_name = self.base_location.join(format!("{name}.sdb"));
 
         if let Err(e) = tokio::fs::remove_file(file_name).await {
@@ -170,6 +260,15 @@ impl SourisState {
         }
     }
 
+    pub async fn get_keys(&self, name: String) -> Result<Vec<String>, SourisError> {
+        let dbs = self.dbs.lock().await;
+        let db = dbs.get(&name).ok_or(SourisError::DatabaseNotFound)?;
+
+        let mut keys: Vec<String> = db.keys().cloned().collect();
+        keys.sort_unstable();
+        Ok(keys)
+    }
+
     pub async fn get_all_db_names(&self) -> Vec<String> {
         self.dbs.lock().await.keys().cloned().collect()
     }
@@ -270,16 +369,34 @@ impl SourisState {
             if let Err(e) = write_to_file(&bytes, file_name, &self.base_location).await {
                 error!(?e, "Error writing out database");
             } else {
-                names.push(Value::String(name.to_string()));
+                names.push(name.to_string());
             }
         }
 
+        self.write_meta(names.iter()).await?;
+
+        Ok(())
+    }
+
+    ///writes out the meta database, which lists the databases to read in at startup
+    async fn write_meta(
+        &self,
+        names: impl Iterator<Item = impl AsRef<str>>,
+    ) -> Result<(), SourisError> {
         let mut meta = Store::default();
-        meta.insert(DB_FILE_NAMES_KEY.into(), Value::Array(names));
+        meta.insert(
+            DB_FILE_NAMES_KEY.into(),
+            Value::Array(
+                names
+                    .map(|name| Value::String(name.as_ref().to_string()))
+                    .collect(),
+            ),
+        );
 
         let location = self.base_location.join(META_DB_FILE_NAME);
-        let meta = meta.ser()?;
-        write_to_file(&meta, location, &self.base_location).await
+        write_to_file(&meta.ser()?, location, &self.base_location).await?;
+
+        Ok(())
     }
 }
 
@@ -287,7 +404,7 @@ async fn write_to_file(
     bytes: &[u8],
     path: impl AsRef<Path> + Debug,
     base_location: impl AsRef<Path> + Debug,
-) -> color_eyre::Result<()> {
+) -> std::io::Result<()> {
     let mut file = match File::create(&path).await {
         Ok(f) => f,
         Err(e) => {
@@ -298,12 +415,129 @@ async fn write_to_file(
                 create_dir_all(&base_location).await?;
                 File::create(path).await?
             } else {
-                return Err(e.into());
+                return Err(e);
             }
         }
     };
 
     file.write_all(bytes).await?;
+    file.flush().await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_state(dir_name: &str) -> SourisState {
+        let base_location =
+            std::env::temp_dir().join(format!("sourisd-test-{dir_name}-{}", std::process::id()));
+        SourisState {
+            base_location,
+            dbs: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_db_names_match_client_rules() {
+        let state = test_state("names");
+
+        for invalid in ["", "meta", "a/b", "../up", "dot.name", "has space"] {
+            assert!(matches!(
+                state.new_db(invalid.into(), false).await,
+                Err(SourisError::InvalidDatabaseName)
+            ));
+            assert!(matches!(
+                state
+                    .new_db_with_contents(invalid.into(), false, Store::default())
+                    .await,
+                Err(SourisError::InvalidDatabaseName)
+            ));
+        }
+        assert_eq!(
+            state.new_db("my-db_2".into(), false).await.unwrap(),
+            StatusCode::CREATED
+        );
+        assert!(matches!(
+            state.rename_db("my-db_2".into(), "a/b".into(), false).await,
+            Err(SourisError::InvalidDatabaseName)
+        ));
+        assert!(matches!(
+            state.copy_db("my-db_2".into(), "a.b".into(), false).await,
+            Err(SourisError::InvalidDatabaseName)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rename_db_writes_new_file() {
+        let state = test_state("rename");
+        state
+            .new_db("old".into(), false)
+            .await
+            .expect("valid database name");
+        state
+            .add_key_value_pair("old".into(), "key".into(), Value::Boolean(true))
+            .await;
+        state.save().await.expect("able to save");
+
+        state
+            .rename_db("old".into(), "new".into(), false)
+            .await
+            .expect("able to rename");
+
+        let old_file = state.base_location.join("old.sdb");
+        let new_file = state.base_location.join("new.sdb");
+        assert!(!old_file.exists());
+        let bytes = tokio::fs::read(&new_file)
+            .await
+            .expect("new file written before the next save");
+        let store = Store::deser(&bytes).expect("able to deser renamed file");
+        assert_eq!(store.get("key"), Some(&Value::Boolean(true)));
+
+        let bytes = tokio::fs::read(state.base_location.join(META_DB_FILE_NAME))
+            .await
+            .expect("meta database written before the next save");
+        let meta = Store::deser(&bytes).expect("able to deser meta database");
+        assert_eq!(
+            meta.get(DB_FILE_NAMES_KEY),
+            Some(&Value::Array(vec![Value::String("new".into())]))
+        );
+
+        tokio::fs::remove_dir_all(&state.base_location)
+            .await
+            .expect("able to clean up");
+    }
+
+    #[tokio::test]
+    async fn test_rename_db_keeps_store_on_error() {
+        let state = test_state("rename-error");
+        //a file where the directory should be, so that nothing can be written into it
+        tokio::fs::write(&state.base_location, b"not a directory")
+            .await
+            .expect("able to write file");
+        state
+            .add_key_value_pair("old".into(), "key".into(), Value::Boolean(true))
+            .await;
+
+        assert!(matches!(
+            state.rename_db("old".into(), "new".into(), false).await,
+            Err(SourisError::IO(_))
+        ));
+        assert_eq!(
+            state
+                .get_value("old".into(), &"key".to_string())
+                .await
+                .expect("store kept under the old name"),
+            Value::Boolean(true)
+        );
+        assert!(matches!(
+            state.get_db("new".into()).await,
+            Err(SourisError::DatabaseNotFound)
+        ));
+
+        tokio::fs::remove_file(&state.base_location)
+            .await
+            .expect("able to clean up");
+    }
+}
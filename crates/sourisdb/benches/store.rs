@@ -0,0 +1,31 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use sourisdb::store::Store;
+
+const DOCUMENTS: i64 = 100;
+
+fn deser_many_stores(c: &mut Criterion) {
+    let documents: Vec<Vec<u8>> = (0..DOCUMENTS)
+        .map(|i| Store::from_range(i..(i * 10)).ser().unwrap())
+        .collect();
+
+    c.bench_function("deser stores", |b| {
+        b.iter(|| {
+            for bytes in &documents {
+                black_box(Store::deser(bytes).unwrap());
+            }
+        })
+    });
+
+    c.bench_function("deser stores into one buffer", |b| {
+        let mut buf = vec![];
+        b.iter(|| {
+            for bytes in &documents {
+                black_box(Store::deser_in(bytes, &mut buf).unwrap());
+            }
+        })
+    });
+}
+
+criterion_group!(runtime, deser_many_stores);
+criterion_main!(runtime);
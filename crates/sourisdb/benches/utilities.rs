@@ -1,6 +1,9 @@
-use criterion::{black_box, Criterion, criterion_group, criterion_main};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
-use sourisdb::utilities::{bits::Bits, cursor::Cursor, huffman::Huffman};
+use sourisdb::{
+    types::integer::Integer,
+    utilities::{bits::Bits, cursor::Cursor, huffman::Huffman},
+};
 
 const BEE_MOVIE: &str = include_str!("./beemoviescript.txt");
 const BEE_MOVIE_LINES: usize = 1;
@@ -92,6 +95,28 @@ fn ser_de_bits(c: &mut Criterion) {
     });
 }
 
-criterion_group!(runtime, en_de_code_beemovie);
+fn display_integers(c: &mut Criterion) {
+    let small: Vec<Integer> = (0_u8..=255).map(Integer::from).collect();
+    let negative: Vec<Integer> = (-128_i8..0).map(Integer::from).collect();
+    let large: Vec<Integer> = (0..256_u128)
+        .map(|i| Integer::from(u128::MAX - i))
+        .collect();
+
+    for (name, ints) in [
+        ("display small integers", small),
+        ("display negative integers", negative),
+        ("display large integers", large),
+    ] {
+        c.bench_function(name, |b| {
+            b.iter(|| {
+                for i in &ints {
+                    black_box(i.to_string());
+                }
+            })
+        });
+    }
+}
+
+criterion_group!(runtime, en_de_code_beemovie, display_integers);
 criterion_group!(serde, ser_de_huffman, ser_de_bits);
 criterion_main!(runtime, serde);
@@ -4,14 +4,19 @@
 //!
 //! The methods available on both clients are identical, save the async ones being async. The [`ClientError`] type changes based off which features are enabled to hold the error types for the HTTP library.
 //!
-//! The sync client is backed by [`ureq`] and the async client by [`reqwest`].
+//! The sync client is backed by [`ureq`] and the async client by [`reqwest`]. The async client can also be given a [`RedirectPolicy`] using [`AsyncClient::new_with_redirect_policy`], which by default doesn't follow redirects to other hosts.
 
-use core::fmt::{Display, Formatter};
+use core::{
+    convert::Infallible,
+    fmt::{Display, Formatter},
+};
 
-use http::{status::InvalidStatusCode, StatusCode};
+#[cfg(feature = "sync_client")]
+use http::status::InvalidStatusCode;
+use http::StatusCode;
 
 #[cfg(feature = "async_client")]
-pub use async_client::AsyncClient;
+pub use async_client::{AsyncClient, CircuitBreakerConfig, RedirectPolicy};
 #[cfg(feature = "sync_client")]
 pub use sync_client::SyncClient;
 
@@ -22,12 +27,100 @@ mod async_client;
 #[cfg(feature = "sync_client")]
 mod sync_client;
 
+///Checks that `bytes` at least starts with the magic bytes of a serialised [`crate::store::Store`], so that obviously wrong bodies are caught before being sent.
+fn check_store_magic_bytes(bytes: &[u8]) -> Result<(), ClientError> {
+    match bytes.get(..8) {
+        None => Err(StoreSerError::NotEnoughBytes.into()),
+        Some(magic_bytes) if magic_bytes != b"SOURISDB" => {
+            Err(StoreSerError::ExpectedMagicBytes.into())
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+///The `Content-Encoding` used for uploads compressed by [`compress_upload`], which the server decompresses before reading the store.
+const UPLOAD_CONTENT_ENCODING: &str = "deflate";
+
+///Compresses `bytes` using zlib (which is what HTTP calls `deflate`) if there is a `threshold` and `bytes` is longer than it, for clients set up with an upload compression threshold.
+fn compress_upload(bytes: &[u8], threshold: Option<usize>) -> Option<Vec<u8>> {
+    let threshold = threshold?;
+    (bytes.len() > threshold).then(|| miniz_oxide::deflate::compress_to_vec_zlib(bytes, 6))
+}
+
+///The name of a database, which has been checked to only contain characters which are safe to send to the server and use as a file name there - see [`crate::is_valid_db_name`] for the rules, which the server checks too.
+///
+/// The client methods take anything which can be turned into a `DbName` (eg. a `&str`), so an invalid name fails with [`ClientError::InvalidDbName`] before anything is sent.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DbName(String);
+
+impl DbName {
+    ///The maximum length of a database name.
+    pub const MAX_LEN: usize = crate::MAX_DB_NAME_LEN;
+
+    ///Checks that `name` is a valid database name.
+    ///
+    /// ## Errors
+    /// [`ClientError::InvalidDbName`] if the name is invalid - see [`DbName`] for the rules.
+    pub fn new(name: impl Into<String>) -> Result<Self, ClientError> {
+        let name = name.into();
+
+        if crate::is_valid_db_name(&name) {
+            Ok(Self(name))
+        } else {
+            Err(ClientError::InvalidDbName(name))
+        }
+    }
+
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for DbName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl TryFrom<&str> for DbName {
+    type Error = ClientError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+impl TryFrom<String> for DbName {
+    type Error = ClientError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+impl TryFrom<&String> for DbName {
+    type Error = ClientError;
+
+    fn try_from(value: &String) -> Result<Self, Self::Error> {
+        Self::new(value.as_str())
+    }
+}
+impl From<&DbName> for DbName {
+    fn from(value: &DbName) -> Self {
+        value.clone()
+    }
+}
+
+///Turns anything which can be turned into a [`DbName`] into one, for use in the client methods.
+fn to_db_name(name: impl TryInto<DbName, Error: Into<ClientError>>) -> Result<DbName, ClientError> {
+    name.try_into().map_err(Into::into)
+}
+
 ///An error which could occur using one of the [`sourisd`] clients.
 #[derive(Debug)]
 pub enum ClientError {
     ///An error from `ureq` - this can only be a transport issue as HTTP error codes are handled in a separate variant - [`ClientError::HttpErrorCode`].
     #[cfg(feature = "sync_client")]
-    Ureq(ureq::Transport),
+    Ureq(Box<ureq::Transport>),
     ///An error from `reqwest` - this could be from a variety of sources, but not HTTP error codes - thy are handled in [`ClientError::HttpErrorCode`].
     #[cfg(feature = "async_client")]
     Reqwest(reqwest::Error),
@@ -47,6 +140,15 @@ pub enum ClientError {
     ServerNotHealthy(StatusCode),
     ///An error occurred with `serde_json`.
     SerdeJson(serde_json::Error),
+    ///A database name was invalid - see [`DbName`] for what is allowed.
+    InvalidDbName(String),
+    ///The server sent back a body larger than the limit set on the client (which is held in this variant) - see [`AsyncClient::with_max_response_bytes`] or [`SyncClient::with_max_response_bytes`].
+    ResponseTooLarge(usize),
+    ///A database couldn't be renamed or copied to this name, as a database with this name already exists and overwriting wasn't allowed.
+    DatabaseAlreadyExists(String),
+    ///The request wasn't sent, as the circuit breaker set with [`AsyncClient::with_circuit_breaker`] is open after too many failures in a row.
+    #[cfg(feature = "async_client")]
+    CircuitOpen,
 }
 
 impl Display for ClientError {
@@ -58,7 +160,9 @@ impl Display for ClientError {
             Self::Reqwest(r) => write!(f, "Error with reqwest: {r}"),
             Self::Store(s) => write!(f, "Error with store: {s}"),
             Self::HttpErrorCode(sc) => write!(f, "Error with response: {sc:?}"),
+            #[cfg(feature = "sync_client")]
             Self::IO(e) => write!(f, "IO Error: {e}"),
+            #[cfg(feature = "sync_client")]
             Self::InvalidStatusCode(e) => write!(f, "Invalid status code provided: {e}"),
             Self::ServerNotHealthy(sc) => write!(
                 f,
@@ -66,6 +170,18 @@ impl Display for ClientError {
             ),
             Self::SerdeJson(e) => write!(f, "Tried to parse JSON and failed: {e}"),
             Self::Value(e) => write!(f, "Error with value: {e}"),
+            Self::InvalidDbName(name) => write!(f, "Invalid database name: {name:?}"),
+            Self::ResponseTooLarge(max) => {
+                write!(f, "Response body was larger than the limit of {max} bytes")
+            }
+            Self::DatabaseAlreadyExists(name) => {
+                write!(f, "A database called {name:?} already exists")
+            }
+            #[cfg(feature = "async_client")]
+            Self::CircuitOpen => write!(
+                f,
+                "Not sending request as the server has failed too many times in a row"
+            ),
         }
     }
 }
@@ -73,7 +189,7 @@ impl Display for ClientError {
 #[cfg(feature = "sync_client")]
 impl From<ureq::Transport> for ClientError {
     fn from(value: ureq::Transport) -> Self {
-        Self::Ureq(value)
+        Self::Ureq(Box::new(value))
     }
 }
 #[cfg(feature = "sync_client")]
@@ -84,7 +200,7 @@ impl From<ureq::Error> for ClientError {
                 Ok(sc) => ClientError::HttpErrorCode(sc),
                 Err(e) => ClientError::InvalidStatusCode(e),
             },
-            ureq::Error::Transport(transport_error) => ClientError::Ureq(transport_error),
+            ureq::Error::Transport(transport_error) => transport_error.into(),
         }
     }
 }
@@ -121,12 +237,112 @@ impl From<ValueSerError> for ClientError {
         Self::Value(value)
     }
 }
+impl From<Infallible> for ClientError {
+    fn from(value: Infallible) -> Self {
+        match value {}
+    }
+}
+
+///A copy of a [`ClientError`] which can be serialised and deserialised with `serde`, eg. for logging errors structurally or sending them on from a gateway.
+///
+/// It is tagged by `kind` (eg. `{"kind": "http_error_code", "status": 404}`), and the names won't change so that it can be read by other processes. Errors from other libraries can't be serialised, so only their messages are kept - this means that it can't be turned back into a [`ClientError`]. Every variant exists no matter which features are enabled, so errors from either client can be read anywhere.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SerialisedClientError {
+    ///[`ClientError::Ureq`], with the error's message.
+    Ureq { message: String },
+    ///[`ClientError::Reqwest`], with the error's message.
+    Reqwest { message: String },
+    ///[`ClientError::Store`], with the error's message.
+    Store { message: String },
+    ///[`ClientError::Value`], with the error's message.
+    Value { message: String },
+    ///[`ClientError::HttpErrorCode`].
+    HttpErrorCode { status: u16 },
+    ///[`ClientError::IO`], with the error's message.
+    #[serde(rename = "io")]
+    IO { message: String },
+    ///[`ClientError::InvalidStatusCode`], with the error's message.
+    InvalidStatusCode { message: String },
+    ///[`ClientError::ServerNotHealthy`].
+    ServerNotHealthy { status: u16 },
+    ///[`ClientError::SerdeJson`], with the error's message.
+    SerdeJson { message: String },
+    ///[`ClientError::InvalidDbName`].
+    InvalidDbName { name: String },
+    ///[`ClientError::ResponseTooLarge`].
+    ResponseTooLarge { max_bytes: usize },
+    ///[`ClientError::DatabaseAlreadyExists`].
+    DatabaseAlreadyExists { name: String },
+    ///[`ClientError::CircuitOpen`].
+    CircuitOpen,
+}
+
+#[cfg(feature = "serde")]
+impl From<&ClientError> for SerialisedClientError {
+    fn from(value: &ClientError) -> Self {
+        match value {
+            #[cfg(feature = "sync_client")]
+            ClientError::Ureq(e) => Self::Ureq {
+                message: e.to_string(),
+            },
+            #[cfg(feature = "async_client")]
+            ClientError::Reqwest(e) => Self::Reqwest {
+                message: e.to_string(),
+            },
+            ClientError::Store(e) => Self::Store {
+                message: e.to_string(),
+            },
+            ClientError::Value(e) => Self::Value {
+                message: e.to_string(),
+            },
+            ClientError::HttpErrorCode(sc) => Self::HttpErrorCode {
+                status: sc.as_u16(),
+            },
+            #[cfg(feature = "sync_client")]
+            ClientError::IO(e) => Self::IO {
+                message: e.to_string(),
+            },
+            #[cfg(feature = "sync_client")]
+            ClientError::InvalidStatusCode(e) => Self::InvalidStatusCode {
+                message: e.to_string(),
+            },
+            ClientError::ServerNotHealthy(sc) => Self::ServerNotHealthy {
+                status: sc.as_u16(),
+            },
+            ClientError::SerdeJson(e) => Self::SerdeJson {
+                message: e.to_string(),
+            },
+            ClientError::InvalidDbName(name) => Self::InvalidDbName { name: name.clone() },
+            ClientError::ResponseTooLarge(max_bytes) => Self::ResponseTooLarge {
+                max_bytes: *max_bytes,
+            },
+            ClientError::DatabaseAlreadyExists(name) => {
+                Self::DatabaseAlreadyExists { name: name.clone() }
+            }
+            #[cfg(feature = "async_client")]
+            ClientError::CircuitOpen => Self::CircuitOpen,
+        }
+    }
+}
+
+///Serialises the error as a [`SerialisedClientError`], which can be used to deserialise it.
+#[cfg(feature = "serde")]
+impl serde::Serialize for ClientError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        SerialisedClientError::from(self).serialize(serializer)
+    }
+}
 
 impl std::error::Error for ClientError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             #[cfg(feature = "sync_client")]
-            Self::Ureq(u) => Some(u),
+            Self::Ureq(u) => Some(u.as_ref()),
             Self::Store(s) => Some(s),
             #[cfg(feature = "sync_client")]
             Self::IO(e) => Some(e),
@@ -138,3 +354,122 @@ impl std::error::Error for ClientError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ClientError, DbName};
+
+    #[test]
+    fn test_db_name() {
+        for valid in ["a", "my_db", "Store-2", &"x".repeat(DbName::MAX_LEN)] {
+            assert_eq!(DbName::new(valid).unwrap().as_str(), valid);
+        }
+
+        for invalid in [
+            "",
+            "meta",
+            "has space",
+            "a/b",
+            "../up",
+            "dot.name",
+            "q?x=1",
+            "ünïcödé",
+            &"x".repeat(DbName::MAX_LEN + 1),
+        ] {
+            assert!(
+                matches!(DbName::new(invalid), Err(ClientError::InvalidDbName(n)) if n == invalid),
+                "{invalid:?} should be invalid"
+            );
+        }
+
+        assert!(DbName::try_from("ok").is_ok());
+        assert!(DbName::try_from(String::from("not ok")).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialised_client_error() {
+        use http::StatusCode;
+        use serde_json::json;
+
+        use super::SerialisedClientError;
+        use crate::{store::StoreSerError, values::ValueSerError};
+
+        let round_trip = |error: ClientError, expected: serde_json::Value| {
+            let serialised = serde_json::to_value(&error).unwrap();
+            assert_eq!(serialised, expected);
+            assert_eq!(
+                serde_json::from_value::<SerialisedClientError>(serialised).unwrap(),
+                SerialisedClientError::from(&error)
+            );
+        };
+
+        #[cfg(feature = "sync_client")]
+        {
+            let ureq::Error::Transport(transport) = ureq::get("not a url").call().unwrap_err()
+            else {
+                panic!("expected a transport error")
+            };
+            let message = transport.to_string();
+            round_trip(
+                ClientError::Ureq(Box::new(transport)),
+                json!({"kind": "ureq", "message": message}),
+            );
+            round_trip(
+                ClientError::IO(std::io::Error::other("disk on fire")),
+                json!({"kind": "io", "message": "disk on fire"}),
+            );
+            let invalid = StatusCode::from_u16(1_000).unwrap_err();
+            let message = invalid.to_string();
+            round_trip(
+                ClientError::InvalidStatusCode(invalid),
+                json!({"kind": "invalid_status_code", "message": message}),
+            );
+        }
+        #[cfg(feature = "async_client")]
+        {
+            let error = reqwest::Client::new().get("not a url").build().unwrap_err();
+            let message = error.to_string();
+            round_trip(
+                ClientError::Reqwest(error),
+                json!({"kind": "reqwest", "message": message}),
+            );
+            round_trip(ClientError::CircuitOpen, json!({"kind": "circuit_open"}));
+        }
+
+        round_trip(
+            ClientError::Store(StoreSerError::ExpectedMagicBytes),
+            json!({"kind": "store", "message": StoreSerError::ExpectedMagicBytes.to_string()}),
+        );
+        round_trip(
+            ClientError::Value(ValueSerError::Empty),
+            json!({"kind": "value", "message": ValueSerError::Empty.to_string()}),
+        );
+        round_trip(
+            ClientError::HttpErrorCode(StatusCode::NOT_FOUND),
+            json!({"kind": "http_error_code", "status": 404}),
+        );
+        round_trip(
+            ClientError::ServerNotHealthy(StatusCode::SERVICE_UNAVAILABLE),
+            json!({"kind": "server_not_healthy", "status": 503}),
+        );
+        let serde_error = serde_json::from_str::<u8>("x").unwrap_err();
+        let message = serde_error.to_string();
+        round_trip(
+            ClientError::SerdeJson(serde_error),
+            json!({"kind": "serde_json", "message": message}),
+        );
+        round_trip(
+            ClientError::InvalidDbName("a b".into()),
+            json!({"kind": "invalid_db_name", "name": "a b"}),
+        );
+        round_trip(
+            ClientError::ResponseTooLarge(1024),
+            json!({"kind": "response_too_large", "max_bytes": 1024}),
+        );
+        round_trip(
+            ClientError::DatabaseAlreadyExists("db".into()),
+            json!({"kind": "database_already_exists", "name": "db"}),
+        );
+    }
+}
@@ -7,16 +7,155 @@
 //!
 //! async fn get_all_database_names_from_localhost () -> Result<Vec<String>, ClientError> {
 //!     let client = AsyncClient::new("localhost", 2256).await?;
-//!     client.get_all_dbs()
+//!     client.get_all_dbs().await
 //! }
 //! ```
 
-use core::fmt::Display;
+use core::{fmt::Display, time::Duration};
+use std::{
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Instant,
+};
 
-use http::StatusCode;
-use reqwest::{Client, Response};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use http::{header::CONTENT_ENCODING, StatusCode};
+use reqwest::{redirect::Policy, Client, RequestBuilder, Response, Url};
 
-use crate::{client::ClientError, store::Store, values::Value};
+use crate::{
+    client::{
+        check_store_magic_bytes, compress_upload, to_db_name, ClientError, DbName,
+        UPLOAD_CONTENT_ENCODING,
+    },
+    store::Store,
+    values::Value,
+};
+
+///How an [`AsyncClient`] handles redirects from the server. A redirect which isn't followed is returned as a [`ClientError::HttpErrorCode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RedirectPolicy {
+    ///Never follow redirects.
+    None,
+    ///Follow up to this many redirects, to any host.
+    Limited(usize),
+    ///Follow up to [`RedirectPolicy::MAX_SAME_HOST_REDIRECTS`] redirects, but only to the same origin as the original request - the same scheme, host and port. This is the default, as following redirects to another host (or another server on the same host) could leak requests somewhere unexpected.
+    #[default]
+    SameHost,
+}
+
+impl RedirectPolicy {
+    ///The maximum number of redirects followed by [`RedirectPolicy::SameHost`].
+    pub const MAX_SAME_HOST_REDIRECTS: usize = 10;
+}
+
+impl From<RedirectPolicy> for Policy {
+    fn from(value: RedirectPolicy) -> Self {
+        match value {
+            RedirectPolicy::None => Policy::none(),
+            RedirectPolicy::Limited(max) => Policy::limited(max),
+            RedirectPolicy::SameHost => Policy::custom(|attempt| {
+                let original_origin = attempt.previous().first().map(Url::origin);
+                if original_origin != Some(attempt.url().origin()) {
+                    attempt.stop()
+                } else if attempt.previous().len() > RedirectPolicy::MAX_SAME_HOST_REDIRECTS {
+                    attempt.error("too many redirects")
+                } else {
+                    attempt.follow()
+                }
+            }),
+        }
+    }
+}
+
+///Settings for the circuit breaker set with [`AsyncClient::with_circuit_breaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitBreakerConfig {
+    ///How many requests need to fail in a row for the breaker to open. `0` is treated as `1`.
+    pub failure_threshold: usize,
+    ///How long the breaker stays open before requests are let through again to check whether the server has recovered.
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+///Counts failed requests for an [`AsyncClient`] - see [`AsyncClient::with_circuit_breaker`].
+#[derive(Debug)]
+struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: Mutex<CircuitBreakerState>,
+}
+
+#[derive(Debug, Default)]
+struct CircuitBreakerState {
+    consecutive_failures: usize,
+    ///When the breaker last opened - it stays open until the cooldown has passed since then.
+    opened_at: Option<Instant>,
+    ///When the one request let through to check on the server after the cooldown (the probe) was sent, if it hasn't finished yet.
+    probe_sent_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::default(),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, CircuitBreakerState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    ///Whether a response with `status` means that the server is struggling - other errors (eg. a `404` or a `500` from a bad request) show that it is up.
+    fn is_failure_status(status: StatusCode) -> bool {
+        matches!(
+            status,
+            StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+        )
+    }
+
+    fn check(&self) -> Result<(), ClientError> {
+        let mut state = self.state();
+        let Some(opened_at) = state.opened_at else {
+            return Ok(());
+        };
+        if opened_at.elapsed() < self.config.cooldown {
+            return Err(ClientError::CircuitOpen);
+        }
+
+        //half-open, so only one probe at a time. If a probe never finishes (eg. its future was dropped), another is let through after a cooldown
+        match state.probe_sent_at {
+            Some(sent_at) if sent_at.elapsed() < self.config.cooldown => {
+                Err(ClientError::CircuitOpen)
+            }
+            _ => {
+                state.probe_sent_at = Some(Instant::now());
+                Ok(())
+            }
+        }
+    }
+
+    fn record(&self, succeeded: bool) {
+        let mut state = self.state();
+        if succeeded {
+            *state = CircuitBreakerState::default();
+            return;
+        }
+
+        //after the cooldown, the failure count is still over the threshold, so a failed probe opens the breaker again straight away
+        state.consecutive_failures += 1;
+        state.probe_sent_at = None;
+        if state.consecutive_failures >= self.config.failure_threshold.max(1) {
+            state.opened_at = Some(Instant::now());
+        }
+    }
+}
 
 ///A client for interacting with `sourisd` asynchronously.
 #[derive(Debug, Clone)]
@@ -24,20 +163,58 @@ pub struct AsyncClient {
     path: String,
     port: u32,
     client: Client,
+    max_response_bytes: Option<usize>,
+    auto_reconnect: bool,
+    upload_compression_threshold: Option<usize>,
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
 }
 
 impl AsyncClient {
-    ///Create a new asynchronous client using the provided path and port.
+    ///Create a new asynchronous client using the provided path and port, which uses the default [`RedirectPolicy`].
     ///
     /// ## Errors
     /// Can fail with:
     /// - [`ClientError::Reqwest`] if there
     pub async fn new(path: impl Display, port: u32) -> Result<Self, ClientError> {
+        Self::new_with_redirect_policy(path, port, RedirectPolicy::default()).await
+    }
+
+    ///Create a new asynchronous client using the provided path and port, which handles redirects using `redirect_policy`.
+    ///
+    /// ## Errors
+    /// Can fail with:
+    /// - [`ClientError::Reqwest`] if the client couldn't be built or there was an error contacting the server.
+    /// - [`ClientError::ServerNotHealthy`] if the healthcheck endpoint didn't return `200 OK`.
+    pub async fn new_with_redirect_policy(
+        path: impl Display,
+        port: u32,
+        redirect_policy: RedirectPolicy,
+    ) -> Result<Self, ClientError> {
         let path = path.to_string();
-        let client = Client::new();
+        let client = Client::builder().redirect(redirect_policy.into()).build()?;
 
-        match client
-            .get(&format!("http://{path}:{port}/healthcheck"))
+        let client = Self {
+            path,
+            port,
+            client,
+            max_response_bytes: None,
+            auto_reconnect: false,
+            upload_compression_threshold: None,
+            circuit_breaker: None,
+        };
+        client.reconnect().await?;
+        Ok(client)
+    }
+
+    ///Re-runs the healthcheck from [`AsyncClient::new`], to check that the server is still there - eg. after a network blip in a long-lived client. The same underlying connection pool is kept.
+    ///
+    /// ## Errors
+    /// - [`ClientError::Reqwest`] if there was an error contacting the server.
+    /// - [`ClientError::ServerNotHealthy`] if the healthcheck endpoint didn't return `200 OK`.
+    pub async fn reconnect(&self) -> Result<(), ClientError> {
+        match self
+            .client
+            .get(format!("http://{}:{}/healthcheck", self.path, self.port))
             .send()
             .await
         {
@@ -57,40 +234,104 @@ impl AsyncClient {
             }
         };
 
-        Ok(Self { path, port, client })
+        Ok(())
+    }
+
+    ///Sets whether [`AsyncClient::reconnect`] is called before every request, so that an unhealthy server is reported as [`ClientError::ServerNotHealthy`] rather than whatever error the request itself would give. This doubles the number of requests made, so it is off by default.
+    #[must_use]
+    pub fn with_auto_reconnect(mut self, auto_reconnect: bool) -> Self {
+        self.auto_reconnect = auto_reconnect;
+        self
+    }
+
+    ///Stops requests from being sent after `config.failure_threshold` requests in a row fail, so that a struggling server isn't hammered with more requests. Whilst the breaker is open, requests fail straight away with [`ClientError::CircuitOpen`]. Once `config.cooldown` has passed, the breaker is half-open - exactly one request is let through as a probe, with every other request still failing with [`ClientError::CircuitOpen`] until it finishes. If the probe succeeds the breaker closes, and if it fails the breaker opens for another cooldown.
+    ///
+    /// Only errors sending requests (eg. the connection being refused) and `502`, `503` and `504` responses count as failures, as any other response shows that the server is up. Clones of the client share the same breaker. By default, there is no circuit breaker.
+    #[must_use]
+    pub fn with_circuit_breaker(mut self, config: CircuitBreakerConfig) -> Self {
+        self.circuit_breaker = Some(Arc::new(CircuitBreaker::new(config)));
+        self
+    }
+
+    ///Checks the circuit breaker, and runs the healthcheck if [`AsyncClient::with_auto_reconnect`] is turned on.
+    async fn before_request(&self) -> Result<(), ClientError> {
+        if let Some(breaker) = &self.circuit_breaker {
+            breaker.check()?;
+        }
+        if self.auto_reconnect {
+            if let Err(e) = self.reconnect().await {
+                if let Some(breaker) = &self.circuit_breaker {
+                    breaker.record(matches!(
+                        e,
+                        ClientError::ServerNotHealthy(status) if !CircuitBreaker::is_failure_status(status)
+                    ));
+                }
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    ///Sends `req`, recording whether it failed with the circuit breaker.
+    async fn send(&self, req: RequestBuilder) -> Result<Response, ClientError> {
+        let rsp = req.send().await;
+        if let Some(breaker) = &self.circuit_breaker {
+            breaker.record(
+                rsp.as_ref()
+                    .is_ok_and(|rsp| !CircuitBreaker::is_failure_status(rsp.status())),
+            );
+        }
+        Ok(rsp?)
+    }
+
+    ///Limits the size of response bodies to `max_response_bytes`. Bodies are read in chunks and abandoned as soon as they go over the limit, so a misbehaving server can't make the client buffer an arbitrarily large body. By default, there is no limit.
+    ///
+    /// Going over the limit gives a [`ClientError::ResponseTooLarge`].
+    #[must_use]
+    pub fn with_max_response_bytes(mut self, max_response_bytes: usize) -> Self {
+        self.max_response_bytes = Some(max_response_bytes);
+        self
+    }
+
+    ///Compresses uploaded stores which are larger than `threshold` bytes once serialised, sending them with a `Content-Encoding: deflate` header for the server to decompress. Smaller stores are sent as-is, as compressing them isn't worth the overhead. By default, nothing is compressed.
+    ///
+    /// This applies to [`AsyncClient::add_db_with_contents`] and everything which uses it, as well as [`AsyncClient::add_db_with_raw_content`].
+    #[must_use]
+    pub fn with_upload_compression_threshold(mut self, threshold: usize) -> Self {
+        self.upload_compression_threshold = Some(threshold);
+        self
     }
 
     pub async fn get_all_dbs(&self) -> Result<Vec<String>, ClientError> {
-        let rsp = self
-            .client
-            .get(&format!(
-                "http://{}:{}/v1/get_all_db_names",
-                self.path, self.port
-            ))
-            .send()
-            .await?;
+        self.before_request().await?;
+        let req = self.client.get(format!(
+            "http://{}:{}/v1/get_all_db_names",
+            self.path, self.port
+        ));
+        let rsp = self.send(req).await?;
         rsp.error_for_status_to_client_error()?;
-        let body = rsp.bytes().await?;
-        Ok(serde_json::from_slice(body.as_ref())?)
+        let body = rsp.body(self.max_response_bytes).await?;
+        Ok(serde_json::from_slice(&body)?)
     }
 
     pub async fn create_new_db(
         &self,
         overwrite_existing: bool,
-        name: &str,
+        name: impl TryInto<DbName, Error: Into<ClientError>>,
     ) -> Result<bool, ClientError> {
-        let rsp = self
+        let name = to_db_name(name)?;
+        self.before_request().await?;
+        let req = self
             .client
-            .post(&format!("http://{}:{}/v1/add_db", self.path, self.port))
+            .post(format!("http://{}:{}/v1/add_db", self.path, self.port))
             .query(&[
                 (
                     "overwrite_existing",
                     if overwrite_existing { "true" } else { "false" },
                 ),
-                ("db_name", name),
-            ])
-            .send()
-            .await?;
+                ("db_name", name.as_str()),
+            ]);
+        let rsp = self.send(req).await?;
         Ok(match rsp.error_for_status_to_client_error()? {
             StatusCode::OK => false,
             StatusCode::CREATED => true,
@@ -98,29 +339,124 @@ impl AsyncClient {
         })
     }
 
-    pub async fn get_store(&self, db_name: &str) -> Result<Store, ClientError> {
-        let rsp = self
+    pub async fn get_store(
+        &self,
+        db_name: impl TryInto<DbName, Error: Into<ClientError>>,
+    ) -> Result<Store, ClientError> {
+        let bytes = self.get_store_raw(db_name).await?;
+        Ok(Store::deser(&bytes)?)
+    }
+
+    ///Gets the serialised bytes of a store without deserialising them, which is useful for caching or forwarding them on. They can be turned into a [`Store`] later using [`Store::deser`].
+    ///
+    /// ## Errors
+    /// - [`ClientError::Reqwest`] if there was an error sending the request or reading the body.
+    /// - [`ClientError::HttpErrorCode`] if the server didn't return a 2xx code, eg. if the store doesn't exist.
+    /// - [`ClientError::ResponseTooLarge`] if the body was larger than the limit set with [`AsyncClient::with_max_response_bytes`].
+    pub async fn get_store_raw(
+        &self,
+        db_name: impl TryInto<DbName, Error: Into<ClientError>>,
+    ) -> Result<Vec<u8>, ClientError> {
+        let db_name = to_db_name(db_name)?;
+        self.before_request().await?;
+        let req = self
             .client
-            .get(&format!("http://{}:{}/v1/get_db", self.path, self.port))
-            .query(&["db_name", db_name])
-            .send()
-            .await?;
+            .get(format!("http://{}:{}/v1/get_db", self.path, self.port))
+            .query(&[("db_name", db_name.as_str())]);
+        let rsp = self.send(req).await?;
+        rsp.error_for_status_to_client_error()?;
+        rsp.body(self.max_response_bytes).await
+    }
+
+    ///Gets the keys of a store without any of their values, sorted, which is useful for browsing a store without downloading all of it.
+    ///
+    /// ## Errors
+    /// - [`ClientError::Reqwest`] if there was an error sending the request or reading the body.
+    /// - [`ClientError::HttpErrorCode`] if the server didn't return a 2xx code, eg. if the store doesn't exist.
+    /// - [`ClientError::ResponseTooLarge`] if the body was larger than the limit set with [`AsyncClient::with_max_response_bytes`].
+    /// - [`ClientError::SerdeJson`] if the body wasn't a JSON array of strings.
+    pub async fn get_keys(
+        &self,
+        db_name: impl TryInto<DbName, Error: Into<ClientError>>,
+    ) -> Result<Vec<String>, ClientError> {
+        let db_name = to_db_name(db_name)?;
+        self.before_request().await?;
+        let req = self
+            .client
+            .get(format!("http://{}:{}/v1/get_keys", self.path, self.port))
+            .query(&[("db_name", db_name.as_str())]);
+        let rsp = self.send(req).await?;
         rsp.error_for_status_to_client_error()?;
-        let bytes = rsp.bytes().await?;
-        Ok(Store::deser(bytes.as_ref())?)
+        let body = rsp.body(self.max_response_bytes).await?;
+        Ok(serde_json::from_slice(&body)?)
     }
 
     pub async fn add_db_with_contents(
         &self,
         overwrite_existing: bool,
-        name: &str,
+        name: impl TryInto<DbName, Error: Into<ClientError>>,
         store: &Store,
     ) -> Result<bool, ClientError> {
+        let name = to_db_name(name)?;
         let store = store.ser()?;
+        self.add_db_with_raw_content(overwrite_existing, &name, &store)
+            .await
+    }
+
+    ///Uploads every store in `items` under its name using [`AsyncClient::add_db_with_contents`], with up to `concurrency` uploads in flight at once. Returns whether a new store was created for each item, in the same order as `items`.
+    ///
+    /// A `concurrency` of `0` is treated as `1`. To carry on past failures, see [`AsyncClient::add_dbs_collecting_errors`].
+    ///
+    /// ## Errors
+    /// The first error from any upload - as soon as one fails, no more uploads are started and any in flight are abandoned, so some stores may already have been uploaded.
+    pub async fn add_dbs(
+        &self,
+        overwrite_existing: bool,
+        items: &[(String, Store)],
+        concurrency: usize,
+    ) -> Result<Vec<bool>, ClientError> {
+        stream::iter(items)
+            .map(|(name, store)| self.add_db_with_contents(overwrite_existing, name, store))
+            .buffered(concurrency.max(1))
+            .try_collect()
+            .await
+    }
 
-        let rsp = self
+    ///Uploads every store in `items` like [`AsyncClient::add_dbs`], but carries on past failures and returns the result of each upload in the same order as `items`.
+    pub async fn add_dbs_collecting_errors(
+        &self,
+        overwrite_existing: bool,
+        items: &[(String, Store)],
+        concurrency: usize,
+    ) -> Vec<Result<bool, ClientError>> {
+        stream::iter(items)
+            .map(|(name, store)| self.add_db_with_contents(overwrite_existing, name, store))
+            .buffered(concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    ///Uploads an already-serialised store without re-encoding it, eg. bytes from [`AsyncClient::get_store_raw`] or a cache. Returns whether a new store was created.
+    ///
+    /// Only the magic bytes at the start are checked before sending - the server will reject anything else which is wrong.
+    ///
+    /// ## Errors
+    /// - [`ClientError::Store`] if `bytes` doesn't start with the magic bytes of a store.
+    /// - [`ClientError::Reqwest`] if there was an error sending the request.
+    /// - [`ClientError::HttpErrorCode`] if the server didn't return a 2xx code.
+    pub async fn add_db_with_raw_content(
+        &self,
+        overwrite_existing: bool,
+        name: impl TryInto<DbName, Error: Into<ClientError>>,
+        bytes: &[u8],
+    ) -> Result<bool, ClientError> {
+        let name = to_db_name(name)?;
+        check_store_magic_bytes(bytes)?;
+        self.before_request().await?;
+
+        let req = self
             .client
-            .put(&format!(
+            .put(format!(
                 "http://{}:{}/v1/add_db_with_content",
                 self.path, self.port
             ))
@@ -129,11 +465,15 @@ impl AsyncClient {
                     "overwrite_existing",
                     if overwrite_existing { "true" } else { "false" },
                 ),
-                ("db_name", name),
-            ])
-            .body(store)
-            .send()
-            .await?;
+                ("db_name", name.as_str()),
+            ]);
+        let req = match compress_upload(bytes, self.upload_compression_threshold) {
+            Some(compressed) => req
+                .header(CONTENT_ENCODING, UPLOAD_CONTENT_ENCODING)
+                .body(compressed),
+            None => req.body(bytes.to_vec()),
+        };
+        let rsp = self.send(req).await?;
 
         Ok(match rsp.error_for_status_to_client_error()? {
             StatusCode::OK => false,
@@ -144,18 +484,19 @@ impl AsyncClient {
 
     pub async fn add_entry_to_db(
         &self,
-        database_name: &str,
+        database_name: impl TryInto<DbName, Error: Into<ClientError>>,
         key: &str,
         value: &Value,
     ) -> Result<bool, ClientError> {
+        let database_name = to_db_name(database_name)?;
         let value = value.ser(None)?;
-        let rsp = self
+        self.before_request().await?;
+        let req = self
             .client
-            .put(&format!("http://{}:{}/v1/add_kv", self.path, self.port))
-            .query(&[("db_name", database_name), ("key", key)])
-            .body(value)
-            .send()
-            .await?;
+            .put(format!("http://{}:{}/v1/add_kv", self.path, self.port))
+            .query(&[("db_name", database_name.as_str()), ("key", key)])
+            .body(value);
+        let rsp = self.send(req).await?;
 
         Ok(match rsp.error_for_status_to_client_error()? {
             StatusCode::OK => false,
@@ -166,31 +507,107 @@ impl AsyncClient {
 
     pub async fn remove_entry_from_db(
         &self,
-        database_name: &str,
+        database_name: impl TryInto<DbName, Error: Into<ClientError>>,
         key: &str,
     ) -> Result<(), ClientError> {
-        self.client
-            .post(&format!("http://{}:{}/v1/rm_kv", self.path, self.port))
-            .query(&[("db_name", database_name), ("key", key)])
-            .send()
-            .await?
-            .error_for_status_to_client_error()?;
+        let database_name = to_db_name(database_name)?;
+        self.before_request().await?;
+        let req = self
+            .client
+            .post(format!("http://{}:{}/v1/rm_kv", self.path, self.port))
+            .query(&[("db_name", database_name.as_str()), ("key", key)]);
+        self.send(req).await?.error_for_status_to_client_error()?;
         Ok(())
     }
 
-    pub async fn remove_db(&self, database_name: &str) -> Result<(), ClientError> {
-        self.client
-            .post(&format!("http://{}:{}/v1/rm_db", self.path, self.port))
-            .query(&[("db_name", database_name)])
-            .send()
-            .await?
-            .error_for_status_to_client_error()?;
+    pub async fn remove_db(
+        &self,
+        database_name: impl TryInto<DbName, Error: Into<ClientError>>,
+    ) -> Result<(), ClientError> {
+        let database_name = to_db_name(database_name)?;
+        self.before_request().await?;
+        let req = self
+            .client
+            .post(format!("http://{}:{}/v1/rm_db", self.path, self.port))
+            .query(&[("db_name", database_name.as_str())]);
+        self.send(req).await?.error_for_status_to_client_error()?;
+        Ok(())
+    }
+
+    ///Renames the database called `old` to `new` on the server, without downloading it.
+    ///
+    /// ## Errors
+    /// - [`ClientError::DatabaseAlreadyExists`] if a database called `new` already exists and `overwrite_existing` is `false`.
+    /// - [`ClientError::Reqwest`] if there was an error sending the request.
+    /// - [`ClientError::HttpErrorCode`] if the server didn't return a 2xx code, eg. `404 Not Found` if there is no database called `old`.
+    pub async fn rename_db(
+        &self,
+        old: impl TryInto<DbName, Error: Into<ClientError>>,
+        new: impl TryInto<DbName, Error: Into<ClientError>>,
+        overwrite_existing: bool,
+    ) -> Result<(), ClientError> {
+        let old = to_db_name(old)?;
+        let new = to_db_name(new)?;
+        self.before_request().await?;
+        let req = self
+            .client
+            .post(format!("http://{}:{}/v1/rename_db", self.path, self.port))
+            .query(&[
+                ("old_name", old.as_str()),
+                ("new_name", new.as_str()),
+                (
+                    "overwrite_existing",
+                    if overwrite_existing { "true" } else { "false" },
+                ),
+            ]);
+        let rsp = self.send(req).await?;
+
+        if rsp.status() == StatusCode::CONFLICT {
+            return Err(ClientError::DatabaseAlreadyExists(new.to_string()));
+        }
+        rsp.error_for_status_to_client_error()?;
+        Ok(())
+    }
+
+    ///Copies the database called `src` to `dst` on the server, without downloading and re-uploading it.
+    ///
+    /// ## Errors
+    /// - [`ClientError::DatabaseAlreadyExists`] if a database called `dst` already exists and `overwrite_existing` is `false`.
+    /// - [`ClientError::Reqwest`] if there was an error sending the request.
+    /// - [`ClientError::HttpErrorCode`] if the server didn't return a 2xx code, eg. `404 Not Found` if there is no database called `src`.
+    pub async fn copy_db(
+        &self,
+        src: impl TryInto<DbName, Error: Into<ClientError>>,
+        dst: impl TryInto<DbName, Error: Into<ClientError>>,
+        overwrite_existing: bool,
+    ) -> Result<(), ClientError> {
+        let src = to_db_name(src)?;
+        let dst = to_db_name(dst)?;
+        self.before_request().await?;
+        let req = self
+            .client
+            .post(format!("http://{}:{}/v1/copy_db", self.path, self.port))
+            .query(&[
+                ("src_name", src.as_str()),
+                ("dst_name", dst.as_str()),
+                (
+                    "overwrite_existing",
+                    if overwrite_existing { "true" } else { "false" },
+                ),
+            ]);
+        let rsp = self.send(req).await?;
+
+        if rsp.status() == StatusCode::CONFLICT {
+            return Err(ClientError::DatabaseAlreadyExists(dst.to_string()));
+        }
+        rsp.error_for_status_to_client_error()?;
         Ok(())
     }
 }
 
 trait ResponseExt {
     fn error_for_status_to_client_error(&self) -> Result<StatusCode, ClientError>;
+    async fn body(self, max_bytes: Option<usize>) -> Result<Vec<u8>, ClientError>;
 }
 
 impl ResponseExt for Response {
@@ -202,4 +619,673 @@ impl ResponseExt for Response {
             Err(ClientError::HttpErrorCode(status))
         }
     }
+
+    async fn body(mut self, max_bytes: Option<usize>) -> Result<Vec<u8>, ClientError> {
+        let Some(max_bytes) = max_bytes else {
+            return Ok(self.bytes().await?.to_vec());
+        };
+
+        //bail early if the server tells us up front, but still count as the header could be missing or wrong
+        if self
+            .content_length()
+            .is_some_and(|len| usize::try_from(len).map_or(true, |len| len > max_bytes))
+        {
+            return Err(ClientError::ResponseTooLarge(max_bytes));
+        }
+
+        let mut body = vec![];
+        while let Some(chunk) = self.chunk().await? {
+            if body.len() + chunk.len() > max_bytes {
+                return Err(ClientError::ResponseTooLarge(max_bytes));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{convert::Infallible, time::Duration};
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicBool, AtomicU16, AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+    };
+
+    use axum::{
+        body::{Body, Bytes},
+        extract::Query,
+        response::Redirect,
+        routing::{get, post, put},
+        Router,
+    };
+    use futures_util::stream;
+    use http::{
+        header::{CONTENT_ENCODING, HOST},
+        HeaderMap, StatusCode,
+    };
+    use tokio::net::TcpListener;
+
+    use super::{AsyncClient, CircuitBreakerConfig, RedirectPolicy};
+    use crate::{
+        client::ClientError,
+        store::{Store, StoreSerError},
+        values::Value,
+    };
+
+    ///Starts a server on a random port with `router` and a healthcheck route, returning the port.
+    async fn mock_server(router: Router) -> u32 {
+        serve(router.route("/healthcheck", get(|| async { StatusCode::OK }))).await
+    }
+
+    ///Starts a server on a random port with just `router`, returning the port.
+    async fn serve(router: Router) -> u32 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        u32::from(port)
+    }
+
+    #[tokio::test]
+    async fn test_get_store_raw() {
+        let mut store = Store::default();
+        store.insert("a".into(), Value::String("hello".into()));
+        store.insert("b".into(), Value::Boolean(true));
+        let bytes = store.ser().unwrap();
+
+        let served_bytes = bytes.clone();
+        let router = Router::new().route(
+            "/v1/get_db",
+            get(move |Query(query): Query<HashMap<String, String>>| {
+                let served_bytes = served_bytes.clone();
+                async move {
+                    assert_eq!(query.get("db_name").map(String::as_str), Some("test"));
+                    served_bytes
+                }
+            }),
+        );
+        let client = AsyncClient::new("127.0.0.1", mock_server(router).await)
+            .await
+            .unwrap();
+
+        let raw = client.get_store_raw("test").await.unwrap();
+        assert_eq!(raw, bytes);
+        assert_eq!(Store::deser(&raw).unwrap(), store);
+        assert_eq!(client.get_store("test").await.unwrap(), store);
+    }
+
+    #[tokio::test]
+    async fn test_max_response_bytes() {
+        const LEN: usize = 1024 * 1024;
+
+        let router = Router::new()
+            .route("/v1/get_db", get(|| async { vec![0xAB_u8; LEN] }))
+            //streamed without a content length, so the client has to count
+            .route(
+                "/v1/get_all_db_names",
+                get(|| async {
+                    let chunks = (0..LEN / 1024).map(|_| Ok::<_, Infallible>(vec![b' '; 1024]));
+                    Body::from_stream(stream::iter(chunks))
+                }),
+            );
+        let port = mock_server(router).await;
+
+        let unlimited = AsyncClient::new("127.0.0.1", port).await.unwrap();
+        assert_eq!(unlimited.get_store_raw("test").await.unwrap().len(), LEN);
+
+        let exact = unlimited.clone().with_max_response_bytes(LEN);
+        assert_eq!(exact.get_store_raw("test").await.unwrap().len(), LEN);
+
+        let limited = unlimited.with_max_response_bytes(1024);
+        assert!(matches!(
+            limited.get_store_raw("test").await,
+            Err(ClientError::ResponseTooLarge(1024))
+        ));
+        assert!(matches!(
+            limited.get_store("test").await,
+            Err(ClientError::ResponseTooLarge(1024))
+        ));
+        assert!(matches!(
+            limited.get_all_dbs().await,
+            Err(ClientError::ResponseTooLarge(1024))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reconnect() {
+        let healthy = Arc::new(AtomicBool::new(true));
+        let healthchecks = Arc::new(AtomicUsize::new(0));
+
+        let router = Router::new()
+            .route(
+                "/healthcheck",
+                get({
+                    let healthy = healthy.clone();
+                    let healthchecks = healthchecks.clone();
+                    move || async move {
+                        healthchecks.fetch_add(1, Ordering::SeqCst);
+                        if healthy.load(Ordering::SeqCst) {
+                            StatusCode::OK
+                        } else {
+                            StatusCode::SERVICE_UNAVAILABLE
+                        }
+                    }
+                }),
+            )
+            .route("/v1/get_all_db_names", get(|| async { r#"["a"]"# }));
+        let port = serve(router).await;
+
+        let client = AsyncClient::new("127.0.0.1", port).await.unwrap();
+        assert_eq!(healthchecks.load(Ordering::SeqCst), 1);
+
+        client.reconnect().await.unwrap();
+        assert_eq!(healthchecks.load(Ordering::SeqCst), 2);
+
+        healthy.store(false, Ordering::SeqCst);
+        assert!(matches!(
+            client.reconnect().await,
+            Err(ClientError::ServerNotHealthy(
+                StatusCode::SERVICE_UNAVAILABLE
+            ))
+        ));
+        //without auto-reconnect, requests don't check the healthcheck
+        assert_eq!(client.get_all_dbs().await.unwrap(), vec!["a".to_string()]);
+        assert_eq!(healthchecks.load(Ordering::SeqCst), 3);
+
+        let client = client.with_auto_reconnect(true);
+        assert!(matches!(
+            client.get_all_dbs().await,
+            Err(ClientError::ServerNotHealthy(
+                StatusCode::SERVICE_UNAVAILABLE
+            ))
+        ));
+        assert_eq!(healthchecks.load(Ordering::SeqCst), 4);
+
+        healthy.store(true, Ordering::SeqCst);
+        assert_eq!(client.get_all_dbs().await.unwrap(), vec!["a".to_string()]);
+        assert_eq!(healthchecks.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn test_add_db_with_raw_content() {
+        let mut store = Store::default();
+        store.insert("a".into(), Value::String("hello".into()));
+        let bytes = store.ser().unwrap();
+
+        let expected_bytes = bytes.clone();
+        let router = Router::new().route(
+            "/v1/add_db_with_content",
+            put(
+                move |Query(query): Query<HashMap<String, String>>, body: Bytes| {
+                    let expected_bytes = expected_bytes.clone();
+                    async move {
+                        assert_eq!(query.get("db_name").map(String::as_str), Some("test"));
+                        assert_eq!(
+                            query.get("overwrite_existing").map(String::as_str),
+                            Some("false")
+                        );
+                        assert_eq!(body.as_ref(), expected_bytes.as_slice());
+                        StatusCode::CREATED
+                    }
+                },
+            ),
+        );
+        let client = AsyncClient::new("127.0.0.1", mock_server(router).await)
+            .await
+            .unwrap();
+
+        assert!(client
+            .add_db_with_raw_content(false, "test", &bytes)
+            .await
+            .unwrap());
+        assert!(matches!(
+            client
+                .add_db_with_raw_content(false, "test", b"NOTASTORE")
+                .await,
+            Err(ClientError::Store(StoreSerError::ExpectedMagicBytes))
+        ));
+        assert!(matches!(
+            client
+                .add_db_with_raw_content(false, "test", b"SOURIS")
+                .await,
+            Err(ClientError::Store(StoreSerError::NotEnoughBytes))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker() {
+        const COOLDOWN: Duration = Duration::from_millis(200);
+
+        //0 is healthy, anything else is returned as the status code
+        let status = Arc::new(AtomicU16::new(503));
+        let hits = Arc::new(AtomicUsize::new(0));
+        let (route_status, route_hits) = (status.clone(), hits.clone());
+        let router = Router::new().route(
+            "/v1/get_all_db_names",
+            get(move || {
+                let (status, hits) = (route_status.clone(), route_hits.clone());
+                async move {
+                    hits.fetch_add(1, Ordering::SeqCst);
+                    match status.load(Ordering::SeqCst) {
+                        0 => {
+                            //slow enough for other requests to arrive whilst this is the probe
+                            tokio::time::sleep(COOLDOWN / 4).await;
+                            (StatusCode::OK, "[]")
+                        }
+                        code => (StatusCode::from_u16(code).unwrap(), ""),
+                    }
+                }
+            }),
+        );
+        let client = AsyncClient::new("127.0.0.1", mock_server(router).await)
+            .await
+            .unwrap()
+            .with_circuit_breaker(CircuitBreakerConfig {
+                failure_threshold: 3,
+                cooldown: COOLDOWN,
+            });
+
+        //other errors show that the server is up, so don't count
+        status.store(500, Ordering::SeqCst);
+        for _ in 0..5 {
+            assert!(matches!(
+                client.get_all_dbs().await,
+                Err(ClientError::HttpErrorCode(
+                    StatusCode::INTERNAL_SERVER_ERROR
+                ))
+            ));
+        }
+
+        for code in [502, 503, 504] {
+            status.store(code, Ordering::SeqCst);
+            assert!(matches!(
+                client.get_all_dbs().await,
+                Err(ClientError::HttpErrorCode(s)) if s.as_u16() == code
+            ));
+        }
+        //tripped, so nothing more reaches the server - including from clones
+        assert!(matches!(
+            client.get_all_dbs().await,
+            Err(ClientError::CircuitOpen)
+        ));
+        assert!(matches!(
+            client.clone().get_all_dbs().await,
+            Err(ClientError::CircuitOpen)
+        ));
+        assert_eq!(hits.load(Ordering::SeqCst), 8);
+
+        //a failed probe after the cooldown opens it again straight away
+        tokio::time::sleep(COOLDOWN).await;
+        assert!(matches!(
+            client.get_all_dbs().await,
+            Err(ClientError::HttpErrorCode(_))
+        ));
+        assert!(matches!(
+            client.get_all_dbs().await,
+            Err(ClientError::CircuitOpen)
+        ));
+        assert_eq!(hits.load(Ordering::SeqCst), 9);
+
+        //only the probe is let through whilst it is in flight, and it closes the breaker
+        status.store(0, Ordering::SeqCst);
+        tokio::time::sleep(COOLDOWN).await;
+        let (probe, during_probe) = tokio::join!(client.get_all_dbs(), async {
+            tokio::time::sleep(COOLDOWN / 20).await;
+            client.get_all_dbs().await
+        });
+        assert_eq!(probe.unwrap(), Vec::<String>::new());
+        assert!(matches!(during_probe, Err(ClientError::CircuitOpen)));
+        assert_eq!(client.get_all_dbs().await.unwrap(), Vec::<String>::new());
+        assert_eq!(hits.load(Ordering::SeqCst), 11);
+
+        //the count starts again from zero
+        status.store(503, Ordering::SeqCst);
+        for _ in 0..2 {
+            assert!(matches!(
+                client.get_all_dbs().await,
+                Err(ClientError::HttpErrorCode(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_keys() {
+        let router = Router::new().route(
+            "/v1/get_keys",
+            get(|Query(query): Query<HashMap<String, String>>| async move {
+                match query.get("db_name").map(String::as_str) {
+                    Some("test") => (StatusCode::OK, r#"["a","b","c"]"#),
+                    _ => (StatusCode::NOT_FOUND, "Could not find database with name"),
+                }
+            }),
+        );
+        let client = AsyncClient::new("127.0.0.1", mock_server(router).await)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            client.get_keys("test").await.unwrap(),
+            vec!["a".to_string(), "b".to_string(), "c".to_string()]
+        );
+        assert!(matches!(
+            client.get_keys("missing").await,
+            Err(ClientError::HttpErrorCode(StatusCode::NOT_FOUND))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_upload_compression_threshold() {
+        let received = Arc::new(Mutex::new(vec![]));
+        let recorder = received.clone();
+        let router = Router::new().route(
+            "/v1/add_db_with_content",
+            put(move |headers: HeaderMap, body: Bytes| {
+                let recorder = recorder.clone();
+                async move {
+                    let encoding = headers
+                        .get(CONTENT_ENCODING)
+                        .map(|v| v.to_str().unwrap().to_string());
+                    recorder.lock().unwrap().push((encoding, body.to_vec()));
+                    StatusCode::CREATED
+                }
+            }),
+        );
+        let client = AsyncClient::new("127.0.0.1", mock_server(router).await)
+            .await
+            .unwrap()
+            .with_upload_compression_threshold(1_024);
+
+        let small = Store::from_range(0..5);
+        let large = Store::from_range(0..10_000);
+        assert!(small.ser().unwrap().len() <= 1_024);
+        assert!(large.ser().unwrap().len() > 1_024);
+
+        client
+            .add_db_with_contents(false, "small", &small)
+            .await
+            .unwrap();
+        client
+            .add_db_with_contents(false, "large", &large)
+            .await
+            .unwrap();
+
+        let received = received.lock().unwrap();
+        let (small_encoding, small_body) = &received[0];
+        assert_eq!(small_encoding, &None);
+        assert_eq!(Store::deser(small_body).unwrap(), small);
+
+        let (large_encoding, large_body) = &received[1];
+        assert_eq!(large_encoding.as_deref(), Some("deflate"));
+        assert!(Store::deser(large_body).is_err());
+        let decompressed = miniz_oxide::inflate::decompress_to_vec_zlib(large_body).unwrap();
+        assert_eq!(Store::deser(&decompressed).unwrap(), large);
+    }
+
+    #[tokio::test]
+    async fn test_add_dbs() {
+        let received = Arc::new(Mutex::new(vec![]));
+        let recorder = received.clone();
+        let router = Router::new().route(
+            "/v1/add_db_with_content",
+            put(
+                move |Query(query): Query<HashMap<String, String>>, body: Bytes| {
+                    let recorder = recorder.clone();
+                    async move {
+                        let store = Store::deser(body.as_ref()).unwrap();
+                        recorder
+                            .lock()
+                            .unwrap()
+                            .push((query["db_name"].clone(), store));
+                        if query["db_name"] == "existing" {
+                            StatusCode::OK
+                        } else {
+                            StatusCode::CREATED
+                        }
+                    }
+                },
+            ),
+        );
+        let client = AsyncClient::new("127.0.0.1", mock_server(router).await)
+            .await
+            .unwrap();
+
+        let items: Vec<(String, Store)> = ["a", "b", "existing", "c", "d"]
+            .into_iter()
+            .map(|name| {
+                let mut store = Store::default();
+                store.insert("name".into(), Value::String(name.into()));
+                (name.to_string(), store)
+            })
+            .collect();
+
+        assert_eq!(
+            client.add_dbs(true, &items, 2).await.unwrap(),
+            vec![true, true, false, true, true]
+        );
+        let mut uploaded = core::mem::take(&mut *received.lock().unwrap());
+        uploaded.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        let mut expected = items.clone();
+        expected.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        assert_eq!(uploaded, expected);
+
+        let mut items = items;
+        items[1].0 = "not a name".into();
+        assert!(matches!(
+            client.add_dbs(true, &items, 0).await,
+            Err(ClientError::InvalidDbName(name)) if name == "not a name"
+        ));
+
+        let results = client.add_dbs_collecting_errors(true, &items, 3).await;
+        assert_eq!(results.len(), 5);
+        assert!(matches!(results[1], Err(ClientError::InvalidDbName(_))));
+        assert!(matches!(results[2], Ok(false)));
+        assert!(results.iter().enumerate().all(|(i, r)| i == 1 || r.is_ok()));
+    }
+
+    #[tokio::test]
+    async fn test_redirect_policy() {
+        let names = || async { r#"["a"]"# };
+
+        let same_host = Router::new()
+            .route(
+                "/v1/get_all_db_names",
+                get(|| async { Redirect::temporary("/names") }),
+            )
+            .route("/names", get(names));
+        let same_host = mock_server(same_host).await;
+
+        //the server is listening on 127.0.0.1, so redirecting to localhost is a different host which still works
+        let cross_host = Router::new()
+            .route(
+                "/v1/get_all_db_names",
+                get(|headers: HeaderMap| async move {
+                    let host = headers[HOST].to_str().unwrap();
+                    let port = host.rsplit_once(':').unwrap().1;
+                    Redirect::temporary(&format!("http://localhost:{port}/names"))
+                }),
+            )
+            .route("/names", get(names));
+        let cross_host = mock_server(cross_host).await;
+
+        //same host, but a different port - so a different server
+        let cross_port = Router::new().route(
+            "/v1/get_all_db_names",
+            get(move || async move {
+                Redirect::temporary(&format!("http://127.0.0.1:{same_host}/names"))
+            }),
+        );
+        let cross_port = mock_server(cross_port).await;
+
+        let client =
+            |port, policy| AsyncClient::new_with_redirect_policy("127.0.0.1", port, policy);
+        let expected = vec!["a".to_string()];
+
+        let default = client(same_host, RedirectPolicy::default()).await.unwrap();
+        assert_eq!(default.get_all_dbs().await.unwrap(), expected);
+        for port in [cross_host, cross_port] {
+            let default = client(port, RedirectPolicy::default()).await.unwrap();
+            assert!(matches!(
+                default.get_all_dbs().await,
+                Err(ClientError::HttpErrorCode(StatusCode::TEMPORARY_REDIRECT))
+            ));
+        }
+
+        let none = client(same_host, RedirectPolicy::None).await.unwrap();
+        assert!(matches!(
+            none.get_all_dbs().await,
+            Err(ClientError::HttpErrorCode(StatusCode::TEMPORARY_REDIRECT))
+        ));
+
+        for port in [cross_host, cross_port] {
+            let limited = client(port, RedirectPolicy::Limited(1)).await.unwrap();
+            assert_eq!(limited.get_all_dbs().await.unwrap(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_db_name_fails_before_sending() {
+        //no routes other than the healthcheck, so any request which is sent would 404
+        let client = AsyncClient::new("127.0.0.1", mock_server(Router::new()).await)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            client.get_store("not/a name").await,
+            Err(ClientError::InvalidDbName(name)) if name == "not/a name"
+        ));
+        assert!(matches!(
+            client.remove_db("meta").await,
+            Err(ClientError::InvalidDbName(_))
+        ));
+        assert!(matches!(
+            client.get_store("valid_name").await,
+            Err(ClientError::HttpErrorCode(StatusCode::NOT_FOUND))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rename_db() {
+        let received = Arc::new(Mutex::new(vec![]));
+        let recorder = received.clone();
+        let router = Router::new().route(
+            "/v1/rename_db",
+            post(move |Query(query): Query<HashMap<String, String>>| {
+                let recorder = recorder.clone();
+                async move {
+                    let conflict =
+                        query["new_name"] == "existing" && query["overwrite_existing"] == "false";
+                    recorder.lock().unwrap().push(query);
+                    if conflict {
+                        StatusCode::CONFLICT
+                    } else {
+                        StatusCode::OK
+                    }
+                }
+            }),
+        );
+        let client = AsyncClient::new("127.0.0.1", mock_server(router).await)
+            .await
+            .unwrap();
+
+        client.rename_db("old", "new", false).await.unwrap();
+        client.rename_db("old", "existing", true).await.unwrap();
+        assert!(matches!(
+            client.rename_db("old", "existing", false).await,
+            Err(ClientError::DatabaseAlreadyExists(name)) if name == "existing"
+        ));
+        assert!(matches!(
+            client.rename_db("old", "meta", true).await,
+            Err(ClientError::InvalidDbName(_))
+        ));
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 3);
+        for (query, (new, overwrite)) in received.iter().zip([
+            ("new", "false"),
+            ("existing", "true"),
+            ("existing", "false"),
+        ]) {
+            assert_eq!(query["old_name"], "old");
+            assert_eq!(query["new_name"], new);
+            assert_eq!(query["overwrite_existing"], overwrite);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_copy_db() {
+        let received = Arc::new(Mutex::new(vec![]));
+        let recorder = received.clone();
+        let router = Router::new().route(
+            "/v1/copy_db",
+            post(move |Query(query): Query<HashMap<String, String>>| {
+                let recorder = recorder.clone();
+                async move {
+                    let status = match (
+                        query["dst_name"].as_str(),
+                        query["overwrite_existing"].as_str(),
+                    ) {
+                        ("existing", "false") => StatusCode::CONFLICT,
+                        ("existing", _) => StatusCode::OK,
+                        _ => StatusCode::CREATED,
+                    };
+                    recorder.lock().unwrap().push(query);
+                    status
+                }
+            }),
+        );
+        let client = AsyncClient::new("127.0.0.1", mock_server(router).await)
+            .await
+            .unwrap();
+
+        client.copy_db("src", "dst", false).await.unwrap();
+        client.copy_db("src", "existing", true).await.unwrap();
+        assert!(matches!(
+            client.copy_db("src", "existing", false).await,
+            Err(ClientError::DatabaseAlreadyExists(name)) if name == "existing"
+        ));
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 3);
+        for (query, (dst, overwrite)) in received.iter().zip([
+            ("dst", "false"),
+            ("existing", "true"),
+            ("existing", "false"),
+        ]) {
+            assert_eq!(query["src_name"], "src");
+            assert_eq!(query["dst_name"], dst);
+            assert_eq!(query["overwrite_existing"], overwrite);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keys_are_percent_encoded() {
+        const KEY: &str = "a&b=c d?e#f+g%20h/ü🦀";
+
+        let received = Arc::new(Mutex::new(vec![]));
+        let record = |received: Arc<Mutex<Vec<String>>>| {
+            move |Query(query): Query<HashMap<String, String>>| async move {
+                received.lock().unwrap().push(query["key"].clone());
+                StatusCode::CREATED
+            }
+        };
+        let router = Router::new()
+            .route("/v1/add_kv", put(record(received.clone())))
+            .route("/v1/rm_kv", post(record(received.clone())));
+        let client = AsyncClient::new("127.0.0.1", mock_server(router).await)
+            .await
+            .unwrap();
+
+        client
+            .add_entry_to_db("test", KEY, &Value::Null(()))
+            .await
+            .unwrap();
+        client.remove_entry_from_db("test", KEY).await.unwrap();
+
+        assert_eq!(*received.lock().unwrap(), vec![KEY, KEY]);
+    }
 }
@@ -3,13 +3,23 @@ use core::fmt::Display;
 use http::StatusCode;
 use ureq::{Agent, Response};
 
-use crate::{client::ClientError, store::Store, values::Value};
+use crate::{
+    client::{
+        check_store_magic_bytes, compress_upload, to_db_name, ClientError, DbName,
+        UPLOAD_CONTENT_ENCODING,
+    },
+    store::Store,
+    values::Value,
+};
 
 #[derive(Debug, Clone)]
 pub struct SyncClient {
     path: String, //path is never changed, so just use arc<str> for cloning benefits
     port: u32,
     agent: Agent, //also internally arc-ed, so easy to clone
+    max_response_bytes: Option<usize>,
+    auto_reconnect: bool,
+    upload_compression_threshold: Option<usize>,
 }
 
 impl SyncClient {
@@ -17,18 +27,67 @@ impl SyncClient {
         let path = path.to_string();
         let agent = Agent::new();
 
-        let rsp = agent
-            .get(&format!("http://{path}:{port}/healthcheck"))
+        let client = Self {
+            path,
+            port,
+            agent,
+            max_response_bytes: None,
+            auto_reconnect: false,
+            upload_compression_threshold: None,
+        };
+        client.reconnect()?;
+        Ok(client)
+    }
+
+    ///Re-runs the healthcheck from [`SyncClient::new`], to check that the server is still there - eg. after a network blip in a long-lived client.
+    ///
+    /// ## Errors
+    /// - [`ClientError::Ureq`] if there was an error contacting the server.
+    /// - [`ClientError::ServerNotHealthy`] if the healthcheck endpoint didn't return `200 OK`.
+    pub fn reconnect(&self) -> Result<(), ClientError> {
+        let rsp = self
+            .agent
+            .get(&format!("http://{}:{}/healthcheck", self.path, self.port))
             .call()?;
         let status = rsp.status_code()?;
         if status != StatusCode::OK {
             return Err(ClientError::ServerNotHealthy(status));
         }
+        Ok(())
+    }
+
+    ///Sets whether [`SyncClient::reconnect`] is called before every request - see [`crate::client::AsyncClient::with_auto_reconnect`].
+    #[must_use]
+    pub fn with_auto_reconnect(mut self, auto_reconnect: bool) -> Self {
+        self.auto_reconnect = auto_reconnect;
+        self
+    }
+
+    fn before_request(&self) -> Result<(), ClientError> {
+        if self.auto_reconnect {
+            self.reconnect()?;
+        }
+        Ok(())
+    }
+
+    ///Limits the size of response bodies to `max_response_bytes`. Bodies are read in chunks and abandoned as soon as they go over the limit, so a misbehaving server can't make the client buffer an arbitrarily large body. By default, there is no limit.
+    ///
+    /// Going over the limit gives a [`ClientError::ResponseTooLarge`].
+    #[must_use]
+    pub fn with_max_response_bytes(mut self, max_response_bytes: usize) -> Self {
+        self.max_response_bytes = Some(max_response_bytes);
+        self
+    }
 
-        Ok(Self { path, port, agent })
+    ///Compresses uploaded stores which are larger than `threshold` bytes once serialised - see [`crate::client::AsyncClient::with_upload_compression_threshold`].
+    #[must_use]
+    pub fn with_upload_compression_threshold(mut self, threshold: usize) -> Self {
+        self.upload_compression_threshold = Some(threshold);
+        self
     }
 
     pub fn get_all_dbs(&self) -> Result<Vec<String>, ClientError> {
+        self.before_request()?;
         let rsp = self
             .agent
             .get(&format!(
@@ -37,11 +96,17 @@ impl SyncClient {
             ))
             .call()?;
 
-        let body = rsp.body()?;
+        let body = rsp.body(self.max_response_bytes)?;
         Ok(serde_json::from_slice(&body)?)
     }
 
-    pub fn create_new_db(&self, overwrite_existing: bool, name: &str) -> Result<bool, ClientError> {
+    pub fn create_new_db(
+        &self,
+        overwrite_existing: bool,
+        name: impl TryInto<DbName, Error: Into<ClientError>>,
+    ) -> Result<bool, ClientError> {
+        let name = to_db_name(name)?;
+        self.before_request()?;
         let rsp = self
             .agent
             .post(&format!("http://{}:{}/v1/add_db", self.path, self.port))
@@ -49,7 +114,7 @@ impl SyncClient {
                 "overwrite_existing",
                 if overwrite_existing { "true" } else { "false" },
             )
-            .query("db_name", name)
+            .query("db_name", name.as_str())
             .call()?;
 
         Ok(match rsp.status_code()? {
@@ -59,26 +124,89 @@ impl SyncClient {
         })
     }
 
-    pub fn get_store(&self, db_name: &str) -> Result<Store, ClientError> {
+    pub fn get_store(
+        &self,
+        db_name: impl TryInto<DbName, Error: Into<ClientError>>,
+    ) -> Result<Store, ClientError> {
+        let body = self.get_store_raw(db_name)?;
+        println!("Received body from client");
+        Ok(Store::deser(&body)?)
+    }
+
+    ///Gets the serialised bytes of a store without deserialising them, which is useful for caching or forwarding them on. They can be turned into a [`Store`] later using [`Store::deser`].
+    ///
+    /// ## Errors
+    /// - [`ClientError::Ureq`] if there was an error sending the request.
+    /// - [`ClientError::HttpErrorCode`] if the server didn't return a 2xx code, eg. if the store doesn't exist.
+    /// - [`ClientError::IO`] if there was an error reading the body.
+    /// - [`ClientError::ResponseTooLarge`] if the body was larger than the limit set with [`SyncClient::with_max_response_bytes`].
+    pub fn get_store_raw(
+        &self,
+        db_name: impl TryInto<DbName, Error: Into<ClientError>>,
+    ) -> Result<Vec<u8>, ClientError> {
+        let db_name = to_db_name(db_name)?;
+        self.before_request()?;
         let rsp = self
             .agent
             .get(&format!("http://{}:{}/v1/get_db", self.path, self.port))
-            .query("db_name", db_name)
+            .query("db_name", db_name.as_str())
             .call()?;
-        let body = rsp.body()?;
-        println!("Received body from client");
-        Ok(Store::deser(&body)?)
+        rsp.body(self.max_response_bytes)
+    }
+
+    ///Gets the keys of a store without any of their values, sorted - see [`crate::client::AsyncClient::get_keys`].
+    ///
+    /// ## Errors
+    /// - [`ClientError::Ureq`] if there was an error sending the request.
+    /// - [`ClientError::HttpErrorCode`] if the server didn't return a 2xx code, eg. if the store doesn't exist.
+    /// - [`ClientError::IO`] if there was an error reading the body.
+    /// - [`ClientError::ResponseTooLarge`] if the body was larger than the limit set with [`SyncClient::with_max_response_bytes`].
+    /// - [`ClientError::SerdeJson`] if the body wasn't a JSON array of strings.
+    pub fn get_keys(
+        &self,
+        db_name: impl TryInto<DbName, Error: Into<ClientError>>,
+    ) -> Result<Vec<String>, ClientError> {
+        let db_name = to_db_name(db_name)?;
+        self.before_request()?;
+        let rsp = self
+            .agent
+            .get(&format!("http://{}:{}/v1/get_keys", self.path, self.port))
+            .query("db_name", db_name.as_str())
+            .call()?;
+        let body = rsp.body(self.max_response_bytes)?;
+        Ok(serde_json::from_slice(&body)?)
     }
 
     pub fn add_db_with_contents(
         &self,
         overwrite_existing: bool,
-        name: &str,
+        name: impl TryInto<DbName, Error: Into<ClientError>>,
         store: &Store,
     ) -> Result<bool, ClientError> {
+        let name = to_db_name(name)?;
         let store = store.ser()?;
+        self.add_db_with_raw_content(overwrite_existing, &name, &store)
+    }
 
-        let rsp = self
+    ///Uploads an already-serialised store without re-encoding it, eg. bytes from [`SyncClient::get_store_raw`] or a cache. Returns whether a new store was created.
+    ///
+    /// Only the magic bytes at the start are checked before sending - the server will reject anything else which is wrong.
+    ///
+    /// ## Errors
+    /// - [`ClientError::Store`] if `bytes` doesn't start with the magic bytes of a store.
+    /// - [`ClientError::Ureq`] if there was an error sending the request.
+    /// - [`ClientError::HttpErrorCode`] if the server didn't return a 2xx code.
+    pub fn add_db_with_raw_content(
+        &self,
+        overwrite_existing: bool,
+        name: impl TryInto<DbName, Error: Into<ClientError>>,
+        bytes: &[u8],
+    ) -> Result<bool, ClientError> {
+        let name = to_db_name(name)?;
+        check_store_magic_bytes(bytes)?;
+        self.before_request()?;
+
+        let req = self
             .agent
             .put(&format!(
                 "http://{}:{}/v1/add_db_with_content",
@@ -88,8 +216,13 @@ impl SyncClient {
                 "overwrite_existing",
                 if overwrite_existing { "true" } else { "false" },
             )
-            .query("db_name", name)
-            .send_bytes(&store)?;
+            .query("db_name", name.as_str());
+        let rsp = match compress_upload(bytes, self.upload_compression_threshold) {
+            Some(compressed) => req
+                .set("Content-Encoding", UPLOAD_CONTENT_ENCODING)
+                .send_bytes(&compressed)?,
+            None => req.send_bytes(bytes)?,
+        };
         Ok(match rsp.status_code()? {
             StatusCode::OK => false,
             StatusCode::CREATED => true,
@@ -99,15 +232,17 @@ impl SyncClient {
 
     pub fn add_entry_to_db(
         &self,
-        database_name: &str,
+        database_name: impl TryInto<DbName, Error: Into<ClientError>>,
         key: &str,
         value: &Value,
     ) -> Result<bool, ClientError> {
+        let database_name = to_db_name(database_name)?;
         let value = value.ser(None)?;
+        self.before_request()?;
         let rsp = self
             .agent
             .put(&format!("http://{}:{}/v1/add_kv", self.path, self.port))
-            .query("db_name", database_name)
+            .query("db_name", database_name.as_str())
             .query("key", key)
             .send_bytes(&value)?;
         Ok(match rsp.status_code()? {
@@ -117,34 +252,119 @@ impl SyncClient {
         })
     }
 
-    pub fn remove_entry_from_db(&self, database_name: &str, key: &str) -> Result<(), ClientError> {
+    pub fn remove_entry_from_db(
+        &self,
+        database_name: impl TryInto<DbName, Error: Into<ClientError>>,
+        key: &str,
+    ) -> Result<(), ClientError> {
+        let database_name = to_db_name(database_name)?;
+        self.before_request()?;
         self.agent
             .post(&format!("http://{}:{}/v1/rm_kv", self.path, self.port))
-            .query("db_name", database_name)
+            .query("db_name", database_name.as_str())
             .query("key", key)
             .call()?;
         Ok(())
     }
 
-    pub fn remove_db(&self, database_name: &str) -> Result<(), ClientError> {
+    pub fn remove_db(
+        &self,
+        database_name: impl TryInto<DbName, Error: Into<ClientError>>,
+    ) -> Result<(), ClientError> {
+        let database_name = to_db_name(database_name)?;
+        self.before_request()?;
         self.agent
             .post(&format!("http://{}:{}/v1/rm_db", self.path, self.port))
-            .query("db_name", database_name)
+            .query("db_name", database_name.as_str())
             .call()?;
         Ok(())
     }
+
+    ///Renames the database called `old` to `new` on the server, without downloading it.
+    ///
+    /// ## Errors
+    /// - [`ClientError::DatabaseAlreadyExists`] if a database called `new` already exists and `overwrite_existing` is `false`.
+    /// - [`ClientError::Ureq`] if there was an error sending the request.
+    /// - [`ClientError::HttpErrorCode`] if the server didn't return a 2xx code, eg. `404 Not Found` if there is no database called `old`.
+    pub fn rename_db(
+        &self,
+        old: impl TryInto<DbName, Error: Into<ClientError>>,
+        new: impl TryInto<DbName, Error: Into<ClientError>>,
+        overwrite_existing: bool,
+    ) -> Result<(), ClientError> {
+        let old = to_db_name(old)?;
+        let new = to_db_name(new)?;
+        self.before_request()?;
+        let rsp = self
+            .agent
+            .post(&format!("http://{}:{}/v1/rename_db", self.path, self.port))
+            .query("old_name", old.as_str())
+            .query("new_name", new.as_str())
+            .query(
+                "overwrite_existing",
+                if overwrite_existing { "true" } else { "false" },
+            )
+            .call();
+
+        match rsp {
+            Err(ureq::Error::Status(status, _)) if status == StatusCode::CONFLICT => {
+                Err(ClientError::DatabaseAlreadyExists(new.to_string()))
+            }
+            rsp => {
+                rsp?;
+                Ok(())
+            }
+        }
+    }
+
+    ///Copies the database called `src` to `dst` on the server, without downloading and re-uploading it.
+    ///
+    /// ## Errors
+    /// - [`ClientError::DatabaseAlreadyExists`] if a database called `dst` already exists and `overwrite_existing` is `false`.
+    /// - [`ClientError::Ureq`] if there was an error sending the request.
+    /// - [`ClientError::HttpErrorCode`] if the server didn't return a 2xx code, eg. `404 Not Found` if there is no database called `src`.
+    pub fn copy_db(
+        &self,
+        src: impl TryInto<DbName, Error: Into<ClientError>>,
+        dst: impl TryInto<DbName, Error: Into<ClientError>>,
+        overwrite_existing: bool,
+    ) -> Result<(), ClientError> {
+        let src = to_db_name(src)?;
+        let dst = to_db_name(dst)?;
+        self.before_request()?;
+        let rsp = self
+            .agent
+            .post(&format!("http://{}:{}/v1/copy_db", self.path, self.port))
+            .query("src_name", src.as_str())
+            .query("dst_name", dst.as_str())
+            .query(
+                "overwrite_existing",
+                if overwrite_existing { "true" } else { "false" },
+            )
+            .call();
+
+        match rsp {
+            Err(ureq::Error::Status(status, _)) if status == StatusCode::CONFLICT => {
+                Err(ClientError::DatabaseAlreadyExists(dst.to_string()))
+            }
+            rsp => {
+                rsp?;
+                Ok(())
+            }
+        }
+    }
 }
 
 trait ResponseExt {
     fn status_code(&self) -> Result<StatusCode, ClientError>;
-    fn body(self) -> Result<Vec<u8>, std::io::Error>;
+    fn body(self, max_bytes: Option<usize>) -> Result<Vec<u8>, ClientError>;
 }
 
 impl ResponseExt for Response {
     fn status_code(&self) -> Result<StatusCode, ClientError> {
         Ok(StatusCode::try_from(self.status())?)
     }
-    fn body(self) -> Result<Vec<u8>, std::io::Error> {
+    fn body(self, max_bytes: Option<usize>) -> Result<Vec<u8>, ClientError> {
         let mut reader = self.into_reader();
         let mut output = vec![];
         loop {
@@ -153,8 +373,78 @@ impl ResponseExt for Response {
                 0 => break,
                 n => output.extend(&tmp[0..n]),
             }
+
+            if let Some(max_bytes) = max_bytes {
+                if output.len() > max_bytes {
+                    return Err(ClientError::ResponseTooLarge(max_bytes));
+                }
+            }
         }
 
         Ok(output)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use core::convert::Infallible;
+
+    use axum::{body::Body, routing::get, Router};
+    use futures_util::stream;
+    use http::StatusCode;
+    use tokio::{net::TcpListener, runtime::Runtime};
+
+    use super::SyncClient;
+    use crate::client::ClientError;
+
+    ///Starts a server on a random port in `runtime` with `router` and a healthcheck route, returning the port. The runtime's worker threads serve requests while the test thread blocks on the client.
+    fn mock_server(runtime: &Runtime, router: Router) -> u32 {
+        let router = router.route("/healthcheck", get(|| async { StatusCode::OK }));
+        runtime.block_on(async move {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+
+            tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+            u32::from(port)
+        })
+    }
+
+    #[test]
+    fn test_max_response_bytes() {
+        const LEN: usize = 1024 * 1024;
+
+        let runtime = Runtime::new().unwrap();
+        let router = Router::new()
+            .route("/v1/get_db", get(|| async { vec![0xAB_u8; LEN] }))
+            //streamed without a content length, so the client has to count
+            .route(
+                "/v1/get_all_db_names",
+                get(|| async {
+                    let chunks = (0..LEN / 1024).map(|_| Ok::<_, Infallible>(vec![b' '; 1024]));
+                    Body::from_stream(stream::iter(chunks))
+                }),
+            );
+        let port = mock_server(&runtime, router);
+
+        let unlimited = SyncClient::new("127.0.0.1", port).unwrap();
+        assert_eq!(unlimited.get_store_raw("test").unwrap().len(), LEN);
+
+        let exact = unlimited.clone().with_max_response_bytes(LEN);
+        assert_eq!(exact.get_store_raw("test").unwrap().len(), LEN);
+
+        let limited = unlimited.with_max_response_bytes(1024);
+        assert!(matches!(
+            limited.get_store_raw("test"),
+            Err(ClientError::ResponseTooLarge(1024))
+        ));
+        assert!(matches!(
+            limited.get_store("test"),
+            Err(ClientError::ResponseTooLarge(1024))
+        ));
+        assert!(matches!(
+            limited.get_all_dbs(),
+            Err(ClientError::ResponseTooLarge(1024))
+        ));
+    }
+}
@@ -0,0 +1,186 @@
+//! `hashed_store` provides [`HashedStore`], a wrapper around a [`Store`] which keeps a hash of its contents up to date as entries are inserted and removed.
+//!
+//! ```rust
+//! use sourisdb::{hashed_store::HashedStore, store::Store, values::Value};
+//!
+//! let mut store = HashedStore::default();
+//! store.insert("a".into(), Value::Boolean(true));
+//! store.insert("b".into(), Value::Boolean(false));
+//!
+//! let mut other = Store::default();
+//! other.insert("b".into(), Value::Boolean(false));
+//! other.insert("a".into(), Value::Boolean(true));
+//!
+//! assert_eq!(store.current_hash(), HashedStore::new(other).current_hash());
+//! ```
+
+use alloc::string::String;
+use core::{
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::{store::Store, values::Value};
+
+///Feeds everything written to it by a [`Hash`] implementation into a SHA-256 digest.
+struct Sha256Hasher(Sha256);
+
+impl Hasher for Sha256Hasher {
+    fn finish(&self) -> u64 {
+        let digest = self.0.clone().finalize();
+        let mut first = [0; 8];
+        first.copy_from_slice(&digest[..8]);
+        u64::from_le_bytes(first)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+}
+
+///A [`Store`] along with a hash of its entries which is updated on every [`HashedStore::insert`] and [`HashedStore::remove`], so getting the hash with [`HashedStore::current_hash`] doesn't need to go through the whole store.
+///
+/// The hash is every entry's SHA-256 hash (of its key and value) combined using XOR, so it doesn't depend on the order of the entries and an entry can be taken back out by combining its hash in again.
+///
+/// Compared to hashing [`Store::canonical_bytes`]:
+/// - updating the hash is `O(1)` in the size of the store rather than `O(n)`, but every change needs to go through this wrapper, so there's no mutable access to the inner store.
+/// - only keys and values are hashed - expiry times are ignored.
+/// - it relies on the [`Hash`] implementation of [`Value`], which isn't guaranteed to be the same on different platforms or versions, so the hash should only be compared with others made by the same program. Hash the canonical bytes to identify a store anywhere else.
+/// - combining entry hashes like this is weaker than hashing everything in one go - eg. it is easier to deliberately find two stores with the same hash - so it shouldn't be used where someone might try to forge a collision.
+#[derive(Debug, Clone, Default)]
+pub struct HashedStore {
+    store: Store,
+    hash: [u8; 32],
+}
+
+impl HashedStore {
+    ///Wraps `store`, hashing all of its entries.
+    #[must_use]
+    pub fn new(store: Store) -> Self {
+        let mut hash = [0; 32];
+        for (k, v) in store.iter() {
+            xor_into(&mut hash, &entry_hash(k, v));
+        }
+
+        Self { store, hash }
+    }
+
+    ///Inserts `v` under `k`, returning the value which was there before and updating the hash.
+    #[allow(clippy::must_use_candidate)]
+    pub fn insert(&mut self, k: String, v: Value) -> Option<Value> {
+        if let Some(old) = self.store.get(&k) {
+            xor_into(&mut self.hash, &entry_hash(&k, old));
+        }
+        xor_into(&mut self.hash, &entry_hash(&k, &v));
+        self.store.insert(k, v)
+    }
+
+    ///Removes the value stored under `k`, returning it and updating the hash.
+    #[allow(clippy::must_use_candidate)]
+    pub fn remove(&mut self, k: &str) -> Option<Value> {
+        let old = self.store.remove(k)?;
+        xor_into(&mut self.hash, &entry_hash(k, &old));
+        Some(old)
+    }
+
+    ///Gets the hash of every entry currently in the store. Stores with the same entries always have the same hash, no matter what order they were inserted in.
+    #[must_use]
+    pub fn current_hash(&self) -> [u8; 32] {
+        self.hash
+    }
+
+    ///Gets the inner store back.
+    #[must_use]
+    pub fn into_inner(self) -> Store {
+        self.store
+    }
+}
+
+impl Deref for HashedStore {
+    type Target = Store;
+
+    fn deref(&self) -> &Self::Target {
+        &self.store
+    }
+}
+
+impl From<Store> for HashedStore {
+    fn from(store: Store) -> Self {
+        Self::new(store)
+    }
+}
+
+fn entry_hash(k: &str, v: &Value) -> [u8; 32] {
+    let mut hasher = Sha256Hasher(Sha256::new());
+    k.hash(&mut hasher);
+    v.hash(&mut hasher);
+    hasher.0.finalize().into()
+}
+
+fn xor_into(hash: &mut [u8; 32], other: &[u8; 32]) {
+    for (a, b) in hash.iter_mut().zip(other) {
+        *a ^= b;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, string::ToString};
+
+    use serde_json::json;
+
+    use super::HashedStore;
+    use crate::{store::Store, values::Value};
+
+    #[test]
+    fn test_incremental_hash_matches_from_scratch() {
+        let mut hashed = HashedStore::new(Store::from_json(json!({"a": 1, "b": [true, "c"]})));
+        let initial = hashed.current_hash();
+
+        for i in 0..20 {
+            hashed.insert(format!("key{i}"), Value::from(i));
+        }
+        hashed.insert("a".to_string(), Value::String("replaced".into()));
+        hashed.remove("key3");
+        hashed.remove("not there");
+        hashed.insert("key5".to_string(), Value::from(5));
+
+        assert_eq!(
+            hashed.current_hash(),
+            HashedStore::new(hashed.clone().into_inner()).current_hash()
+        );
+        assert_ne!(hashed.current_hash(), initial);
+
+        for i in 0..20 {
+            hashed.remove(&format!("key{i}"));
+        }
+        hashed.insert("a".to_string(), Value::from(1));
+        assert_eq!(hashed.current_hash(), initial);
+
+        hashed.remove("a");
+        hashed.remove("b");
+        assert_eq!(hashed.current_hash(), HashedStore::default().current_hash());
+    }
+
+    #[test]
+    fn test_nested_containers_dont_collide() {
+        for (a, b) in [
+            (json!({"k": [[1], 2]}), json!({"k": [[1, 2]]})),
+            (json!({"k": [[], [1]]}), json!({"k": [[1], []]})),
+            (json!({"k": [{"a": 1}, {}]}), json!({"k": [{}, {"a": 1}]})),
+            (
+                json!({"k": {"a": {"b": 1}}}),
+                json!({"k": {"a": {}, "b": 1}}),
+            ),
+        ] {
+            let (a, b) = (Store::from_json(a), Store::from_json(b));
+            assert_ne!(a, b);
+            assert_ne!(
+                HashedStore::new(a).current_hash(),
+                HashedStore::new(b).current_hash()
+            );
+        }
+    }
+}
@@ -21,6 +21,7 @@ pub use chrono;
 pub use hashbrown;
 pub use serde_json;
 
+pub mod hashed_store;
 pub mod store;
 pub mod types;
 pub mod utilities;
@@ -31,9 +32,31 @@ pub mod values;
 #[cfg(feature = "axum")]
 pub mod axum;
 
+#[cfg(feature = "std")]
+pub mod shared_store;
+
 #[cfg(any(feature = "sync_client", feature = "async_client"))]
 pub mod client;
 
+///The maximum length of a database name on a `sourisd` server - see [`is_valid_db_name`].
+pub const MAX_DB_NAME_LEN: usize = 64;
+
+///Checks whether `name` can be used as the name of a database on a `sourisd` server, which also uses it as a file name. This is the rule used by both the server and the clients.
+///
+/// A valid name:
+/// - is between 1 and [`MAX_DB_NAME_LEN`] characters long.
+/// - only contains ASCII letters, digits, `-` and `_`.
+/// - isn't `meta`, which the server uses internally.
+#[must_use]
+pub fn is_valid_db_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_DB_NAME_LEN
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+        && name != "meta"
+}
+
 #[must_use]
 pub fn display_bytes_as_hex_array(b: &[u8]) -> String {
     let mut out;
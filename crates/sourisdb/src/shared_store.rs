@@ -0,0 +1,128 @@
+//! `shared_store` provides [`SharedStore`], a cheaply-cloneable handle to a [`Store`] which can be shared between threads or async tasks.
+//!
+//! Every method only holds the lock for as long as it needs to, and reads return clones so that no lock is held once they return.
+//!
+//! ```rust
+//! use sourisdb::{shared_store::SharedStore, values::Value};
+//!
+//! let store = SharedStore::default();
+//! let handle = store.clone();
+//!
+//! std::thread::spawn(move || {
+//!     handle.insert("hello".into(), Value::String("world".into()));
+//! })
+//! .join()
+//! .unwrap();
+//!
+//! assert_eq!(store.get("hello"), Some(Value::String("world".into())));
+//! ```
+
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::{store::Store, values::Value};
+
+///A handle to a [`Store`] behind an [`Arc`] and a [`RwLock`]. Cloning the handle gives another handle to the same store.
+///
+/// If a thread panics whilst holding the lock, the store is still used as-is rather than every later call panicking - each method leaves the store in a valid state at every point.
+#[derive(Debug, Clone, Default)]
+pub struct SharedStore(Arc<RwLock<Store>>);
+
+impl SharedStore {
+    ///Creates a new handle holding `store`.
+    #[must_use]
+    pub fn new(store: Store) -> Self {
+        Self(Arc::new(RwLock::new(store)))
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Store> {
+        self.0.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Store> {
+        self.0.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    ///Gets a clone of the value stored under `k`.
+    #[must_use]
+    pub fn get(&self, k: &str) -> Option<Value> {
+        self.read().get(k).cloned()
+    }
+
+    ///Inserts `v` under `k`, returning the value which was there before.
+    #[allow(clippy::must_use_candidate)]
+    pub fn insert(&self, k: String, v: Value) -> Option<Value> {
+        self.write().insert(k, v)
+    }
+
+    ///Removes the value stored under `k`, returning it.
+    #[allow(clippy::must_use_candidate)]
+    pub fn remove(&self, k: &str) -> Option<Value> {
+        self.write().remove(k)
+    }
+
+    ///Gets a clone of the whole store, eg. for serialising without holding the lock.
+    #[must_use]
+    pub fn snapshot(&self) -> Store {
+        self.read().clone()
+    }
+
+    ///Runs `f` with the store locked for reading, for anything which needs more than one read to be consistent.
+    pub fn with_read<T>(&self, f: impl FnOnce(&Store) -> T) -> T {
+        f(&self.read())
+    }
+
+    ///Runs `f` with the store locked for writing, for anything which needs more than one change to happen at once.
+    pub fn with_write<T>(&self, f: impl FnOnce(&mut Store) -> T) -> T {
+        f(&mut self.write())
+    }
+}
+
+impl From<Store> for SharedStore {
+    fn from(store: Store) -> Self {
+        Self::new(store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, string::ToString};
+    use std::thread;
+
+    use super::SharedStore;
+    use crate::values::Value;
+
+    #[test]
+    fn test_concurrent_inserts() {
+        const THREADS: usize = 8;
+        const PER_THREAD: usize = 100;
+
+        let store = SharedStore::default();
+
+        thread::scope(|s| {
+            for t in 0..THREADS {
+                let store = store.clone();
+                s.spawn(move || {
+                    for i in 0..PER_THREAD {
+                        store.insert(format!("{t}-{i}"), Value::from(i));
+                    }
+                });
+            }
+        });
+
+        let snapshot = store.snapshot();
+        assert_eq!(snapshot.len(), THREADS * PER_THREAD);
+        for t in 0..THREADS {
+            for i in 0..PER_THREAD {
+                assert_eq!(snapshot.get(&format!("{t}-{i}")), Some(&Value::from(i)));
+            }
+        }
+
+        assert_eq!(store.remove("0-0"), Some(Value::from(0_usize)));
+        assert_eq!(store.get("0-0"), None);
+        assert_eq!(
+            store.insert("0-1".to_string(), Value::Null(())),
+            Some(Value::from(1_usize))
+        );
+        assert_eq!(store.with_read(|s| s.len()), THREADS * PER_THREAD - 1);
+    }
+}
@@ -1,30 +1,39 @@
 //! Provides the main key-value store designed to be used for communications.
 
 use alloc::{
+    boxed::Box,
+    format,
     string::{String, ToString},
     vec,
     vec::Vec,
 };
 use core::{
+    cmp::Ordering,
     fmt::{Display, Formatter},
-    ops::{Deref, DerefMut},
+    num::NonZeroUsize,
+    ops::{Deref, DerefMut, Range},
 };
 
-use hashbrown::HashMap;
-use lz4_flex::{block::DecompressError as Lz4DecompressError, compress, decompress};
+use chrono::{NaiveDateTime, TimeDelta};
+use hashbrown::{HashMap, HashSet};
+use lz4_flex::{block::DecompressError as Lz4DecompressError, compress, decompress_into};
 use miniz_oxide::{
     deflate::compress_to_vec,
-    inflate::{decompress_to_vec, DecompressError as MinizDecompressError},
+    inflate::{
+        core::{decompress as miniz_decompress, inflate_flags, DecompressorOxide},
+        DecompressError as MinizDecompressError, TINFLStatus,
+    },
 };
-use serde_json::{Error as SJError, Value as SJValue};
+use serde_json::{json, Error as SJError, Value as SJValue};
+use sha2::{Digest, Sha256};
 
 use crate::{
-    types::integer::{Integer, IntegerSerError, SignedState},
+    types::integer::{BiggestInt, BiggestIntButSigned, Integer, IntegerSerError, SignedState},
     utilities::{
         cursor::Cursor,
         huffman::{Huffman, HuffmanSerError},
     },
-    values::{Value, ValueSerError, ValueTy},
+    values::{FloatFormat, Value, ValueSerError, ValueTy, DEFAULT_MAX_DEPTH},
 };
 
 ///A key-value store where the keys are [`String`]s and the values are [`Value`]s - this is a thin wrapper around [`hashbrown::HashMap`] and implements both [`Deref`] and [`DerefMut`] pointing to it. This database is optimised for storage when serialised.
@@ -32,8 +41,301 @@ use crate::{
 /// The expectation is that if you need an in-memory key-value database, you do one of two things:
 /// - Spin up a server running `sourisd` and make HTTP requests to it. Then, serialise or deserialise the values appropriately.
 /// - Create a `Store` and keep it in the state of your program. To access values just use it as a [`hashbrown::HashMap`]. When your program exits (or periodically to allow for if the program quits unexpectedly), serialise the database and write it to a file. Then, when starting the program again read the database in.
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
-pub struct Store(HashMap<String, Value>);
+///
+/// Keys can also be given an expiry time using [`Store::insert_with_ttl`], for cache-like usage. Expiry times are kept alongside the map and are serialised with it, but are only acted upon by [`Store::purge_expired`]. Every method on `Store` which adds or removes a key keeps the expiry times in step - changing the set of keys through the [`hashbrown::HashMap`] methods which `Store` doesn't shadow (like [`hashbrown::HashMap::entry`]) can leave a stale expiry time behind for a key which is later re-inserted.
+#[derive(Debug, Clone, Eq, Default)]
+pub struct Store(HashMap<String, Value>, HashMap<String, NaiveDateTime>);
+
+impl PartialEq for Store {
+    fn eq(&self, other: &Self) -> bool {
+        //expiry times left behind by keys which have since been removed don't count
+        fn live_expiries(store: &Store) -> impl Iterator<Item = (&String, &NaiveDateTime)> {
+            store.1.iter().filter(|(k, _)| store.0.contains_key(*k))
+        }
+
+        self.0 == other.0
+            && live_expiries(self).count() == live_expiries(other).count()
+            && live_expiries(self).all(|(k, t)| other.expiry(k) == Some(*t))
+    }
+}
+
+///The key used by [`Store::from_json`] and [`Store::to_json`] to hold JSON values which aren't objects - eg. an array of records.
+///
+/// A store which only contains a [`Value::Array`] under this key is treated as an array by methods like [`Store::as_array`].
+pub const JSON_KEY: &str = "JSON";
+
+///Set in the compression byte of a serialised store if a [`Value::Map`] of expiry times (see [`Store::insert_with_ttl`]) follows the map of values. This is only ever set along with [`VERSIONED_FLAG`].
+const EXPIRY_FLAG: u8 = 0b0100_0000;
+
+///Set in the compression byte of a serialised store if a format version byte follows it - see [`FORMAT_VERSION`].
+///
+/// This sits in the bits which used to only hold the compression type, so readers from before versions existed fail with [`StoreSerError::UnsupportedCompression`] rather than misreading the store.
+const VERSIONED_FLAG: u8 = 0b0000_1000;
+
+///The newest format version which this reader understands. Stores which don't need anything newer than the original format have no version byte at all, so older readers can still read them.
+///
+/// - `1` added expiry times, see [`EXPIRY_FLAG`].
+const FORMAT_VERSION: u8 = 1;
+
+///Set in the compression byte of a serialised store if a length-prefixed [`Value::Map`] of metadata (see [`Store::ser_with_metadata`]) follows the compression byte.
+const METADATA_FLAG: u8 = 0b0010_0000;
+
+///Normalisation to apply to data as it is imported using [`Store::from_json_with_options`]. The [`Default`] is to do no normalisation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IngestOptions {
+    ///Trim leading and trailing whitespace from every [`Value::String`].
+    pub trim_strings: bool,
+    ///Lowercase every key. If two keys in the same map only differ by case, only one of their values is kept.
+    pub lowercase_keys: bool,
+    ///Turn every non-empty map whose keys are exactly `"0"` to `"n-1"` into a [`Value::Array`], as these are usually arrays which were turned into objects somewhere upstream. Keys with leading zeroes or gaps in the indices stop a map from being turned into an array.
+    ///
+    /// If the top-level map is turned into an array, the store holds an array (see [`Store::as_array`]).
+    pub sequential_maps_to_arrays: bool,
+    ///What to do with JSON `null`s.
+    pub null_policy: NullPolicy,
+}
+
+///How [`Store::from_json_with_options`] imports JSON `null`s - see [`IngestOptions::null_policy`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NullPolicy {
+    ///Keep them as [`Value::Null`].
+    #[default]
+    AsNull,
+    ///Remove every entry in a map whose value is `null`. Nulls inside arrays are kept as [`Value::Null`] so that the other elements keep their indices, and a `null` on its own gives an empty store.
+    Skip,
+    ///Turn them into empty [`Value::String`]s.
+    AsEmptyString,
+}
+
+impl IngestOptions {
+    fn normalise(self, v: Value) -> Value {
+        match v {
+            Value::String(s) if self.trim_strings => Value::String(s.trim().to_string()),
+            Value::Null(()) if self.null_policy == NullPolicy::AsEmptyString => {
+                Value::String(String::new())
+            }
+            Value::Map(m) => {
+                let m: HashMap<String, Value> = m
+                    .into_iter()
+                    .filter(|(_, v)| {
+                        !(self.null_policy == NullPolicy::Skip && matches!(v, Value::Null(())))
+                    })
+                    .map(|(k, v)| {
+                        let k = if self.lowercase_keys {
+                            k.to_lowercase()
+                        } else {
+                            k
+                        };
+                        (k, self.normalise(v))
+                    })
+                    .collect();
+
+                if self.sequential_maps_to_arrays {
+                    Self::sequential_map_to_array(m)
+                } else {
+                    Value::Map(m)
+                }
+            }
+            Value::Array(a) => Value::Array(a.into_iter().map(|v| self.normalise(v)).collect()),
+            v => v,
+        }
+    }
+
+    fn sequential_map_to_array(mut m: HashMap<String, Value>) -> Value {
+        //as there are `len` keys, finding every index from 0 to `len - 1` means that there are no others
+        let is_sequential =
+            !m.is_empty() && (0..m.len()).all(|i| m.contains_key(i.to_string().as_str()));
+        if !is_sequential {
+            return Value::Map(m);
+        }
+
+        Value::Array(
+            (0..m.len())
+                .filter_map(|i| m.remove(i.to_string().as_str()))
+                .collect(),
+        )
+    }
+}
+
+///Adds `token` onto the end of the JSON Pointer `pointer`, escaping it as described in [RFC 6901](https://datatracker.ietf.org/doc/html/rfc6901).
+fn json_pointer_push(pointer: &str, token: &str) -> String {
+    format!("{pointer}/{}", token.replace('~', "~0").replace('/', "~1"))
+}
+
+///Splits a JSON Pointer into its unescaped tokens - the inverse of [`json_pointer_push`]. The empty pointer refers to the whole document, so has no tokens.
+fn json_pointer_tokens(pointer: &str) -> Result<Vec<String>, StoreSerError> {
+    if pointer.is_empty() {
+        return Ok(vec![]);
+    }
+    let Some(pointer) = pointer.strip_prefix('/') else {
+        return Err(StoreSerError::InvalidJsonPatch(format!(
+            "pointer {pointer:?} doesn't start with '/'"
+        )));
+    };
+
+    Ok(pointer
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+///Parses a JSON Pointer token as an array index, which can't have leading zeroes.
+fn json_pointer_index(token: &str, path: &str) -> Result<usize, StoreSerError> {
+    match token.parse::<usize>() {
+        Ok(i) if i.to_string() == token => Ok(i),
+        _ => Err(StoreSerError::MissingKey(path.to_string())),
+    }
+}
+
+///Gets the value at `tokens` (from [`json_pointer_tokens`] for `path`) for [`Store::apply_json_patch`].
+fn json_patch_get<'a>(
+    root: &'a mut Value,
+    path: &str,
+    tokens: &[String],
+) -> Result<&'a mut Value, StoreSerError> {
+    let mut current = root;
+    for token in tokens {
+        current = match current {
+            Value::Map(m) => m.get_mut(token),
+            Value::Array(a) => {
+                let len = a.len();
+                let i = json_pointer_index(token, path)?;
+                Some(
+                    a.get_mut(i)
+                        .ok_or(StoreSerError::IndexOutOfBounds(i, len))?,
+                )
+            }
+            _ => None,
+        }
+        .ok_or_else(|| StoreSerError::MissingKey(path.to_string()))?;
+    }
+    Ok(current)
+}
+
+///Adds `value` at `tokens` for [`Store::apply_json_patch`] - replacing any value in a map, and shifting along any elements in an array. `-` adds to the end of an array.
+fn json_patch_add(
+    root: &mut Value,
+    path: &str,
+    tokens: &[String],
+    value: Value,
+) -> Result<(), StoreSerError> {
+    let Some((last, parent)) = tokens.split_last() else {
+        *root = value;
+        return Ok(());
+    };
+
+    match json_patch_get(root, path, parent)? {
+        Value::Map(m) => {
+            m.insert(last.clone(), value);
+        }
+        Value::Array(a) if last == "-" => a.push(value),
+        Value::Array(a) => {
+            let i = json_pointer_index(last, path)?;
+            if i > a.len() {
+                return Err(StoreSerError::IndexOutOfBounds(i, a.len()));
+            }
+            a.insert(i, value);
+        }
+        _ => return Err(StoreSerError::MissingKey(path.to_string())),
+    }
+    Ok(())
+}
+
+///Removes and returns the value at `tokens` for [`Store::apply_json_patch`].
+fn json_patch_remove(
+    root: &mut Value,
+    path: &str,
+    tokens: &[String],
+) -> Result<Value, StoreSerError> {
+    let Some((last, parent)) = tokens.split_last() else {
+        return Err(StoreSerError::InvalidJsonPatch(
+            "cannot remove the whole store".to_string(),
+        ));
+    };
+
+    match json_patch_get(root, path, parent)? {
+        Value::Map(m) => m
+            .remove(last)
+            .ok_or_else(|| StoreSerError::MissingKey(path.to_string())),
+        Value::Array(a) => {
+            let i = json_pointer_index(last, path)?;
+            if i >= a.len() {
+                return Err(StoreSerError::IndexOutOfBounds(i, a.len()));
+            }
+            Ok(a.remove(i))
+        }
+        _ => Err(StoreSerError::MissingKey(path.to_string())),
+    }
+}
+
+///Receives the entries of a store one at a time from [`Store::deser_visit`].
+///
+/// This is implemented for any `FnMut(&str, Value)`.
+pub trait StoreVisitor {
+    ///Called with each top-level entry, in the order they were serialised.
+    fn visit_entry(&mut self, key: &str, value: Value);
+}
+
+impl<F: FnMut(&str, Value)> StoreVisitor for F {
+    fn visit_entry(&mut self, key: &str, value: Value) {
+        self(key, value);
+    }
+}
+
+///Everything read from a serialised store before its values - see [`Store::deser_header`].
+struct StoreHeader {
+    metadata: Option<Store>,
+    huffman: Option<Huffman<char>>,
+    ///Whether the expiry times follow the values in the body.
+    has_expiries: bool,
+    ///How many bytes of the input came after the compressed body, which is always `0` for uncompressed stores as their body is the rest of the input.
+    trailing: usize,
+    ///Whether the body was compressed, and so how to work out where the store ends.
+    compressed: bool,
+}
+
+///What [`Store::enforce_key_limits`] does with keys which are too long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LimitAction {
+    ///Shorten the key, adding a suffix if that would clash with another key.
+    Truncate,
+    ///Fail with [`StoreSerError::KeyTooLong`], without changing the store.
+    Error,
+    ///Remove the entry.
+    Drop,
+}
+
+///Which value [`Store::merge_logged`] keeps when both stores hold different values under the same key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MergeStrategy {
+    ///Keep the value already in the store.
+    KeepOurs,
+    ///Keep the value from the store being merged in.
+    KeepTheirs,
+}
+
+///A key which held different values in both stores passed to [`Store::merge_logged`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    ///The key which both stores held.
+    pub key: String,
+    ///The value which is now in the store.
+    pub kept: Value,
+    ///The value which was thrown away.
+    pub discarded: Value,
+}
+
+///The collection inside a store, returned by [`Store::into_parts`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoreParts {
+    ///A store holding an array (see [`Store::as_array`]).
+    Array(Vec<Value>),
+    ///Any other store.
+    Map(HashMap<String, Value>),
+}
+
+///The separator put between the prefix and each key by [`Store::join_prefixed`].
+pub const PREFIX_SEPARATOR: char = '.';
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
 enum CompressionType {
@@ -81,25 +383,204 @@ impl Store {
             (Some(lz4), CompressionType::Lz4)
         }
     }
-    fn decompress(
+    ///Decompresses `bytes` into `out`, replacing anything which was in it and reusing its capacity. Returns how many bytes of `bytes` were used, so anything after the compressed data can be spotted - uncompressed data always uses everything.
+    ///
+    /// Fails with [`StoreSerError::DecompressedTooLarge`] rather than making `out` longer than `max_len`.
+    fn decompress_into(
         bytes: &[u8],
         compression_type: CompressionType,
-    ) -> Result<Vec<u8>, StoreSerError> {
+        out: &mut Vec<u8>,
+        max_len: usize,
+    ) -> Result<usize, StoreSerError> {
+        //each byte of an lz4 block can add at most 255 bytes to the output, so any length claiming more than that can't be right
+        const MAX_LZ4_RATIO: usize = 255;
+
+        out.clear();
         match compression_type {
-            CompressionType::None => Ok(bytes.to_vec()),
+            CompressionType::None => {
+                if bytes.len() > max_len {
+                    return Err(StoreSerError::DecompressedTooLarge(max_len));
+                }
+                out.extend_from_slice(bytes);
+                Ok(bytes.len())
+            }
             CompressionType::Lz4 => {
                 let mut cursor = Cursor::new(&bytes);
                 let original_len: usize =
                     Integer::deser(SignedState::Unsigned, &mut cursor)?.try_into()?;
+                let block = cursor.as_ref();
+                let max_len = max_len.min(block.len().saturating_mul(MAX_LZ4_RATIO));
+                if original_len > max_len {
+                    return Err(StoreSerError::DecompressedTooLarge(max_len));
+                }
+                //if the end can't be found, the block is broken, so decompressing all of it gives the right error
+                let block_len = Self::lz4_block_len(block, original_len).unwrap_or(block.len());
+
+                out.resize(original_len, 0);
+                let len = decompress_into(&block[..block_len], out)?;
+                //a block which was cut off between sequences decompresses fine, just to too few bytes
+                if len != original_len {
+                    return Err(StoreSerError::NotEnoughBytes);
+                }
+
+                Ok(cursor.pos() + block_len)
+            }
+            CompressionType::Miniz => {
+                let mut decompressor = Box::<DecompressorOxide>::default();
+                out.resize(bytes.len().saturating_mul(2).max(64).min(max_len), 0);
+
+                let (mut in_pos, mut out_pos) = (0, 0);
+                loop {
+                    let (status, in_consumed, out_consumed) = miniz_decompress(
+                        &mut decompressor,
+                        &bytes[in_pos..],
+                        out,
+                        out_pos,
+                        inflate_flags::TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF,
+                    );
+                    in_pos += in_consumed;
+                    out_pos += out_consumed;
+
+                    match status {
+                        TINFLStatus::Done => {
+                            out.truncate(out_pos);
+                            return Ok(in_pos);
+                        }
+                        TINFLStatus::HasMoreOutput => {
+                            if out.len() >= max_len {
+                                out.truncate(out_pos);
+                                return Err(StoreSerError::DecompressedTooLarge(max_len));
+                            }
+                            out.resize(out.len().saturating_mul(2).min(max_len), 0);
+                        }
+                        status => {
+                            out.truncate(out_pos);
+                            return Err(MinizDecompressError {
+                                status,
+                                output: core::mem::take(out),
+                            }
+                            .into());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    ///Finds where an lz4 block which decompresses to `original_len` bytes ends, as blocks don't record their own length. A block is a run of sequences of literals followed by a match, and the last sequence only has literals - so it ends after the literals which fill up the output.
+    fn lz4_block_len(block: &[u8], original_len: usize) -> Option<usize> {
+        fn read_len(block: &[u8], pos: &mut usize, nibble: u8) -> Option<usize> {
+            let mut len = usize::from(nibble);
+            if nibble == 0b1111 {
+                loop {
+                    let byte = *block.get(*pos)?;
+                    *pos += 1;
+                    len = len.checked_add(usize::from(byte))?;
+                    if byte != u8::MAX {
+                        break;
+                    }
+                }
+            }
+            Some(len)
+        }
+
+        let (mut pos, mut produced) = (0_usize, 0_usize);
+        loop {
+            let token = *block.get(pos)?;
+            pos += 1;
 
-                Ok(decompress(cursor.as_ref(), original_len)?)
+            let literals = read_len(block, &mut pos, token >> 4)?;
+            pos = pos.checked_add(literals)?;
+            produced = produced.checked_add(literals)?;
+            if pos > block.len() || produced > original_len {
+                return None;
             }
-            CompressionType::Miniz => Ok(decompress_to_vec(bytes)?),
+            if produced == original_len {
+                return Some(pos);
+            }
+
+            //skip the offset of the match
+            pos += 2;
+            let match_len = read_len(block, &mut pos, token & 0b1111)?.checked_add(4)?;
+            produced = produced.checked_add(match_len)?;
         }
     }
 
     ///Serialises a store into bytes. There are 8 magic bytes at the front which read `SOURISDB` and the rest is serialised as a [`Value::Map`] containing the map stored within the caller.
+    ///
+    /// If any keys have expiry times, they follow as a second [`Value::Map`] of [`Value::Timestamp`]s, and [`EXPIRY_FLAG`] is set in the compression byte along with a format version of `1` (see [`FORMAT_VERSION`]). Stores without any expiry times are serialised exactly as they were before expiry times existed. Readers from before format versions existed can't read stores with expiry times, and fail with [`StoreSerError::UnsupportedCompression`] rather than silently dropping them.
     pub fn ser(&self) -> Result<Vec<u8>, StoreSerError> {
+        self.ser_inner(None)
+    }
+
+    ///Serialises a store like [`Store::ser`], but with `metadata` (eg. who created it, or a schema version) stored in the header, separately from the data. The metadata can be read back using [`Store::deser_with_metadata`], and is skipped by [`Store::deser`].
+    ///
+    /// The metadata is serialised as a [`Value::Map`] straight after the compression byte, prefixed with its length and with [`METADATA_FLAG`] set. It isn't compressed or huffman-encoded, so should be kept small. Readers from before metadata existed can't skip it, and will fail to read the store.
+    ///
+    /// ## Errors
+    /// Any error from [`Store::ser`], or from serialising the metadata.
+    pub fn ser_with_metadata(&self, metadata: &Store) -> Result<Vec<u8>, StoreSerError> {
+        self.ser_inner(Some(metadata))
+    }
+
+    ///Serialises a store so that stores which are equal (using [`PartialEq`]) always give exactly the same bytes, eg. for signing a store or using a hash of it as an identifier.
+    ///
+    /// The output is in the same format as [`Store::ser`] and can be read back using [`Store::deser`], but it is usually larger and may differ from [`Store::ser`] for the same store, as:
+    /// - it is never compressed or huffman-encoded, as the chosen compression and the huffman tree can depend on the order in which keys are visited.
+    /// - it never has metadata, and the compression byte only ever has [`EXPIRY_FLAG`] and [`VERSIONED_FLAG`] set.
+    /// - keys are always in sorted order, including in nested maps and the map of expiry times.
+    /// - every non-negative [`Value::Integer`] is written as unsigned, as integers with the same value are equal no matter how they were created.
+    /// - expiry times for keys which aren't in the store are skipped.
+    ///
+    /// Arrays (see [`Store::as_array`]) are written as a map with their one [`JSON_KEY`] entry, as they are stored.
+    ///
+    /// ## Errors
+    /// Any error from serialising the values.
+    pub fn canonical_bytes(&self) -> Result<Vec<u8>, StoreSerError> {
+        let map = Value::Map(
+            self.0
+                .iter()
+                .map(|(k, v)| (k.clone(), Self::canonicalise(v)))
+                .collect(),
+        );
+        let expiries: HashMap<String, Value> = self
+            .1
+            .iter()
+            .filter(|(k, _)| self.0.contains_key(*k))
+            .map(|(k, t)| (k.clone(), Value::Timestamp(*t)))
+            .collect();
+
+        let mut res = vec![];
+        res.extend(b"SOURISDB");
+        if expiries.is_empty() {
+            res.push(u8::from(CompressionType::None));
+            res.extend(map.ser(None)?);
+        } else {
+            res.push(u8::from(CompressionType::None) | EXPIRY_FLAG | VERSIONED_FLAG);
+            res.push(FORMAT_VERSION);
+            res.extend(map.ser(None)?);
+            res.extend(Value::Map(expiries).ser(None)?);
+        }
+
+        Ok(res)
+    }
+
+    ///Clones `value` with every non-negative [`Value::Integer`] made unsigned, so that equal values always serialise to the same bytes - see [`Store::canonical_bytes`].
+    fn canonicalise(value: &Value) -> Value {
+        match value {
+            Value::Integer(i) if !i.is_negative() => BiggestInt::try_from(*i)
+                .map_or(Value::Integer(*i), |i| Value::Integer(Integer::from(i))),
+            Value::Map(m) => Value::Map(
+                m.iter()
+                    .map(|(k, v)| (k.clone(), Self::canonicalise(v)))
+                    .collect(),
+            ),
+            Value::Array(a) => Value::Array(a.iter().map(Self::canonicalise).collect()),
+            v => v.clone(),
+        }
+    }
+
+    fn ser_inner(&self, metadata: Option<&Store>) -> Result<Vec<u8>, StoreSerError> {
         fn add_value_text_to_string(value: &Value, string: &mut String) {
             match value {
                 Value::Map(map) => {
@@ -129,14 +610,43 @@ impl Store {
         add_value_text_to_string(&raw_map, &mut all_text);
 
         let huffman = Huffman::new_str(&all_text);
-        let raw_map = raw_map.ser(huffman.as_ref())?;
+        let mut raw_map = raw_map.ser(huffman.as_ref())?;
+
+        //only expiries for keys which still exist are kept, and their keys are already in the huffman tree from the values
+        let expiries: HashMap<String, Value> = self
+            .1
+            .iter()
+            .filter(|(k, _)| self.0.contains_key(*k))
+            .map(|(k, t)| (k.clone(), Value::Timestamp(*t)))
+            .collect();
+        let has_expiries = !expiries.is_empty();
+        if has_expiries {
+            raw_map.extend(Value::Map(expiries).ser(huffman.as_ref())?);
+        }
 
         let (map, compression_ty) = Self::compress(&raw_map);
 
         let mut res = vec![];
 
         res.extend(b"SOURISDB");
-        res.push(u8::from(compression_ty) | (u8::from(huffman.is_some()) << 7));
+        res.push(
+            u8::from(compression_ty)
+                | (u8::from(huffman.is_some()) << 7)
+                | if has_expiries {
+                    EXPIRY_FLAG | VERSIONED_FLAG
+                } else {
+                    0
+                }
+                | if metadata.is_some() { METADATA_FLAG } else { 0 },
+        );
+        if has_expiries {
+            res.push(FORMAT_VERSION);
+        }
+        if let Some(metadata) = metadata {
+            let metadata = Value::Map(metadata.0.clone()).ser(None)?;
+            res.extend(Integer::from(metadata.len()).ser().1);
+            res.extend(metadata);
+        }
         if let Some(huffman) = huffman {
             res.extend(huffman.ser());
         }
@@ -150,8 +660,131 @@ impl Store {
         Ok(res)
     }
 
+    ///Serialises the store using [`Store::ser`], and then returns an iterator over the bytes in chunks of `chunk_size` - useful for protocols which frame messages. The chunks concatenate to exactly the output of [`Store::ser`], and only the last chunk may be shorter than `chunk_size`.
+    ///
+    /// The whole store is still serialised up-front, as the compression and huffman encoding both need to see everything, but each chunk is only allocated as it is needed.
+    ///
+    /// ## Errors
+    /// Any error from [`Store::ser`].
+    pub fn ser_chunks(
+        &self,
+        chunk_size: NonZeroUsize,
+    ) -> Result<impl Iterator<Item = Vec<u8>>, StoreSerError> {
+        let chunk_size = chunk_size.get();
+        let bytes = self.ser()?;
+        let len = bytes.len();
+        Ok((0..len)
+            .step_by(chunk_size)
+            .map(move |start| bytes[start..len.min(start + chunk_size)].to_vec()))
+    }
+
+    ///Deserialises a store, limiting nesting to [`DEFAULT_MAX_DEPTH`] levels - see [`Store::deser_with_max_depth`].
+    ///
+    /// ## Errors
+    /// See [`Store::deser_with_max_depth`].
     pub fn deser(bytes: &[u8]) -> Result<Self, StoreSerError> {
-        let mut bytes = Cursor::new(&bytes);
+        Self::deser_with_max_depth(bytes, DEFAULT_MAX_DEPTH)
+    }
+
+    ///Deserialises a store, failing with [`ValueSerError::DepthExceeded`] if values are nested more than `max_depth` levels deep (see [`Value::deser_with_max_depth`]). The store itself counts as one level, and the limit applies to any metadata (see [`Store::ser_with_metadata`]) in the same way.
+    ///
+    /// ## Errors
+    /// - [`StoreSerError::Value`] if any value fails to deserialise, including exceeding `max_depth`.
+    /// - Any of the header, compression or huffman errors from reading the store itself.
+    pub fn deser_with_max_depth(bytes: &[u8], max_depth: usize) -> Result<Self, StoreSerError> {
+        Ok(Self::deser_inner(bytes, max_depth, usize::MAX, false, &mut vec![])?.0)
+    }
+
+    ///Deserialises a store like [`Store::deser`], but fails rather than decompressing its body to more than `max_len` bytes - for reading stores from untrusted sources, as a small compressed store can otherwise claim to be huge.
+    ///
+    /// Without a limit, lz4-compressed stores are still limited to what their compressed size could possibly decompress to.
+    ///
+    /// ## Errors
+    /// - [`StoreSerError::DecompressedTooLarge`] if the body of the store is larger than `max_len` bytes.
+    /// - Any error from [`Store::deser`].
+    pub fn deser_with_max_len(bytes: &[u8], max_len: usize) -> Result<Self, StoreSerError> {
+        Ok(Self::deser_inner(bytes, DEFAULT_MAX_DEPTH, max_len, false, &mut vec![])?.0)
+    }
+
+    ///Deserialises a store like [`Store::deser`], but fails if there is anything left over after the store - useful when the store should be the entire payload, as trailing data could mean that two payloads were concatenated or a length was wrong.
+    ///
+    /// This works for every compression type - compressed stores are checked for anything after the compressed data, as well as anything left over once it has been decompressed.
+    ///
+    /// ## Errors
+    /// - [`StoreSerError::TrailingData`] with the number of bytes left over if the store didn't use all of `bytes`.
+    /// - Any error from [`Store::deser`].
+    pub fn deser_strict(bytes: &[u8]) -> Result<Self, StoreSerError> {
+        Ok(Self::deser_inner(bytes, DEFAULT_MAX_DEPTH, usize::MAX, true, &mut vec![])?.0)
+    }
+
+    ///Deserialises a store like [`Store::deser`], also returning the metadata from its header if it was serialised using [`Store::ser_with_metadata`].
+    ///
+    /// ## Errors
+    /// Any error from [`Store::deser`], or from deserialising the metadata.
+    pub fn deser_with_metadata(bytes: &[u8]) -> Result<(Self, Option<Self>), StoreSerError> {
+        let (store, metadata, _) =
+            Self::deser_inner(bytes, DEFAULT_MAX_DEPTH, usize::MAX, false, &mut vec![])?;
+        Ok((store, metadata))
+    }
+
+    ///Deserialises a store like [`Store::deser`], but decompresses its body into `buf` (clearing it first) rather than a new buffer, so that the allocation for the body can be reused when deserialising lots of stores one after another. Uncompressed bodies are read straight from `bytes`, leaving `buf` alone.
+    ///
+    /// Only that one buffer is reused - this isn't an arena, as every key, [`Value::String`] and [`Value::Binary`] owns its own allocation and still allocates as it is read. In the `store` benchmark, the time is dominated by reading the values and decompressing, so this is about as fast as [`Store::deser`].
+    ///
+    /// ## Errors
+    /// Any error from [`Store::deser`].
+    pub fn deser_in(bytes: &[u8], buf: &mut Vec<u8>) -> Result<Self, StoreSerError> {
+        Ok(Self::deser_inner(bytes, DEFAULT_MAX_DEPTH, usize::MAX, false, buf)?.0)
+    }
+
+    ///Deserialises a store from the start of `bytes` like [`Store::deser`], also returning how many bytes it used so that whatever comes after it can be read.
+    pub(crate) fn deser_framed(bytes: &[u8]) -> Result<(Self, usize), StoreSerError> {
+        let (store, _, used) =
+            Self::deser_inner(bytes, DEFAULT_MAX_DEPTH, usize::MAX, false, &mut vec![])?;
+        Ok((store, used))
+    }
+
+    ///Deserialises every store from a buffer of serialised stores which have been appended one after another, eg. in a file which has had stores appended to it.
+    ///
+    /// Stores don't record their own length, but each one works out where it ends as it is read (see [`Store::deser_strict`]), so the buffer is read in one pass with the next store starting straight after the last.
+    ///
+    /// ## Errors
+    /// - [`StoreSerError::ExpectedMagicBytes`] if `bytes` doesn't start with the magic bytes.
+    /// - [`StoreSerError::TrailingData`] with the number of bytes left over if something which isn't a store comes after a store.
+    /// - Any error from [`Store::deser`] for the first store which fails to deserialise.
+    pub fn deser_all(bytes: &[u8]) -> Result<Vec<Self>, StoreSerError> {
+        const MAGIC_BYTES: &[u8] = b"SOURISDB";
+
+        let mut stores = vec![];
+        let mut body = vec![];
+        let mut rest = bytes;
+        while !rest.is_empty() {
+            if !rest.starts_with(MAGIC_BYTES) {
+                return Err(if stores.is_empty() {
+                    StoreSerError::ExpectedMagicBytes
+                } else {
+                    StoreSerError::TrailingData(rest.len())
+                });
+            }
+
+            let (store, _, used) =
+                Self::deser_inner(rest, DEFAULT_MAX_DEPTH, usize::MAX, false, &mut body)?;
+            stores.push(store);
+            rest = &rest[used..];
+        }
+
+        Ok(stores)
+    }
+
+    ///Reads everything before the values, returning them (and any expiry times) alongside the header. Compressed bodies are decompressed into `body`, while uncompressed ones are borrowed straight from `bytes` rather than copied. Either way, the body can't be longer than `max_len`. Any metadata is limited to `max_depth` levels of nesting, like the values.
+    fn deser_header<'a>(
+        bytes: &'a [u8],
+        body: &'a mut Vec<u8>,
+        max_depth: usize,
+        max_len: usize,
+    ) -> Result<(StoreHeader, &'a [u8]), StoreSerError> {
+        let input = bytes;
+        let mut bytes = Cursor::new(&input);
         {
             let Some(magic_bytes) = bytes.read_exact() else {
                 return Err(StoreSerError::NotEnoughBytes);
@@ -163,6 +796,26 @@ impl Store {
         let Some(compression_ty) = bytes.next().copied() else {
             return Err(StoreSerError::NotEnoughBytes);
         };
+        if (compression_ty & VERSIONED_FLAG) > 0 {
+            let Some(version) = bytes.next().copied() else {
+                return Err(StoreSerError::NotEnoughBytes);
+            };
+            if version > FORMAT_VERSION {
+                return Err(StoreSerError::UnsupportedFormatVersion(version));
+            }
+        }
+        let metadata = if (compression_ty & METADATA_FLAG) > 0 {
+            let len: usize = Integer::deser(SignedState::Unsigned, &mut bytes)?.try_into()?;
+            let val = Value::deser_exact_with_max_depth(&mut bytes, len, None, max_depth)?;
+            let ty = val.as_ty();
+            let Some(metadata) = val.to_map() else {
+                return Err(StoreSerError::ExpectedMap(ty));
+            };
+            Some(Self(metadata, HashMap::new()))
+        } else {
+            None
+        };
+
         let is_huffman_encoded = (compression_ty & 0b1000_0000) > 0;
         let huffman = if is_huffman_encoded {
             Some(Huffman::deser(&mut bytes)?)
@@ -170,15 +823,217 @@ impl Store {
             None
         };
 
-        let compression_ty = CompressionType::try_from(compression_ty & 0b1111)?;
-        let uncompressed_bytes = Self::decompress(bytes.as_ref(), compression_ty)?;
+        let has_expiries = (compression_ty & EXPIRY_FLAG) > 0;
+        let compression_ty = CompressionType::try_from(compression_ty & 0b0111)?;
+        let rest = &input[bytes.pos()..];
+        let (body, consumed) = if compression_ty == CompressionType::None {
+            if rest.len() > max_len {
+                return Err(StoreSerError::DecompressedTooLarge(max_len));
+            }
+            (rest, rest.len())
+        } else {
+            let consumed = Self::decompress_into(rest, compression_ty, body, max_len)?;
+            (body.as_slice(), consumed)
+        };
+
+        Ok((
+            StoreHeader {
+                metadata,
+                huffman,
+                has_expiries,
+                trailing: rest.len() - consumed,
+                compressed: compression_ty != CompressionType::None,
+            },
+            body,
+        ))
+    }
+
+    fn deser_inner(
+        bytes: &[u8],
+        max_depth: usize,
+        max_len: usize,
+        strict: bool,
+        body: &mut Vec<u8>,
+    ) -> Result<(Self, Option<Self>, usize), StoreSerError> {
+        let (
+            StoreHeader {
+                metadata,
+                huffman,
+                has_expiries,
+                trailing,
+                compressed,
+            },
+            body,
+        ) = Self::deser_header(bytes, body, max_depth, max_len)?;
+        if strict && trailing > 0 {
+            return Err(StoreSerError::TrailingData(trailing));
+        }
+        let mut uncompressed_bytes = Cursor::new(&body);
 
-        let val = Value::deser(&mut Cursor::new(&uncompressed_bytes), huffman.as_ref())?;
+        let val =
+            Value::deser_with_max_depth(&mut uncompressed_bytes, huffman.as_ref(), max_depth)?;
         let ty = val.as_ty();
         let Some(map) = val.to_map() else {
             return Err(StoreSerError::ExpectedMap(ty));
         };
-        Ok(Self(map))
+
+        let mut expiries = HashMap::new();
+        if has_expiries {
+            let val = Value::deser_with_max_depth(&mut uncompressed_bytes, huffman.as_ref(), 1)?;
+            let ty = val.as_ty();
+            let Some(raw_expiries) = val.to_map() else {
+                return Err(StoreSerError::ExpectedMap(ty));
+            };
+
+            for (k, v) in raw_expiries {
+                let found = v.as_ty();
+                let Some(t) = v.to_timestamp() else {
+                    return Err(StoreSerError::Value(ValueSerError::UnexpectedValueType(
+                        found,
+                        ValueTy::Timestamp,
+                    )));
+                };
+                expiries.insert(k, t);
+            }
+        }
+
+        if strict && !uncompressed_bytes.is_finished() {
+            return Err(StoreSerError::TrailingData(
+                uncompressed_bytes.items_remaining(),
+            ));
+        }
+
+        //the body of an uncompressed store is the rest of the input, so whatever it didn't use is left over
+        let used = if compressed {
+            bytes.len() - trailing
+        } else {
+            bytes.len() - uncompressed_bytes.items_remaining()
+        };
+
+        Ok((Self(map, expiries), metadata, used))
+    }
+
+    ///Deserialises a store one entry at a time, passing each entry to `visitor` as soon as it has been read rather than building up a whole [`Store`]. This means that entries can be filtered or indexed without all of them being kept in memory at once.
+    ///
+    /// The body of the store is still decompressed all at once before any entries are read. Any metadata or expiry times are skipped, and nesting is limited like [`Store::deser`].
+    ///
+    /// ```rust
+    /// use sourisdb::{store::Store, values::Value};
+    ///
+    /// let store = Store::from_json(serde_json::json!({"a": 1, "b": 2, "c": "three"}));
+    /// let bytes = store.ser().unwrap();
+    ///
+    /// let mut integers = 0;
+    /// Store::deser_visit(&bytes, &mut |_: &str, v: Value| {
+    ///     if matches!(v, Value::Integer(_)) {
+    ///         integers += 1;
+    ///     }
+    /// })
+    /// .unwrap();
+    /// assert_eq!(integers, 2);
+    /// ```
+    ///
+    /// ## Errors
+    /// - [`StoreSerError::ExpectedMap`] if the store doesn't hold a map.
+    /// - Any error from [`Store::deser`]. If an entry fails to deserialise, `visitor` will already have been given every entry before it.
+    pub fn deser_visit(bytes: &[u8], visitor: &mut impl StoreVisitor) -> Result<(), StoreSerError> {
+        let mut body = vec![];
+        let (StoreHeader { huffman, .. }, body) =
+            Self::deser_header(bytes, &mut body, DEFAULT_MAX_DEPTH, usize::MAX)?;
+        let mut body = Cursor::new(&body);
+
+        let byte = body.next().copied().ok_or(StoreSerError::NotEnoughBytes)?;
+        let ty = ValueTy::try_from((byte & 0b1111_0000) >> 4)?;
+        if ty != ValueTy::Map {
+            return Err(StoreSerError::ExpectedMap(ty));
+        }
+
+        let len = Value::deser_array_or_map_len(byte, &mut body, ValueTy::Map)?;
+        for _ in 0..len {
+            let key = Value::deser_string(&mut body, huffman.as_ref())?;
+            //the store itself is one level
+            let value =
+                Value::deser_with_max_depth(&mut body, huffman.as_ref(), DEFAULT_MAX_DEPTH - 1)?;
+            visitor.visit_entry(&key, value);
+        }
+
+        Ok(())
+    }
+
+    ///Reads a store in from the file at `path`, and then deserialises it using [`Store::deser`].
+    ///
+    /// ## Errors
+    /// - [`StoreSerError::IO`] if the file cannot be read.
+    /// - Any error from [`Store::deser`].
+    #[cfg(feature = "std")]
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, StoreSerError> {
+        let bytes = std::fs::read(path)?;
+        Self::deser(&bytes)
+    }
+
+    ///Serialises the store using [`Store::ser`], and then writes it out to the file at `path`, creating it if it doesn't exist.
+    ///
+    /// The bytes are first written to a sibling file (`path` with the process ID, a counter and `.tmp` appended, so that concurrent saves never share one), which is synced to disk and then renamed over `path`. On Unix, the directory is then synced too so that the rename itself survives a crash. This means that if the process dies mid-write, `path` will either contain the old store or the new one - never half of one.
+    ///
+    /// ## Errors
+    /// - Any error from [`Store::ser`].
+    /// - [`StoreSerError::IO`] if the temporary file cannot be written to or renamed, or the directory cannot be synced.
+    #[cfg(feature = "std")]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), StoreSerError> {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        static TMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let path = path.as_ref();
+        let mut tmp_file_name = path.file_name().map(ToOwned::to_owned).unwrap_or_default();
+        tmp_file_name.push(format!(
+            ".{}-{}.tmp",
+            std::process::id(),
+            TMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        self.save_via(path, &path.with_file_name(tmp_file_name))
+    }
+
+    ///Does the work for [`Store::save`], writing to `tmp_path` first.
+    #[cfg(feature = "std")]
+    fn save_via(
+        &self,
+        path: &std::path::Path,
+        tmp_path: &std::path::Path,
+    ) -> Result<(), StoreSerError> {
+        use std::io::Write;
+
+        let bytes = self.ser()?;
+
+        let write_tmp = || -> std::io::Result<()> {
+            let mut file = std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(tmp_path)?;
+            file.write_all(&bytes)?;
+            file.sync_all()
+        };
+        if let Err(e) = write_tmp() {
+            let _ = std::fs::remove_file(tmp_path);
+            return Err(e.into());
+        }
+
+        if let Err(e) = std::fs::rename(tmp_path, path) {
+            let _ = std::fs::remove_file(tmp_path);
+            return Err(e.into());
+        }
+
+        #[cfg(unix)]
+        {
+            let dir = match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => std::path::Path::new("."),
+            };
+            std::fs::File::open(dir)?.sync_all()?;
+        }
+
+        Ok(())
     }
 
     pub fn from_json_bytes(json: &[u8]) -> Result<Self, StoreSerError> {
@@ -201,147 +1056,3963 @@ impl Store {
     }
 
     ///fails if integer out of range, or float is NaN or infinite
+    ///
+    /// If the only key in the store is [`JSON_KEY`], the value under it is returned by itself - use [`Store::to_json_object`] to treat that key like any other.
     #[must_use]
-    pub fn to_json(mut self) -> Option<SJValue> {
+    pub fn to_json(self) -> Option<SJValue> {
+        self.to_json_with(FloatFormat::default())
+    }
+
+    ///Converts the store into JSON like [`Store::to_json`], but with floats rounded according to `float_format` - see [`Value::convert_to_json_with`].
+    #[must_use]
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_json_with(mut self, float_format: FloatFormat) -> Option<SJValue> {
         if self.len() == 1 {
-            if let Some(v) = self.0.remove("JSON") {
-                return v.convert_to_json();
+            if let Some(v) = self.0.remove(JSON_KEY) {
+                return v.convert_to_json_with(float_format);
             }
         }
 
+        self.into_json_object_with(float_format)
+    }
+
+    ///Converts the store into a JSON object, without any special handling for [`JSON_KEY`] - so a store which legitimately has a key named `"JSON"` always keeps it.
+    ///
+    /// Fails in the same cases as [`Store::to_json`].
+    #[must_use]
+    pub fn to_json_object(self) -> Option<SJValue> {
+        self.into_json_object_with(FloatFormat::default())
+    }
+
+    fn into_json_object_with(self, float_format: FloatFormat) -> Option<SJValue> {
         Some(SJValue::Object(
             self.0
                 .into_iter()
-                .map(|(k, v)| v.convert_to_json().map(|v| (k, v)))
+                .map(|(k, v)| v.convert_to_json_with(float_format).map(|v| (k, v)))
                 .collect::<Option<_>>()?,
         ))
     }
 
-    #[must_use]
-    pub fn from_json(val: SJValue) -> Self {
-        Self(match Value::convert_from_json(val) {
-            Value::Map(m) => m,
-            v => {
-                let mut map = HashMap::new();
-                map.insert("JSON".into(), v);
-                map
+    ///Works out an [RFC 6902](https://datatracker.ietf.org/doc/html/rfc6902) JSON Patch which turns this store into `other`, for sending changes to web clients. The patch is an array of `add`, `remove` and `replace` operations, with paths given as JSON Pointers into the stores as objects (see [`Store::to_json_object`]) - so a store holding an array has its elements at `/JSON/0`, `/JSON/1` and so on.
+    ///
+    /// Maps are compared key by key and arrays index by index, so only the parts which changed are in the patch. Elements added to or removed from the end of an array are added or removed one at a time, and anything else which differs is replaced.
+    ///
+    /// ## Errors
+    /// [`StoreSerError::UnableToConvertToJson`] if either store can't be converted to JSON.
+    pub fn diff_json_patch(&self, other: &Store) -> Result<SJValue, StoreSerError> {
+        fn diff(path: &str, a: &SJValue, b: &SJValue, ops: &mut Vec<SJValue>) {
+            match (a, b) {
+                (SJValue::Object(a), SJValue::Object(b)) => {
+                    let mut removed = a.keys().filter(|k| !b.contains_key(*k)).collect::<Vec<_>>();
+                    removed.sort_unstable();
+                    for k in removed {
+                        ops.push(json!({"op": "remove", "path": json_pointer_push(path, k)}));
+                    }
+
+                    let mut keys = b.keys().collect::<Vec<_>>();
+                    keys.sort_unstable();
+                    for k in keys {
+                        let child = json_pointer_push(path, k);
+                        match a.get(k) {
+                            Some(a) => diff(&child, a, &b[k], ops),
+                            None => ops.push(json!({"op": "add", "path": child, "value": b[k]})),
+                        }
+                    }
+                }
+                (SJValue::Array(a), SJValue::Array(b)) => {
+                    for (i, (a, b)) in a.iter().zip(b).enumerate() {
+                        diff(&json_pointer_push(path, &i.to_string()), a, b, ops);
+                    }
+                    //removing from the end first, so that the indices of the elements still to be removed don't change
+                    for i in (b.len()..a.len()).rev() {
+                        ops.push(json!({"op": "remove", "path": json_pointer_push(path, &i.to_string())}));
+                    }
+                    for (i, v) in b.iter().enumerate().skip(a.len()) {
+                        ops.push(json!({"op": "add", "path": json_pointer_push(path, &i.to_string()), "value": v}));
+                    }
+                }
+                (a, b) if a != b => ops.push(json!({"op": "replace", "path": path, "value": b})),
+                _ => {}
             }
-        })
+        }
+
+        let to_json = |store: &Store| {
+            store
+                .clone()
+                .to_json_object()
+                .ok_or(StoreSerError::UnableToConvertToJson)
+        };
+
+        let mut ops = vec![];
+        diff("", &to_json(self)?, &to_json(other)?, &mut ops);
+        Ok(SJValue::Array(ops))
     }
-}
 
-impl TryFrom<Value> for Store {
-    type Error = StoreSerError;
+    ///Applies an [RFC 6902](https://datatracker.ietf.org/doc/html/rfc6902) JSON Patch to the store, eg. one from [`Store::diff_json_patch`]. Every operation is supported (`add`, `remove`, `replace`, `move`, `copy` and `test`), with paths given as JSON Pointers into the store as an object (see [`Store::to_json_object`]). Values in the patch are converted using [`Value::convert_from_json`].
+    ///
+    /// The patch is applied atomically - if any operation fails, the store is left unchanged. Like [`Store::insert`] and [`Store::remove`], any top-level key whose value is removed, replaced or moved loses its expiry time.
+    ///
+    /// ## Errors
+    /// - [`StoreSerError::JsonPatchTestFailed`] with the path if a `test` operation found a different value.
+    /// - [`StoreSerError::InvalidJsonPatch`] if the patch is malformed - eg. it isn't an array, an operation is unknown or missing a field, a pointer doesn't start with `/`, or a value is moved into itself.
+    /// - [`StoreSerError::MissingKey`] with the path if a path doesn't exist, or goes through a value which isn't a map or an array.
+    /// - [`StoreSerError::IndexOutOfBounds`] if an array index is past the end of the array.
+    /// - [`StoreSerError::ExpectedMap`] if the whole store is replaced with something other than a map.
+    pub fn apply_json_patch(&mut self, patch: &SJValue) -> Result<(), StoreSerError> {
+        let invalid = |msg: &str| StoreSerError::InvalidJsonPatch(msg.to_string());
 
-    fn try_from(value: Value) -> Result<Self, Self::Error> {
-        let ty = value.as_ty();
-        let Some(db) = value.to_map() else {
-            return Err(StoreSerError::ExpectedMap(ty));
+        let Some(ops) = patch.as_array() else {
+            return Err(invalid("expected an array of operations"));
         };
-        Ok(Self(db))
-    }
-}
 
-impl Deref for Store {
-    type Target = HashMap<String, Value>;
+        //top-level keys whose values were removed or overwritten, which lose their expiry times like with `Store::insert` and `Store::remove` - `None` if the whole store was
+        let mut replaced_keys = Some(HashSet::new());
+        let mut replaced = |tokens: &[String]| match (tokens.first(), &mut replaced_keys) {
+            (None, _) => replaced_keys = None,
+            (Some(key), Some(keys)) if tokens.len() == 1 => {
+                keys.insert(key.clone());
+            }
+            _ => {}
+        };
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
-impl DerefMut for Store {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
-    }
-}
+        let mut root = Value::Map(self.0.clone());
+        for op in ops {
+            let field = |name: &str| {
+                op.get(name)
+                    .and_then(SJValue::as_str)
+                    .ok_or_else(|| StoreSerError::InvalidJsonPatch(format!("missing {name:?}")))
+            };
+            let value = || {
+                op.get("value")
+                    .cloned()
+                    .map(Value::convert_from_json)
+                    .ok_or_else(|| invalid("missing \"value\""))
+            };
+
+            let pointer = field("path")?;
+            let tokens = json_pointer_tokens(pointer)?;
+            match field("op")? {
+                "add" => {
+                    json_patch_add(&mut root, pointer, &tokens, value()?)?;
+                    replaced(&tokens);
+                }
+                "remove" => {
+                    json_patch_remove(&mut root, pointer, &tokens)?;
+                    replaced(&tokens);
+                }
+                "replace" => {
+                    //the whole store always exists, so replacing it is just adding over it
+                    if !tokens.is_empty() {
+                        json_patch_remove(&mut root, pointer, &tokens)?;
+                    }
+                    json_patch_add(&mut root, pointer, &tokens, value()?)?;
+                    replaced(&tokens);
+                }
+                "move" => {
+                    let from = field("from")?;
+                    let from_tokens = json_pointer_tokens(from)?;
+                    if tokens.len() > from_tokens.len() && tokens.starts_with(&from_tokens) {
+                        return Err(invalid("cannot move a value into itself"));
+                    }
+                    let moved = json_patch_remove(&mut root, from, &from_tokens)?;
+                    json_patch_add(&mut root, pointer, &tokens, moved)?;
+                    replaced(&from_tokens);
+                    replaced(&tokens);
+                }
+                "copy" => {
+                    let from = field("from")?;
+                    let copied =
+                        json_patch_get(&mut root, from, &json_pointer_tokens(from)?)?.clone();
+                    json_patch_add(&mut root, pointer, &tokens, copied)?;
+                    replaced(&tokens);
+                }
+                "test" => {
+                    if *json_patch_get(&mut root, pointer, &tokens)? != value()? {
+                        return Err(StoreSerError::JsonPatchTestFailed(pointer.to_string()));
+                    }
+                }
+                op => {
+                    return Err(StoreSerError::InvalidJsonPatch(format!(
+                        "unknown op {op:?}"
+                    )))
+                }
+            }
+        }
+
+        let ty = root.as_ty();
+        let Some(map) = root.to_map() else {
+            return Err(StoreSerError::ExpectedMap(ty));
+        };
+        self.0 = map;
+        match replaced_keys {
+            Some(keys) => self
+                .1
+                .retain(|k, _| !keys.contains(k) && self.0.contains_key(k)),
+            None => self.1.clear(),
+        }
+        Ok(())
+    }
+
+    ///Converts the store into indented JSON using [`Store::to_json`], which is useful for debugging as it is more widely readable than the [`Display`] implementation.
+    ///
+    /// ## Errors
+    /// - [`StoreSerError::UnableToConvertToJson`] if [`Store::to_json`] fails.
+    /// - [`StoreSerError::SerdeJson`] if `serde_json` fails to write the JSON out.
+    pub fn to_json_string_pretty(&self) -> Result<String, StoreSerError> {
+        let json = self
+            .clone()
+            .to_json()
+            .ok_or(StoreSerError::UnableToConvertToJson)?;
+        Ok(serde_json::to_string_pretty(&json)?)
+    }
+
+    ///Converts JSON into a store like [`Store::from_json`], normalising all of the values (however deeply nested) according to `options`.
+    #[must_use]
+    pub fn from_json_with_options(val: SJValue, options: IngestOptions) -> Self {
+        if val.is_null() && options.null_policy == NullPolicy::Skip {
+            return Self::default();
+        }
+        Self::from_converted_json(options.normalise(Value::convert_from_json(val)))
+    }
+
+    #[must_use]
+    pub fn from_json(val: SJValue) -> Self {
+        Self::from_converted_json(Value::convert_from_json(val))
+    }
+
+    ///Wraps any value which isn't a map under [`JSON_KEY`].
+    fn from_converted_json(val: Value) -> Self {
+        let map = match val {
+            Value::Map(m) => m,
+            v => {
+                let mut map = HashMap::new();
+                map.insert(JSON_KEY.into(), v);
+                map
+            }
+        };
+        Self(map, HashMap::new())
+    }
+}
+
+impl Store {
+    ///Gets the array held by the store, if the store only contains a [`Value::Array`] under [`JSON_KEY`] - this is how [`Store::from_json`] stores JSON arrays.
+    #[must_use]
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        if self.0.len() == 1 {
+            self.0.get(JSON_KEY).and_then(Value::as_array)
+        } else {
+            None
+        }
+    }
+
+    ///Mutably gets the array held by the store - see [`Store::as_array`].
+    #[must_use]
+    pub fn as_mut_array(&mut self) -> Option<&mut Vec<Value>> {
+        if self.0.len() == 1 {
+            self.0.get_mut(JSON_KEY).and_then(Value::as_mut_array)
+        } else {
+            None
+        }
+    }
+
+    ///Takes the array held by the store - see [`Store::as_array`].
+    #[must_use]
+    pub fn to_array(mut self) -> Option<Vec<Value>> {
+        if self.0.len() == 1 {
+            self.0.remove(JSON_KEY).and_then(Value::to_array)
+        } else {
+            None
+        }
+    }
+
+    ///Takes the collection out of the store, as a [`StoreParts::Array`] if the store holds an array (see [`Store::as_array`]) and as a [`StoreParts::Map`] otherwise - for handing the values to code which wants a plain [`Vec`] or [`HashMap`].
+    ///
+    /// Expiry times (see [`Store::insert_with_ttl`]) are thrown away.
+    #[must_use]
+    pub fn into_parts(mut self) -> StoreParts {
+        if self.as_array().is_some() {
+            if let Some(Value::Array(a)) = self.0.remove(JSON_KEY) {
+                return StoreParts::Array(a);
+            }
+        }
+        StoreParts::Map(self.0)
+    }
+
+    ///Creates a store holding an array - see [`Store::as_array`].
+    #[must_use]
+    pub fn from_array(array: Vec<Value>) -> Self {
+        let mut map = HashMap::new();
+        map.insert(JSON_KEY.into(), Value::Array(array));
+        Self(map, HashMap::new())
+    }
+
+    ///Creates a store holding an array (see [`Store::as_array`]) of every [`Value::Integer`] in `range`, in order - handy for generating sample data.
+    #[must_use]
+    pub fn from_range(range: Range<i64>) -> Self {
+        Self::from_array(range.map(Value::from).collect())
+    }
+
+    ///Splits the store's array (see [`Store::as_array`]) in two at `at`, returning a new store holding the elements `[at..]` - much like [`Vec::split_off`].
+    ///
+    /// ## Errors
+    /// - [`StoreSerError::ExpectedArray`] if the store doesn't hold an array.
+    /// - [`StoreSerError::IndexOutOfBounds`] if `at` is greater than the length of the array.
+    pub fn split_off(&mut self, at: usize) -> Result<Store, StoreSerError> {
+        let Some(array) = self.as_mut_array() else {
+            return Err(StoreSerError::ExpectedArray);
+        };
+        if at > array.len() {
+            return Err(StoreSerError::IndexOutOfBounds(at, array.len()));
+        }
+
+        Ok(Self::from_array(array.split_off(at)))
+    }
+
+    ///Consumes the store, returning a new store which only holds this one as a [`Value::Map`] under `key`.
+    ///
+    /// If the store holds an array (see [`Store::as_array`]), the array is nested directly as a [`Value::Array`] rather than as a map holding [`JSON_KEY`].
+    #[must_use]
+    pub fn nest(self, key: String) -> Store {
+        let value = if self.as_array().is_some() {
+            Value::Array(self.to_array().unwrap_or_default())
+        } else {
+            Value::Map(self.0)
+        };
+
+        let mut map = HashMap::new();
+        map.insert(key, value);
+        Self(map, HashMap::new())
+    }
+
+    ///Removes the [`Value::Map`] stored under `key` and moves all of its entries into the store, flattening it by one level - the inverse of [`Store::nest`].
+    ///
+    /// ## Errors
+    /// If any of these happen, the store is left unchanged.
+    /// - [`StoreSerError::MissingKey`] if there is nothing stored under `key`.
+    /// - [`StoreSerError::ExpectedMap`] if the value under `key` isn't a map.
+    /// - [`StoreSerError::KeyAlreadyExists`] if any key in the nested map already exists in the store. The nested map may contain `key` itself, as that is removed first.
+    pub fn unnest(&mut self, key: &str) -> Result<(), StoreSerError> {
+        let Some(value) = self.0.get(key) else {
+            return Err(StoreSerError::MissingKey(key.to_string()));
+        };
+        let Some(inner) = value.as_map() else {
+            return Err(StoreSerError::ExpectedMap(value.as_ty()));
+        };
+        if let Some(k) = inner
+            .keys()
+            .find(|k| k.as_str() != key && self.0.contains_key(k.as_str()))
+        {
+            return Err(StoreSerError::KeyAlreadyExists(k.clone()));
+        }
+
+        if let Some(inner) = self.remove(key).and_then(Value::to_map) {
+            for (k, v) in inner {
+                self.insert(k, v);
+            }
+        }
+        Ok(())
+    }
+
+    ///Compares two stores, ignoring the order of elements if both stores hold arrays (see [`Store::as_array`]) - the arrays are compared as multisets, so repeated elements must be repeated the same number of times in both.
+    ///
+    /// Otherwise, this is the same as [`PartialEq::eq`].
+    #[must_use]
+    pub fn unordered_eq(&self, other: &Store) -> bool {
+        let (Some(a), Some(b)) = (self.as_array(), other.as_array()) else {
+            return self == other;
+        };
+        if a.len() != b.len() {
+            return false;
+        }
+
+        let mut frequencies: HashMap<&Value, isize> = HashMap::new();
+        for v in a {
+            *frequencies.entry(v).or_default() += 1;
+        }
+        for v in b {
+            *frequencies.entry(v).or_default() -= 1;
+        }
+        frequencies.values().all(|n| *n == 0)
+    }
+
+    ///Upserts the elements of `other`'s array into this store's array (see [`Store::as_array`]), matching [`Value::Map`] elements on the value they hold for `key`.
+    ///
+    /// - If an element in `self` holds the same value for `key`, it is replaced by the element from `other`.
+    /// - If no element matches, the element from `other` is appended.
+    /// - If the element from `other` doesn't hold `key` (or isn't a map), it is appended if `append_missing_key` is `true`, and otherwise an error is returned.
+    ///
+    /// ## Errors
+    /// - [`StoreSerError::ExpectedArray`] if either store doesn't hold an array.
+    /// - [`StoreSerError::MissingKey`] if an element from `other` doesn't hold `key` and `append_missing_key` is `false`. Elements before that one will already have been merged.
+    pub fn merge_array_by_key(
+        &mut self,
+        other: Store,
+        key: &str,
+        append_missing_key: bool,
+    ) -> Result<(), StoreSerError> {
+        let Some(other) = other.to_array() else {
+            return Err(StoreSerError::ExpectedArray);
+        };
+        let Some(array) = self.as_mut_array() else {
+            return Err(StoreSerError::ExpectedArray);
+        };
+
+        for element in other {
+            let Some(id) = element.as_map().and_then(|m| m.get(key)).cloned() else {
+                if append_missing_key {
+                    array.push(element);
+                    continue;
+                }
+                return Err(StoreSerError::MissingKey(key.to_string()));
+            };
+
+            let existing = array
+                .iter_mut()
+                .find(|v| v.as_map().and_then(|m| m.get(key)) == Some(&id));
+            match existing {
+                Some(existing) => *existing = element,
+                None => array.push(element),
+            }
+        }
+
+        Ok(())
+    }
+
+    ///Groups the [`Value::Map`] elements of this store's array (see [`Store::as_array`]) by the value they hold for `key`, returning a store which maps each distinct value to an array of the elements holding it, in the order they appeared.
+    ///
+    /// [`Value::String`]s are used as keys as-is, and all other values are converted to keys using their [`Display`] implementation - so eg. the string `"1"` and the integer `1` end up in the same group.
+    ///
+    /// Elements which don't hold `key` (or aren't maps) go in the group named by `missing_bucket`, or cause an error if it is [`None`].
+    ///
+    /// ## Errors
+    /// - [`StoreSerError::ExpectedArray`] if the store doesn't hold an array.
+    /// - [`StoreSerError::MissingKey`] if an element doesn't hold `key` and `missing_bucket` is [`None`].
+    pub fn group_by(
+        &self,
+        key: &str,
+        missing_bucket: Option<&str>,
+    ) -> Result<Store, StoreSerError> {
+        let Some(array) = self.as_array() else {
+            return Err(StoreSerError::ExpectedArray);
+        };
+
+        let mut groups: HashMap<String, Value> = HashMap::new();
+        for element in array {
+            let group = Self::group_key(element, key, missing_bucket)?;
+            if let Value::Array(a) = groups.entry(group).or_insert_with(|| Value::Array(vec![])) {
+                a.push(element.clone());
+            }
+        }
+
+        Ok(Self(groups, HashMap::new()))
+    }
+
+    ///Counts how many [`Value::Map`] elements of this store's array hold each distinct value for `key`, returning a store which maps each value to a [`Value::Integer`] count - a frequency table of the groups from [`Store::group_by`], which decides the keys and handles `missing_bucket` in the same way. The elements themselves are never cloned.
+    ///
+    /// ## Errors
+    /// Any error from [`Store::group_by`].
+    pub fn count_by(
+        &self,
+        key: &str,
+        missing_bucket: Option<&str>,
+    ) -> Result<Store, StoreSerError> {
+        let Some(array) = self.as_array() else {
+            return Err(StoreSerError::ExpectedArray);
+        };
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for element in array {
+            *counts
+                .entry(Self::group_key(element, key, missing_bucket)?)
+                .or_default() += 1;
+        }
+
+        Ok(Self(
+            counts
+                .into_iter()
+                .map(|(group, count)| (group, Value::from(count)))
+                .collect(),
+            HashMap::new(),
+        ))
+    }
+
+    ///Gets the name of the group `element` goes into for [`Store::group_by`] and [`Store::count_by`].
+    fn group_key(
+        element: &Value,
+        key: &str,
+        missing_bucket: Option<&str>,
+    ) -> Result<String, StoreSerError> {
+        Ok(match element.as_map().and_then(|m| m.get(key)) {
+            Some(Value::String(s)) => s.clone(),
+            Some(v) => v.to_string(),
+            None => missing_bucket
+                .ok_or_else(|| StoreSerError::MissingKey(key.to_string()))?
+                .to_string(),
+        })
+    }
+
+    ///Moves every entry from `other` into the store, returning every key which held different values in both stores, sorted by key. `strategy` decides which of those values is kept, and the other is returned in the [`Conflict`] so that nothing is lost silently.
+    ///
+    /// Keys which held equal values in both aren't conflicts. Expiry times (see [`Store::insert_with_ttl`]) are kept along with whichever value is kept.
+    pub fn merge_logged(&mut self, other: Store, strategy: MergeStrategy) -> Vec<Conflict> {
+        let Store(values, mut expiries) = other;
+        let mut conflicts = vec![];
+
+        for (key, theirs) in values {
+            let their_expiry = expiries.remove(&key);
+
+            let Some(ours) = self.0.get_mut(&key) else {
+                if let Some(expires_at) = their_expiry {
+                    self.1.insert(key.clone(), expires_at);
+                }
+                self.0.insert(key, theirs);
+                continue;
+            };
+            if *ours == theirs {
+                continue;
+            }
+
+            let (kept, discarded) = match strategy {
+                MergeStrategy::KeepOurs => (ours.clone(), theirs),
+                MergeStrategy::KeepTheirs => {
+                    match their_expiry {
+                        Some(expires_at) => self.1.insert(key.clone(), expires_at),
+                        None => self.1.remove(&key),
+                    };
+                    let discarded = core::mem::replace(ours, theirs.clone());
+                    (theirs, discarded)
+                }
+            };
+            conflicts.push(Conflict {
+                key,
+                kept,
+                discarded,
+            });
+        }
+
+        conflicts.sort_unstable_by(|a, b| a.key.cmp(&b.key));
+        conflicts
+    }
+
+    ///Inserts `v` under `k`, returning the value which was previously stored under `k` if there was one.
+    ///
+    /// This never treats any key specially (unlike [`Store::from_json`] with [`JSON_KEY`]), so it is always a plain overwrite.
+    pub fn replace(&mut self, k: String, v: Value) -> Option<Value> {
+        self.insert(k, v)
+    }
+
+    ///Removes every empty [`Value::Map`] and [`Value::Array`] from the store, however deeply nested. Containers which only held empty containers are then empty themselves, so they are removed too.
+    pub fn prune_empty(&mut self) {
+        fn prune(v: &mut Value) -> bool {
+            match v {
+                Value::Map(m) => {
+                    m.retain(|_, v| prune(v));
+                    !m.is_empty()
+                }
+                Value::Array(a) => {
+                    a.retain_mut(prune);
+                    !a.is_empty()
+                }
+                _ => true,
+            }
+        }
+
+        self.retain(|_, v| prune(v));
+    }
+
+    ///Counts how many values are at each nesting depth, where index `d` of the result holds the count of values at depth `d`. Top-level values are at depth `0`, and the values inside a [`Value::Map`] or [`Value::Array`] are one level deeper than it.
+    ///
+    /// This is useful for finding unexpectedly deep documents. An empty store gives an empty [`Vec`].
+    #[must_use]
+    pub fn depth_histogram(&self) -> Vec<usize> {
+        fn count(v: &Value, depth: usize, histogram: &mut Vec<usize>) {
+            if histogram.len() <= depth {
+                histogram.push(0);
+            }
+            histogram[depth] += 1;
+
+            match v {
+                Value::Map(m) => {
+                    for v in m.values() {
+                        count(v, depth + 1, histogram);
+                    }
+                }
+                Value::Array(a) => {
+                    for v in a {
+                        count(v, depth + 1, histogram);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut histogram = vec![];
+        for v in self.0.values() {
+            count(v, 0, &mut histogram);
+        }
+        histogram
+    }
+
+    ///Estimates how many bytes the store is using in memory, including space which has been allocated but not used yet (eg. the spare capacity of a [`Vec`]). This is usually much larger than the serialised size (see [`Store::ser`]), and is useful for finding which stores are using the most memory.
+    ///
+    /// The estimate counts the entries of every map at their full size along with the bytes owned by every key, [`Value::String`], [`Value::Binary`], [`Value::Array`] and [`Value::Map`], however deeply nested. It doesn't count the hash tables' control bytes or allocator overhead, and [`Value::JSON`]s are estimated from their length as text.
+    #[must_use]
+    pub fn memory_footprint(&self) -> usize {
+        use core::mem::size_of;
+
+        fn value_heap(v: &Value) -> usize {
+            match v {
+                Value::String(s) => s.capacity(),
+                Value::Binary(b) => b.capacity(),
+                Value::Array(a) => {
+                    a.capacity() * size_of::<Value>() + a.iter().map(value_heap).sum::<usize>()
+                }
+                Value::Map(m) => map_heap(m),
+                Value::JSON(j) => j.to_string().len(),
+                _ => 0,
+            }
+        }
+        fn map_heap(m: &HashMap<String, Value>) -> usize {
+            m.capacity() * size_of::<(String, Value)>()
+                + m.iter()
+                    .map(|(k, v)| k.capacity() + value_heap(v))
+                    .sum::<usize>()
+        }
+
+        size_of::<Self>()
+            + map_heap(&self.0)
+            + self.1.capacity() * size_of::<(String, NaiveDateTime)>()
+            + self.1.keys().map(String::capacity).sum::<usize>()
+    }
+
+    ///Gets the only entry in the store, for stores which are expected to hold exactly one entry (eg. the result of a query for one key).
+    ///
+    /// Arrays (see [`Store::as_array`]) are a single entry under [`JSON_KEY`], so that entry is returned.
+    ///
+    /// ## Errors
+    /// [`StoreSerError::ExpectedSingleEntry`] with the number of entries if the store is empty or holds more than one entry.
+    pub fn single_entry(&self) -> Result<(&String, &Value), StoreSerError> {
+        let mut entries = self.0.iter();
+        match (entries.next(), entries.next()) {
+            (Some(entry), None) => Ok(entry),
+            _ => Err(StoreSerError::ExpectedSingleEntry(self.0.len())),
+        }
+    }
+
+    ///Moves the value stored under `k` out of the store, without needing an owned key.
+    ///
+    /// If the store holds an array (see [`Store::as_array`]), `k` is instead parsed as an index into it, and the element there is removed by shifting all the later elements down - like [`Vec::remove`], this keeps the order but is `O(n)`. A `k` which isn't a valid index gives [`None`].
+    pub fn take(&mut self, k: &str) -> Option<Value> {
+        if let Some(array) = self.as_mut_array() {
+            let index: usize = k.parse().ok()?;
+            return (index < array.len()).then(|| array.remove(index));
+        }
+
+        self.remove(k)
+    }
+
+    ///Drops every entry whose key isn't in `keep`.
+    ///
+    /// For array stores (see [`Store::as_array`]), `keep` is instead parsed as a list of indices to keep - any that aren't valid indices are ignored, and the kept elements stay in their original order.
+    pub fn retain_keys(&mut self, keep: &[&str]) {
+        if let Some(array) = self.as_mut_array() {
+            let indices: Vec<usize> = keep.iter().filter_map(|k| k.parse().ok()).collect();
+            let mut index = 0;
+            array.retain(|_| {
+                let kept = indices.contains(&index);
+                index += 1;
+                kept
+            });
+            return;
+        }
+
+        self.retain(|k, _| keep.contains(&k.as_str()));
+    }
+
+    ///Removes every entry whose key starts with `prefix`, along with its expiry time, returning how many entries were removed - eg. `"session:"` to remove a whole namespace of keys.
+    pub fn remove_prefix(&mut self, prefix: &str) -> usize {
+        let before = self.0.len();
+        self.0.retain(|k, _| !k.starts_with(prefix));
+        self.1.retain(|k, _| !k.starts_with(prefix));
+        before - self.0.len()
+    }
+
+    ///Splits the store in two, returning a store with every entry for which `predicate` returns `true` and a store with the rest - eg. for splitting valid records from invalid ones. Expiry times go with their entries.
+    ///
+    /// If the store holds an array (see [`Store::as_array`]), the elements are split instead, with `predicate` given each element's index as its key. Both stores then hold arrays, with the elements in their original order.
+    pub fn partition(self, predicate: impl Fn(&str, &Value) -> bool) -> (Store, Store) {
+        if self.as_array().is_some() {
+            let (matching, rest): (Vec<_>, Vec<_>) = self
+                .to_array()
+                .unwrap_or_default()
+                .into_iter()
+                .enumerate()
+                .partition(|(i, v)| predicate(&i.to_string(), v));
+            let strip_indices = |a: Vec<(usize, Value)>| a.into_iter().map(|(_, v)| v).collect();
+            return (
+                Self::from_array(strip_indices(matching)),
+                Self::from_array(strip_indices(rest)),
+            );
+        }
+
+        let Store(values, mut expiries) = self;
+        let (mut matching, mut rest) = (Store::default(), Store::default());
+        for (k, v) in values {
+            let into = if predicate(&k, &v) {
+                &mut matching
+            } else {
+                &mut rest
+            };
+            if let Some(expires_at) = expiries.remove(&k) {
+                into.1.insert(k.clone(), expires_at);
+            }
+            into.0.insert(k, v);
+        }
+        (matching, rest)
+    }
+
+    ///Makes sure that no key is longer than `max_len` bytes, for exporting to systems which limit key lengths. Keys which are too long are handled according to `on_violation` - see [`LimitAction`]. Expiry times (see [`Store::insert_with_ttl`]) follow their keys.
+    ///
+    /// Keys are shortened on a character boundary, so a truncated key may be a few bytes shorter than `max_len`. If a truncated key would clash with another key, a suffix of `~1`, `~2` and so on is added to it (still within `max_len`). Keys are truncated in sorted order, so the same store always gives the same keys.
+    ///
+    /// ## Errors
+    /// - [`StoreSerError::KeyTooLong`] with the first (in sorted order) key which is too long, if `on_violation` is [`LimitAction::Error`].
+    /// - [`StoreSerError::KeyAlreadyExists`] with the clashing truncated key if `max_len` is too short to fit a suffix.
+    ///
+    /// In both cases, the store is left unchanged.
+    pub fn enforce_key_limits(
+        &mut self,
+        max_len: usize,
+        on_violation: LimitAction,
+    ) -> Result<(), StoreSerError> {
+        fn truncate(k: &str, max_len: usize) -> &str {
+            let mut end = max_len.min(k.len());
+            while !k.is_char_boundary(end) {
+                end -= 1;
+            }
+            &k[..end]
+        }
+
+        let mut too_long = self
+            .0
+            .keys()
+            .filter(|k| k.len() > max_len)
+            .cloned()
+            .collect::<Vec<_>>();
+        too_long.sort_unstable();
+
+        let renames = match on_violation {
+            LimitAction::Error => {
+                if let Some(k) = too_long.into_iter().next() {
+                    return Err(StoreSerError::KeyTooLong(k));
+                }
+                vec![]
+            }
+            LimitAction::Drop => {
+                for k in too_long {
+                    self.0.remove(&k);
+                    self.1.remove(&k);
+                }
+                vec![]
+            }
+            LimitAction::Truncate => {
+                let mut taken = self
+                    .0
+                    .keys()
+                    .filter(|k| k.len() <= max_len)
+                    .cloned()
+                    .collect::<HashSet<_>>();
+
+                let mut renames = vec![];
+                for k in too_long {
+                    let mut new_key = truncate(&k, max_len).to_string();
+                    let mut n = 1_usize;
+                    while taken.contains(&new_key) {
+                        let suffix = format!("~{n}");
+                        if suffix.len() > max_len {
+                            return Err(StoreSerError::KeyAlreadyExists(new_key));
+                        }
+                        new_key = format!("{}{suffix}", truncate(&k, max_len - suffix.len()));
+                        n += 1;
+                    }
+
+                    taken.insert(new_key.clone());
+                    renames.push((k, new_key));
+                }
+                renames
+            }
+        };
+
+        for (old, new) in renames {
+            if let Some(v) = self.0.remove(&old) {
+                self.0.insert(new.clone(), v);
+            }
+            if let Some(expires_at) = self.1.remove(&old) {
+                self.1.insert(new, expires_at);
+            }
+        }
+
+        Ok(())
+    }
+
+    ///Gets a clone of the value stored under `k` and converts it into a `T`, returning `Ok(None)` if there isn't one.
+    ///
+    /// ## Errors
+    /// Any error from converting the value into a `T` - for the built-in conversions that's [`ValueSerError::UnexpectedValueType`] if the value is a different type, or [`ValueSerError::IntegerSerError`] if an integer doesn't fit.
+    pub fn get_as<T: TryFrom<Value>>(&self, k: &str) -> Result<Option<T>, T::Error> {
+        self.0.get(k).cloned().map(T::try_from).transpose()
+    }
+
+    ///Gets a mutable reference to the value stored under `k`, first inserting the result of `f` if there isn't one. `f` is only called if `k` is missing.
+    pub fn get_or_insert_with(&mut self, k: String, f: impl FnOnce() -> Value) -> &mut Value {
+        if !self.0.contains_key(&k) {
+            self.1.remove(&k);
+        }
+        self.0.entry(k).or_insert_with(f)
+    }
+
+    ///Inserts `v` under `k`, returning the value which was there before. This shadows [`hashbrown::HashMap::insert`] so that any expiry time for `k` is cleared - the new value doesn't inherit the old one's TTL.
+    pub fn insert(&mut self, k: String, v: Value) -> Option<Value> {
+        self.1.remove(&k);
+        self.0.insert(k, v)
+    }
+
+    ///Removes the value stored under `k`, returning it if there was one. This shadows [`hashbrown::HashMap::remove`] so that any expiry time for `k` is removed too.
+    pub fn remove(&mut self, k: &str) -> Option<Value> {
+        self.1.remove(k);
+        self.0.remove(k)
+    }
+
+    ///Removes every entry, along with every expiry time. This shadows [`hashbrown::HashMap::clear`].
+    pub fn clear(&mut self) {
+        self.0.clear();
+        self.1.clear();
+    }
+
+    ///Removes the entry stored under `k`, returning its key and value if there was one. This shadows [`hashbrown::HashMap::remove_entry`] so that any expiry time for `k` is removed too.
+    pub fn remove_entry(&mut self, k: &str) -> Option<(String, Value)> {
+        self.1.remove(k);
+        self.0.remove_entry(k)
+    }
+
+    ///Keeps only the entries for which `f` returns `true`. This shadows [`hashbrown::HashMap::retain`] so that the expiry times of removed entries are removed too.
+    pub fn retain(&mut self, mut f: impl FnMut(&String, &mut Value) -> bool) {
+        let expiries = &mut self.1;
+        self.0.retain(|k, v| {
+            let keep = f(k, v);
+            if !keep {
+                expiries.remove(k);
+            }
+            keep
+        });
+    }
+
+    ///Removes every entry and returns them, along with every expiry time. This shadows [`hashbrown::HashMap::drain`].
+    pub fn drain(&mut self) -> hashbrown::hash_map::Drain<'_, String, Value> {
+        self.1.clear();
+        self.0.drain()
+    }
+
+    ///Inserts `v` under `k`, which expires `ttl` after `now` - see [`Store::purge_expired`]. Returns the value which was there before.
+    ///
+    /// If the expiry time would be out of range, it is clamped to [`NaiveDateTime::MAX`].
+    pub fn insert_with_ttl(
+        &mut self,
+        k: String,
+        v: Value,
+        ttl: TimeDelta,
+        now: NaiveDateTime,
+    ) -> Option<Value> {
+        let expires_at = now.checked_add_signed(ttl).unwrap_or(NaiveDateTime::MAX);
+        self.1.insert(k.clone(), expires_at);
+        self.0.insert(k, v)
+    }
+
+    ///Gets the time at which `k` expires, if it was inserted using [`Store::insert_with_ttl`].
+    ///
+    /// [`Store::insert`], [`Store::remove`] and [`Store::clear`] all clear expiry times. Entries can still be removed through other [`hashbrown::HashMap`] methods (eg. [`hashbrown::HashMap::retain`]) which leave their expiry times behind, but those are ignored everywhere as the key is no longer present.
+    #[must_use]
+    pub fn expiry(&self, k: &str) -> Option<NaiveDateTime> {
+        self.1.get(k).filter(|_| self.0.contains_key(k)).copied()
+    }
+
+    ///Stops `k` from expiring, returning the time it would have expired at.
+    pub fn clear_ttl(&mut self, k: &str) -> Option<NaiveDateTime> {
+        self.1.remove(k)
+    }
+
+    ///Removes every entry which expires at or before `now`, along with its expiry time. Returns how many entries were removed.
+    pub fn purge_expired(&mut self, now: NaiveDateTime) -> usize {
+        let mut removed = 0;
+        self.1.retain(|k, expires_at| {
+            //expiry times for keys which have been removed are dropped, rather than being applied to a key inserted later
+            if !self.0.contains_key(k) {
+                return false;
+            }
+            if *expires_at > now {
+                return true;
+            }
+
+            if self.0.remove(k).is_some() {
+                removed += 1;
+            }
+            false
+        });
+        removed
+    }
+
+    ///Keeps every key in the store, but replaces every value with a clone of `default` - useful for turning a document into an empty template.
+    ///
+    /// If `recursive` is `true`, [`Value::Map`]s and [`Value::Array`]s are kept and the values inside them are reset instead, so the whole shape of the document is kept.
+    pub fn reset_values(&mut self, default: &Value, recursive: bool) {
+        fn reset(v: &mut Value, default: &Value, recursive: bool) {
+            match v {
+                Value::Map(m) if recursive => {
+                    for v in m.values_mut() {
+                        reset(v, default, recursive);
+                    }
+                }
+                Value::Array(a) if recursive => {
+                    for v in a {
+                        reset(v, default, recursive);
+                    }
+                }
+                _ => *v = default.clone(),
+            }
+        }
+
+        for v in self.0.values_mut() {
+            reset(v, default, recursive);
+        }
+    }
+
+    ///Replaces every [`Value::Binary`] longer than `threshold` bytes with a [`Value::String`] noting how long it was (eg. `"<binary: 1024 bytes>"`), so that the store can be logged or exported without huge blobs in the output. Binaries inside [`Value::Map`]s and [`Value::Array`]s are replaced too.
+    ///
+    /// Returns how many binaries were replaced.
+    pub fn strip_large_binaries(&mut self, threshold: usize) -> usize {
+        fn strip(v: &mut Value, threshold: usize) -> usize {
+            match v {
+                Value::Binary(b) if b.len() > threshold => {
+                    *v = Value::String(format!("<binary: {} bytes>", b.len()));
+                    1
+                }
+                Value::Map(m) => m.values_mut().map(|v| strip(v, threshold)).sum(),
+                Value::Array(a) => a.iter_mut().map(|v| strip(v, threshold)).sum(),
+                _ => 0,
+            }
+        }
+
+        self.0.values_mut().map(|v| strip(v, threshold)).sum()
+    }
+
+    ///Inserts every entry of `other` into this store, with its key prefixed by `prefix` and [`PREFIX_SEPARATOR`] - so `"b"` joined with the prefix `"a"` becomes `"a.b"`. Expiry times (see [`Store::insert_with_ttl`]) follow their keys.
+    ///
+    /// ## Errors
+    /// [`StoreSerError::KeyAlreadyExists`] if any of the prefixed keys already exist in this store. This is checked before anything is inserted, so on an error the store is unchanged.
+    pub fn join_prefixed(&mut self, prefix: &str, other: Store) -> Result<(), StoreSerError> {
+        let Store(values, mut expiries) = other;
+        let prefixed = values
+            .into_iter()
+            .map(|(k, v)| {
+                let expires_at = expiries.remove(&k);
+                (format!("{prefix}{PREFIX_SEPARATOR}{k}"), v, expires_at)
+            })
+            .collect::<Vec<_>>();
+
+        if let Some((k, _, _)) = prefixed.iter().find(|(k, _, _)| self.0.contains_key(k)) {
+            return Err(StoreSerError::KeyAlreadyExists(k.clone()));
+        }
+
+        for (k, v, expires_at) in prefixed {
+            match expires_at {
+                Some(expires_at) => self.1.insert(k.clone(), expires_at),
+                None => self.1.remove(&k),
+            };
+            self.0.insert(k, v);
+        }
+        Ok(())
+    }
+
+    ///Flattens the store into pairs of keys and serialised values, for inserting into an external key-value store like Redis. Nested [`Value::Map`]s are flattened, with each level of key joined using `separator` - so `{"a": {"b": 1}}` with a separator of `"."` becomes `"a.b"` mapped to the bytes of `1`. The pairs are sorted by key.
+    ///
+    /// Every other value (including arrays and empty maps) is serialised as-is using [`Value::ser`] without huffman encoding, and can be read back using [`Value::deser`]. To read the pairs back into a store, see [`Store::from_kv_pairs`] - this only works if `separator` doesn't appear in any of the keys.
+    ///
+    /// ## Errors
+    /// Any error from [`Value::ser`].
+    pub fn to_kv_pairs(&self, separator: &str) -> Result<Vec<(String, Vec<u8>)>, StoreSerError> {
+        fn flatten(
+            key: String,
+            v: &Value,
+            separator: &str,
+            out: &mut Vec<(String, Vec<u8>)>,
+        ) -> Result<(), ValueSerError> {
+            match v {
+                Value::Map(m) if !m.is_empty() => {
+                    for (k, v) in m {
+                        flatten(format!("{key}{separator}{k}"), v, separator, out)?;
+                    }
+                }
+                v => out.push((key, v.ser(None)?)),
+            }
+            Ok(())
+        }
+
+        let mut pairs = vec![];
+        for (k, v) in &self.0 {
+            flatten(k.clone(), v, separator, &mut pairs)?;
+        }
+        pairs.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(pairs)
+    }
+
+    ///Rebuilds a store from pairs of keys and serialised values - the inverse of [`Store::to_kv_pairs`]. Each key is split on `separator`, with every part but the last becoming a nested [`Value::Map`].
+    ///
+    /// ## Errors
+    /// - [`StoreSerError::KeyAlreadyExists`] if the same key appears more than once.
+    /// - [`StoreSerError::ConflictingPath`] if a key is both a value and a prefix of another key, eg. `"a"` and `"a.b"`.
+    /// - Any error from [`Value::deser_exact`] if a value doesn't deserialise, or has bytes left over.
+    /// - [`StoreSerError::EmptySeparator`] if `separator` is empty.
+    pub fn from_kv_pairs(
+        pairs: impl IntoIterator<Item = (String, Vec<u8>)>,
+        separator: &str,
+    ) -> Result<Self, StoreSerError> {
+        if separator.is_empty() {
+            return Err(StoreSerError::EmptySeparator);
+        }
+
+        let mut leaves = HashSet::new();
+        let mut prefixes = HashSet::new();
+        let mut values = vec![];
+        for (key, bytes) in pairs {
+            if leaves.contains(&key) {
+                return Err(StoreSerError::KeyAlreadyExists(key));
+            }
+            if prefixes.contains(&key) {
+                return Err(StoreSerError::ConflictingPath(key));
+            }
+            for (i, _) in key.match_indices(separator) {
+                let prefix = &key[..i];
+                if leaves.contains(prefix) {
+                    return Err(StoreSerError::ConflictingPath(prefix.to_string()));
+                }
+                prefixes.insert(prefix.to_string());
+            }
+
+            let value = Value::deser_exact(&mut Cursor::new(&bytes), bytes.len(), None)?;
+            leaves.insert(key.clone());
+            values.push((key, value));
+        }
+
+        let mut map = HashMap::new();
+        for (key, value) in values {
+            let (parents, last) = match key.rsplit_once(separator) {
+                Some((parents, last)) => (Some(parents), last),
+                None => (None, key.as_str()),
+            };
+
+            let mut current = &mut map;
+            for part in parents.into_iter().flat_map(|p| p.split(separator)) {
+                let Value::Map(next) = current
+                    .entry(part.to_string())
+                    .or_insert_with(|| Value::Map(HashMap::new()))
+                else {
+                    return Err(StoreSerError::ConflictingPath(key));
+                };
+                current = next;
+            }
+            current.insert(last.to_string(), value);
+        }
+
+        Ok(Self(map, HashMap::new()))
+    }
+
+    ///Builds a store from the process's environment variables using [`Store::from_vars`], eg. for loading configuration. Variables which aren't valid unicode are skipped.
+    ///
+    /// ## Errors
+    /// [`StoreSerError::EmptySeparator`] if `separator` is empty.
+    #[cfg(feature = "std")]
+    pub fn from_env_vars(prefix: &str, separator: &str) -> Result<Self, StoreSerError> {
+        Self::from_vars(
+            std::env::vars_os()
+                .filter_map(|(k, v)| Some((k.into_string().ok()?, v.into_string().ok()?))),
+            prefix,
+            separator,
+        )
+    }
+
+    ///Builds a nested store from variables in the style of environment variables, only using those whose names start with `prefix`.
+    ///
+    /// `prefix` is removed from each name, along with a separator straight after it (which must be there unless `prefix` ends with one), and the rest is split on `separator` with every part but the last becoming a nested [`Value::Map`] - so with a prefix of `APP` and a separator of `__`, `APP__DB__HOST` is stored as `HOST` inside `DB`. Names which are only the prefix are skipped.
+    ///
+    /// Values are stored as a [`Value::Boolean`] if they are `true` or `false`, a [`Value::Integer`] if they are written exactly as that integer would be (so `01234` or `+5`, eg. a zip code, stays a [`Value::String`]), and otherwise as a [`Value::String`].
+    ///
+    /// If a name is both a value and a prefix of another name (eg. `APP__DB` and `APP__DB__HOST`), the nested map is kept.
+    ///
+    /// ## Errors
+    /// [`StoreSerError::EmptySeparator`] if `separator` is empty.
+    pub fn from_vars(
+        vars: impl IntoIterator<Item = (String, String)>,
+        prefix: &str,
+        separator: &str,
+    ) -> Result<Self, StoreSerError> {
+        if separator.is_empty() {
+            return Err(StoreSerError::EmptySeparator);
+        }
+
+        let mut vars = vars
+            .into_iter()
+            .filter_map(|(k, v)| {
+                let k = k.strip_prefix(prefix)?;
+                //so that a prefix of `APP` doesn't match `APPLE__X`
+                let k = if prefix.is_empty() || prefix.ends_with(separator) {
+                    k
+                } else {
+                    k.strip_prefix(separator)?
+                };
+                (!k.is_empty()).then(|| (k.to_string(), v))
+            })
+            .collect::<Vec<_>>();
+        //sorted so that conflicts are always resolved the same way
+        vars.sort_unstable();
+
+        let mut map = HashMap::new();
+        for (key, value) in vars {
+            let value = match value.as_str() {
+                "true" => Value::Boolean(true),
+                "false" => Value::Boolean(false),
+                v => match v.parse::<Integer>() {
+                    Ok(i) if i.to_string() == v => Value::Integer(i),
+                    _ => Value::String(value),
+                },
+            };
+
+            let mut parts = key.split(separator).collect::<Vec<_>>();
+            let last = parts.pop().unwrap_or_default();
+
+            let mut current = &mut map;
+            for part in parts {
+                let entry = current
+                    .entry(part.to_string())
+                    .or_insert_with(|| Value::Map(HashMap::new()));
+                if !entry.is_map() {
+                    *entry = Value::Map(HashMap::new());
+                }
+                let Value::Map(next) = entry else {
+                    unreachable!("just made sure that this is a map")
+                };
+                current = next;
+            }
+
+            if !current.get(last).is_some_and(Value::is_map) {
+                current.insert(last.to_string(), value);
+            }
+        }
+
+        Ok(Self(map, HashMap::new()))
+    }
+
+    ///Consumes the store, passing every entry through `f` and collecting the results into a new store. Entries are visited in an arbitrary order.
+    ///
+    /// ## Errors
+    /// - [`StoreSerError::KeyAlreadyExists`] if `f` returns the same key for more than one entry.
+    pub fn transform(
+        self,
+        mut f: impl FnMut(String, Value) -> (String, Value),
+    ) -> Result<Store, StoreSerError> {
+        let mut map = HashMap::with_capacity(self.0.len());
+        for (k, v) in self.0 {
+            let (k, v) = f(k, v);
+            if map.contains_key(&k) {
+                return Err(StoreSerError::KeyAlreadyExists(k));
+            }
+            map.insert(k, v);
+        }
+
+        Ok(Self(map, HashMap::new()))
+    }
+
+    ///Checks whether the store holds an array (see [`Store::as_array`]) of [`Value::Map`]s which all have exactly the same keys - ie. whether it is a table, with no missing cells. An empty array counts, as does an array of empty maps.
+    #[must_use]
+    pub fn is_homogeneous_array(&self) -> bool {
+        let Some(array) = self.as_array() else {
+            return false;
+        };
+        let Some(maps) = array.iter().map(Value::as_map).collect::<Option<Vec<_>>>() else {
+            return false;
+        };
+
+        let Some((first, rest)) = maps.split_first() else {
+            return true;
+        };
+        rest.iter()
+            .all(|m| m.len() == first.len() && m.keys().all(|k| first.contains_key(k)))
+    }
+
+    ///Writes the store's array (see [`Store::as_array`]) of [`Value::Map`]s out to `w` as CSV.
+    ///
+    /// The header row is every key found in any of the maps, sorted so that the output is deterministic. Each map then becomes one row, with an empty cell for any key it doesn't have. Cells are written as:
+    /// - [`Value::String`]s and [`Value::Character`]s as their plain text.
+    /// - [`Value::Binary`] as hex - see [`Value::binary_to_hex`].
+    /// - [`Value::Null`] as an empty cell.
+    /// - [`Value::Map`]s and [`Value::Array`]s as JSON where possible.
+    /// - Everything else using its [`Display`] implementation.
+    ///
+    /// Cells which contain commas, quotes or newlines are quoted.
+    ///
+    /// ## Errors
+    /// - [`StoreSerError::ExpectedArray`] if the store doesn't hold an array.
+    /// - [`StoreSerError::ExpectedMap`] if any element of the array isn't a map. This is checked before anything is written.
+    /// - [`StoreSerError::IO`] if writing to `w` fails.
+    #[cfg(feature = "std")]
+    pub fn to_csv<W: std::io::Write>(&self, mut w: W) -> Result<(), StoreSerError> {
+        fn cell(v: &Value) -> String {
+            let raw = match v {
+                Value::String(s) => s.clone(),
+                Value::Character(ch) => ch.to_string(),
+                Value::Null(()) => String::new(),
+                Value::Binary(_) => v.binary_to_hex().unwrap_or_default(),
+                Value::Map(_) | Value::Array(_) => match v.clone().convert_to_json() {
+                    Some(json) => json.to_string(),
+                    None => v.to_string(),
+                },
+                _ => v.to_string(),
+            };
+
+            if raw.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", raw.replace('"', "\"\""))
+            } else {
+                raw
+            }
+        }
+
+        let Some(array) = self.as_array() else {
+            return Err(StoreSerError::ExpectedArray);
+        };
+        let rows = array
+            .iter()
+            .map(|v| v.as_map().ok_or(StoreSerError::ExpectedMap(v.as_ty())))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut headers = rows.iter().flat_map(|row| row.keys()).collect::<Vec<_>>();
+        headers.sort_unstable();
+        headers.dedup();
+
+        let header_row = headers
+            .iter()
+            .map(|k| cell(&Value::String((*k).clone())))
+            .collect::<Vec<_>>();
+        writeln!(w, "{}", header_row.join(","))?;
+
+        for row in rows {
+            let cells = headers
+                .iter()
+                .map(|k| row.get(*k).map(cell).unwrap_or_default())
+                .collect::<Vec<_>>();
+            writeln!(w, "{}", cells.join(","))?;
+        }
+
+        Ok(())
+    }
+
+    ///Gets the SHA-256 hash of the value stored under `k`, or [`None`] if there isn't one. This is useful for working out which entries differ between two copies of a store without sending the values.
+    ///
+    /// The hash is of the value serialised in the same canonical form as [`Store::canonical_bytes`], so equal values always have equal hashes - even integers which were created from different types.
+    ///
+    /// Returns [`None`] if the value can't be serialised.
+    #[must_use]
+    pub fn value_hash(&self, k: &str) -> Option<[u8; 32]> {
+        let bytes = Self::canonicalise(self.0.get(k)?).ser(None).ok()?;
+        Some(Sha256::digest(bytes).into())
+    }
+
+    ///Gets the Shannon entropy of the bytes of the [`Value::String`] or [`Value::Binary`] stored under `k`, in bits per byte. This is useful for spotting near-constant fields (close to `0.0`) or random-looking ones, like encrypted or compressed data (close to `8.0`).
+    ///
+    /// Returns [`None`] if there is no value under `k` or it is another kind of value. Empty values have an entropy of `0.0`.
+    #[must_use]
+    pub fn value_entropy(&self, k: &str) -> Option<f64> {
+        let bytes = match self.0.get(k)? {
+            Value::String(s) => s.as_bytes(),
+            Value::Binary(b) => b.as_slice(),
+            _ => return None,
+        };
+        if bytes.is_empty() {
+            return Some(0.0);
+        }
+
+        let mut counts = [0_usize; 256];
+        for b in bytes {
+            counts[usize::from(*b)] += 1;
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        let len = bytes.len() as f64;
+        #[allow(clippy::cast_precision_loss)]
+        let entropy = counts
+            .into_iter()
+            .filter(|c| *c > 0)
+            .map(|c| {
+                let p = c as f64 / len;
+                -p * p.log2()
+            })
+            .sum::<f64>();
+        Some(entropy)
+    }
+
+    ///Gets the entropy (see [`Store::value_entropy`]) of every [`Value::String`] and [`Value::Binary`] in the top level of the store, keyed by their keys.
+    #[must_use]
+    pub fn entropy_summary(&self) -> HashMap<String, f64> {
+        self.0
+            .keys()
+            .filter_map(|k| Some((k.clone(), self.value_entropy(k)?)))
+            .collect()
+    }
+
+    ///Gets up to `n` keys from the store for a quick preview. The first `n` keys in sorted order are returned, so the same store always gives the same sample.
+    ///
+    /// If the store holds an array (see [`Store::as_array`]), the first `n` indices are returned instead.
+    #[must_use]
+    pub fn sample_keys(&self, n: usize) -> Vec<String> {
+        if let Some(array) = self.as_array() {
+            return (0..array.len().min(n)).map(|i| i.to_string()).collect();
+        }
+
+        let mut keys = self.0.keys().collect::<Vec<_>>();
+        if n < keys.len() {
+            keys.select_nth_unstable(n);
+            keys.truncate(n);
+        }
+        keys.sort_unstable();
+        keys.into_iter().cloned().collect()
+    }
+
+    ///Gets every key which matches the glob `pattern`, in sorted order.
+    ///
+    /// In the pattern, `*` matches any run of characters (including none) and `?` matches exactly one character. Everything else matches itself - to match a literal `*`, `?` or `\`, put a `\` before it (eg. the Rust string `"what\\?"` only matches `"what?"`).
+    ///
+    /// If the store holds an array (see [`Store::as_array`]), the pattern is matched against the indices instead.
+    ///
+    /// ```rust
+    /// use sourisdb::{store::Store, values::Value};
+    ///
+    /// let mut store = Store::default();
+    /// store.insert("user:alice".into(), Value::Null(()));
+    /// store.insert("user:bob".into(), Value::Null(()));
+    /// store.insert("session:alice".into(), Value::Null(()));
+    ///
+    /// assert_eq!(store.keys_matching("user:*"), vec!["user:alice", "user:bob"]);
+    /// assert_eq!(store.keys_matching("*:alice"), vec!["session:alice", "user:alice"]);
+    /// ```
+    #[must_use]
+    pub fn keys_matching(&self, pattern: &str) -> Vec<String> {
+        let pattern = GlobToken::parse(pattern);
+        let matches = |k: &str| GlobToken::matches(&pattern, &k.chars().collect::<Vec<_>>());
+
+        if let Some(array) = self.as_array() {
+            return (0..array.len())
+                .map(|i| i.to_string())
+                .filter(|i| matches(i))
+                .collect();
+        }
+
+        let mut keys = self
+            .0
+            .keys()
+            .filter(|k| matches(k))
+            .cloned()
+            .collect::<Vec<_>>();
+        keys.sort_unstable();
+        keys
+    }
+
+    ///Gets every key, sorted by comparing the entries using `cmp` - eg. by value or by the length of the key.
+    ///
+    /// Entries which `cmp` says are equal are sorted by key, so the order is always the same for the same store.
+    ///
+    /// ```rust
+    /// use sourisdb::{store::Store, values::Value};
+    ///
+    /// let mut store = Store::default();
+    /// store.insert("a".into(), Value::from(1));
+    /// store.insert("bbb".into(), Value::from(2));
+    /// store.insert("cc".into(), Value::from(3));
+    ///
+    /// let by_length = store.keys_sorted_by(|(a, _), (b, _)| a.len().cmp(&b.len()));
+    /// assert_eq!(by_length, vec!["a", "cc", "bbb"]);
+    /// ```
+    pub fn keys_sorted_by(
+        &self,
+        mut cmp: impl FnMut(&(&String, &Value), &(&String, &Value)) -> Ordering,
+    ) -> Vec<String> {
+        let mut entries = self.0.iter().collect::<Vec<_>>();
+        entries.sort_unstable_by_key(|(k, _)| *k);
+        //stable, so ties stay sorted by key
+        entries.sort_by(|a, b| cmp(a, b));
+        entries.into_iter().map(|(k, _)| k.clone()).collect()
+    }
+
+    ///Finds the entry with the smallest key for which `predicate` returns `true`, so that the same entry is always found for the same store - handy when only one entry is expected to match.
+    ///
+    /// `predicate` is called on every entry, in no particular order.
+    pub fn find(&self, predicate: impl Fn(&str, &Value) -> bool) -> Option<(&String, &Value)> {
+        self.0
+            .iter()
+            .filter(|(k, v)| predicate(k, v))
+            .min_by_key(|(k, _)| *k)
+    }
+
+    ///Consumes the store, returning every entry sorted by key - unlike iterating over the store, which gives an arbitrary order.
+    ///
+    /// If the store holds an array (see [`Store::as_array`]), the elements are returned in order, each keyed by its index.
+    #[must_use]
+    pub fn into_sorted_vec(self) -> Vec<(String, Value)> {
+        if self.as_array().is_some() {
+            return self
+                .to_array()
+                .unwrap_or_default()
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| (i.to_string(), v))
+                .collect();
+        }
+
+        let mut entries = self.0.into_iter().collect::<Vec<_>>();
+        entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        entries
+    }
+
+    ///Consumes the store, returning an iterator over every entry in a stable order without cloning anything - map entries are sorted by key, and array elements (see [`Store::as_array`]) are given in order, each keyed by its index. See [`Store::into_sorted_vec`] to collect the entries instead.
+    #[must_use]
+    pub fn into_ordered_iter(self) -> OrderedIntoIter {
+        OrderedIntoIter(self.into_sorted_vec().into_iter())
+    }
+
+    ///Reduces every entry in the store into one value, starting from `init`. Entries are visited in an arbitrary order.
+    pub fn fold<B>(&self, init: B, mut f: impl FnMut(B, &str, &Value) -> B) -> B {
+        self.0.iter().fold(init, |acc, (k, v)| f(acc, k, v))
+    }
+
+    ///Sums every top-level [`Value::Integer`] in the store, ignoring all other values.
+    ///
+    /// Returns [`None`] if any of the integers or the running total don't fit into a [`BiggestIntButSigned`].
+    #[must_use]
+    pub fn sum_ints(&self) -> Option<Integer> {
+        self.fold(Some(0), |acc: Option<BiggestIntButSigned>, _, v| {
+            let Some(i) = v.as_int() else {
+                return acc;
+            };
+            acc?.checked_add(BiggestIntButSigned::try_from(*i).ok()?)
+        })
+        .map(Integer::from)
+    }
+}
+
+///One part of a glob pattern used by [`Store::keys_matching`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum GlobToken {
+    ///`*` - any run of characters, including none.
+    AnyRun,
+    ///`?` - exactly one character.
+    AnyOne,
+    ///Any other character, or an escaped `*`, `?` or `\`.
+    Literal(char),
+}
+
+impl GlobToken {
+    fn parse(pattern: &str) -> Vec<Self> {
+        let mut tokens = vec![];
+        let mut chars = pattern.chars();
+        while let Some(ch) = chars.next() {
+            tokens.push(match ch {
+                '*' => Self::AnyRun,
+                '?' => Self::AnyOne,
+                //a trailing backslash has nothing to escape, so it matches itself
+                '\\' => Self::Literal(chars.next().unwrap_or('\\')),
+                ch => Self::Literal(ch),
+            });
+        }
+        tokens
+    }
+
+    fn matches(pattern: &[Self], key: &[char]) -> bool {
+        let (mut p, mut k) = (0, 0);
+        //where to go back to if a match fails after a `*` - the token after the `*`, and the next key character for the `*` to take
+        let mut backtrack = None;
+
+        while k < key.len() {
+            match pattern.get(p) {
+                Some(Self::AnyRun) => {
+                    p += 1;
+                    backtrack = Some((p, k + 1));
+                }
+                Some(Self::AnyOne) => {
+                    p += 1;
+                    k += 1;
+                }
+                Some(Self::Literal(ch)) if *ch == key[k] => {
+                    p += 1;
+                    k += 1;
+                }
+                _ => {
+                    let Some((bp, bk)) = backtrack else {
+                        return false;
+                    };
+                    p = bp;
+                    k = bk;
+                    backtrack = Some((bp, bk + 1));
+                }
+            }
+        }
+
+        pattern[p..].iter().all(|t| *t == Self::AnyRun)
+    }
+}
+
+///A consuming iterator over the entries of a [`Store`] in a stable order - see [`Store::into_ordered_iter`].
+#[derive(Debug)]
+pub struct OrderedIntoIter(vec::IntoIter<(String, Value)>);
+
+impl Iterator for OrderedIntoIter {
+    type Item = (String, Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for OrderedIntoIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back()
+    }
+}
+
+impl ExactSizeIterator for OrderedIntoIter {}
+
+impl TryFrom<Value> for Store {
+    type Error = StoreSerError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let ty = value.as_ty();
+        let Some(db) = value.to_map() else {
+            return Err(StoreSerError::ExpectedMap(ty));
+        };
+        Ok(Self(db, HashMap::new()))
+    }
+}
+
+impl Deref for Store {
+    type Target = HashMap<String, Value>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl DerefMut for Store {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
 
 impl Display for Store {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        write!(f, "{}", Value::Map(self.0.clone()))
+        write!(f, "{}", Value::Map(self.0.clone()))
+    }
+}
+
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub enum StoreSerError {
+    ExpectedMap(ValueTy),
+    ExpectedMagicBytes,
+    NotEnoughBytes,
+    Value(ValueSerError),
+    Integer(IntegerSerError),
+    SerdeJson(SJError),
+    UnableToConvertToJson,
+    UnsupportedCompression(u8),
+    UnsupportedFormatVersion(u8),
+    DecompressedTooLarge(usize),
+    Lz4Decompress(Lz4DecompressError),
+    MinizDecompresss(MinizDecompressError),
+    Huffman(HuffmanSerError),
+    #[cfg(feature = "std")]
+    IO(std::io::Error),
+    ExpectedArray,
+    MissingKey(String),
+    IndexOutOfBounds(usize, usize),
+    KeyAlreadyExists(String),
+    TrailingData(usize),
+    ConflictingPath(String),
+    InvalidJsonPatch(String),
+    JsonPatchTestFailed(String),
+    KeyTooLong(String),
+    ExpectedSingleEntry(usize),
+    EmptySeparator,
+}
+
+impl Display for StoreSerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            StoreSerError::ExpectedMap(t) => write!(
+                f,
+                "Expected to find a map when deserialising, found {t} instead"
+            ),
+            StoreSerError::NotEnoughBytes => write!(f, "Not enough bytes"),
+            StoreSerError::ExpectedMagicBytes => write!(f, "Unable to find starting magic bytes"),
+            StoreSerError::Integer(i) => write!(f, "Error with integer: {i}"),
+            StoreSerError::Value(e) => write!(f, "Error with values: {e}"),
+            StoreSerError::SerdeJson(e) => write!(f, "Error with serde_json: {e}"),
+            StoreSerError::UnableToConvertToJson => write!(f, "Unable to convert self to JSON"),
+            StoreSerError::UnsupportedCompression(b) => {
+                write!(f, "Unable to read compression type: {b:#b}")
+            }
+            StoreSerError::UnsupportedFormatVersion(v) => write!(
+                f,
+                "Unable to read format version {v}, only versions up to {FORMAT_VERSION} are supported"
+            ),
+            StoreSerError::DecompressedTooLarge(max) => write!(
+                f,
+                "Store would be larger than the limit of {max} bytes once decompressed"
+            ),
+            StoreSerError::Lz4Decompress(d) => write!(f, "Error with Lz4 decompression: {d}"),
+            StoreSerError::MinizDecompresss(d) => write!(f, "Error with miniz decompression: {d}"),
+            StoreSerError::Huffman(h) => write!(f, "Error with huffman: {h}"),
+            #[cfg(feature = "std")]
+            StoreSerError::IO(e) => write!(f, "IO Error: {e}"),
+            StoreSerError::ExpectedArray => {
+                write!(
+                    f,
+                    "Expected to find a store holding an array, found a map instead"
+                )
+            }
+            StoreSerError::MissingKey(k) => write!(f, "Unable to find key: {k:?}"),
+            StoreSerError::IndexOutOfBounds(i, len) => {
+                write!(f, "Index {i} out of bounds for array of length {len}")
+            }
+            StoreSerError::KeyAlreadyExists(k) => write!(f, "Key already exists: {k:?}"),
+            StoreSerError::TrailingData(remaining) => {
+                write!(f, "Found {remaining} trailing byte(s) after the store")
+            }
+            StoreSerError::ConflictingPath(k) => {
+                write!(f, "Key is both a value and a prefix of another key: {k:?}")
+            }
+            StoreSerError::InvalidJsonPatch(msg) => write!(f, "Invalid JSON Patch: {msg}"),
+            StoreSerError::JsonPatchTestFailed(path) => {
+                write!(f, "JSON Patch test failed at {path:?}")
+            }
+            StoreSerError::KeyTooLong(k) => write!(f, "Key is too long: {k:?}"),
+            StoreSerError::ExpectedSingleEntry(len) => {
+                write!(f, "Expected exactly one entry, found {len}")
+            }
+            StoreSerError::EmptySeparator => write!(f, "Separator cannot be empty"),
+        }
+    }
+}
+
+impl From<ValueSerError> for StoreSerError {
+    fn from(value: ValueSerError) -> Self {
+        Self::Value(value)
+    }
+}
+impl From<SJError> for StoreSerError {
+    fn from(value: SJError) -> Self {
+        Self::SerdeJson(value)
+    }
+}
+impl From<IntegerSerError> for StoreSerError {
+    fn from(value: IntegerSerError) -> Self {
+        Self::Integer(value)
+    }
+}
+impl From<Lz4DecompressError> for StoreSerError {
+    fn from(value: Lz4DecompressError) -> Self {
+        Self::Lz4Decompress(value)
+    }
+}
+impl From<MinizDecompressError> for StoreSerError {
+    fn from(value: MinizDecompressError) -> Self {
+        Self::MinizDecompresss(value)
+    }
+}
+impl From<HuffmanSerError> for StoreSerError {
+    fn from(value: HuffmanSerError) -> Self {
+        Self::Huffman(value)
+    }
+}
+#[cfg(feature = "std")]
+impl From<std::io::Error> for StoreSerError {
+    fn from(value: std::io::Error) -> Self {
+        Self::IO(value)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StoreSerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Integer(i) => Some(i),
+            Self::Value(e) => Some(e),
+            Self::SerdeJson(e) => Some(e),
+            Self::Lz4Decompress(d) => Some(d),
+            Self::MinizDecompresss(d) => Some(d),
+            Self::Huffman(h) => Some(h),
+            Self::IO(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        string::{String, ToString},
+        vec,
+    };
+    use core::num::NonZeroUsize;
+
+    use hashbrown::HashMap;
+    use serde_json::json;
+
+    use chrono::{NaiveDate, NaiveDateTime, TimeDelta};
+    use proptest::{
+        collection::vec, prelude::any, prop_assert, prop_assert_eq, prop_oneof, proptest,
+        sample::Index,
+    };
+
+    use super::{
+        CompressionType, Conflict, IngestOptions, LimitAction, MergeStrategy, NullPolicy, Store,
+        StoreParts, StoreSerError, StoreVisitor, EXPIRY_FLAG, FORMAT_VERSION, JSON_KEY,
+        METADATA_FLAG, VERSIONED_FLAG,
+    };
+    use crate::{
+        types::integer::Integer,
+        values::{Value, ValueSerError, ValueTy, DEFAULT_MAX_DEPTH},
+    };
+
+    fn sample_store() -> Store {
+        let mut store = Store::default();
+        store.insert("name".to_string(), Value::String("souris".to_string()));
+        store.insert("count".to_string(), Value::Integer(42.into()));
+        store.insert(
+            "flags".to_string(),
+            Value::Array(vec![Value::Boolean(true), Value::Null(())]),
+        );
+        store
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join(format!(
+            "sourisdb_test_save_and_load_{}.sdb",
+            std::process::id()
+        ));
+
+        let store = sample_store();
+        store.save(&path).unwrap();
+        let loaded = Store::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(store, loaded);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_save_only_replaces_after_complete_write() {
+        let dir =
+            std::env::temp_dir().join(format!("sourisdb_test_atomic_save_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("store.sdb");
+
+        let original = sample_store();
+        original.save(&path).unwrap();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        //block the temporary file so that the write fails part-way
+        let tmp_path = dir.join("store.sdb.tmp");
+        std::fs::create_dir(&tmp_path).unwrap();
+        let mut updated = sample_store();
+        updated.insert("extra".to_string(), Value::Boolean(false));
+        assert!(updated.save_via(&path, &tmp_path).is_err());
+        assert_eq!(Store::load(&path).unwrap(), original);
+
+        std::fs::remove_dir(&tmp_path).unwrap();
+        updated.save(&path).unwrap();
+        assert_eq!(Store::load(&path).unwrap(), updated);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_concurrent_saves_dont_clobber() {
+        let dir = std::env::temp_dir().join(format!(
+            "sourisdb_test_concurrent_save_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("store.sdb");
+
+        let stores: Vec<Store> = (0..8).map(|i| Store::from_range(0..i * 100)).collect();
+        std::thread::scope(|scope| {
+            for store in &stores {
+                scope.spawn(|| {
+                    for _ in 0..10 {
+                        store.save(&path).unwrap();
+                    }
+                });
+            }
+        });
+
+        assert!(stores.contains(&Store::load(&path).unwrap()));
+        //every temporary file was renamed away
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_merge_array_by_key() {
+        let mut existing = Store::from_json(json!([
+            {"id": 1, "v": "one"},
+            {"id": 2, "v": "two"},
+        ]));
+        let incoming = Store::from_json(json!([
+            {"id": 2, "v": "TWO"},
+            {"id": 3, "v": "three"},
+        ]));
+
+        existing.merge_array_by_key(incoming, "id", false).unwrap();
+        assert_eq!(
+            existing,
+            Store::from_json(json!([
+                {"id": 1, "v": "one"},
+                {"id": 2, "v": "TWO"},
+                {"id": 3, "v": "three"},
+            ]))
+        );
+
+        let no_id = Store::from_json(json!([{"v": "four"}]));
+        assert!(matches!(
+            existing
+                .clone()
+                .merge_array_by_key(no_id.clone(), "id", false),
+            Err(StoreSerError::MissingKey(_))
+        ));
+        existing.merge_array_by_key(no_id, "id", true).unwrap();
+        assert_eq!(existing.as_array().unwrap().len(), 4);
+
+        assert!(matches!(
+            sample_store().merge_array_by_key(Store::default(), "id", true),
+            Err(StoreSerError::ExpectedArray)
+        ));
+    }
+
+    #[test]
+    fn test_fold_and_sum_ints() {
+        let mut store = sample_store();
+        store.insert("negative".to_string(), Value::Integer((-50).into()));
+        store.insert("big".to_string(), Value::Integer(1_000_u32.into()));
+        assert_eq!(store.sum_ints(), Some(992.into()));
+        assert_eq!(Store::default().sum_ints(), Some(0.into()));
+
+        let mut keys = store.fold(vec![], |mut keys, k, _| {
+            keys.push(k.to_string());
+            keys
+        });
+        keys.sort();
+        assert_eq!(keys, ["big", "count", "flags", "name", "negative"]);
+    }
+
+    #[test]
+    fn test_split_off() {
+        let mut store = Store::from_array((0..6).map(Value::from).collect());
+        let back = store.split_off(3).unwrap();
+
+        assert_eq!(store, Store::from_array((0..3).map(Value::from).collect()));
+        assert_eq!(back, Store::from_array((3..6).map(Value::from).collect()));
+
+        assert!(matches!(
+            store.split_off(4),
+            Err(StoreSerError::IndexOutOfBounds(4, 3))
+        ));
+        assert!(matches!(
+            sample_store().split_off(0),
+            Err(StoreSerError::ExpectedArray)
+        ));
+    }
+
+    #[test]
+    fn test_replace() {
+        let mut store = sample_store();
+
+        assert_eq!(
+            store.replace("count".to_string(), Value::Integer(7.into())),
+            Some(Value::Integer(42.into()))
+        );
+        assert_eq!(store.get("count"), Some(&Value::Integer(7.into())));
+
+        assert_eq!(
+            store.replace(JSON_KEY.to_string(), Value::Boolean(true)),
+            None
+        );
+        assert_eq!(store.get(JSON_KEY), Some(&Value::Boolean(true)));
+    }
+
+    #[test]
+    fn test_unordered_eq() {
+        let a = Store::from_json(json!([1, 2, 2, "three"]));
+        let b = Store::from_json(json!(["three", 2, 1, 2]));
+        let c = Store::from_json(json!(["three", 1, 1, 2]));
+
+        assert_ne!(a, b);
+        assert!(a.unordered_eq(&b));
+        assert!(!a.unordered_eq(&c));
+        assert!(sample_store().unordered_eq(&sample_store()));
+        assert!(!sample_store().unordered_eq(&a));
+
+        let records = (0..32).map(|i| json!({"id": i, "v": i.to_string(), "w": [i]}));
+        let a = Store::from_json(records.clone().collect());
+        let b = Store::from_json(records.rev().collect());
+        assert!(a.unordered_eq(&b));
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let mut nested = Value::Null(());
+        for i in 0..DEFAULT_MAX_DEPTH {
+            let mut map = HashMap::new();
+            map.insert(i.to_string(), nested);
+            nested = Value::Map(map);
+        }
+        let mut store = Store::default();
+        store.insert("nested".to_string(), nested);
+        let bytes = store.ser().unwrap();
+
+        assert!(matches!(
+            Store::deser(&bytes),
+            Err(StoreSerError::Value(ValueSerError::DepthExceeded))
+        ));
+        assert_eq!(
+            Store::deser_with_max_depth(&bytes, DEFAULT_MAX_DEPTH + 1).unwrap(),
+            store
+        );
+
+        //the limit applies to metadata too
+        let shallow = Store::from_json(json!({"key": 1}));
+        let bytes = shallow
+            .ser_with_metadata(&Store::from_json(json!({"a": [[1]]})))
+            .unwrap();
+        assert_eq!(Store::deser_with_max_depth(&bytes, 3).unwrap(), shallow);
+        assert!(matches!(
+            Store::deser_with_max_depth(&bytes, 2),
+            Err(StoreSerError::Value(ValueSerError::DepthExceeded))
+        ));
+        assert!(matches!(
+            Store::deser_with_metadata(&shallow.ser_with_metadata(&store).unwrap()),
+            Err(StoreSerError::Value(ValueSerError::DepthExceeded))
+        ));
+    }
+
+    #[test]
+    fn test_deser_deeply_nested() {
+        const DEPTH: usize = 10_000;
+
+        //built by hand, as serialising a value this deep would recurse
+        let mut map_of_one = HashMap::new();
+        map_of_one.insert("nested".to_string(), Value::Null(()));
+        let map_of_one = Value::Map(map_of_one).ser(None).unwrap();
+        let array_of_one = Value::Array(vec![Value::Null(())]).ser(None).unwrap();
+
+        let mut bytes = b"SOURISDB".to_vec();
+        bytes.push(0); //no compression or huffman
+        bytes.extend(&map_of_one[..map_of_one.len() - 1]);
+        bytes.extend(core::iter::repeat_n(array_of_one[0], DEPTH));
+        bytes.push(array_of_one[1]);
+
+        assert!(matches!(
+            Store::deser(&bytes),
+            Err(StoreSerError::Value(ValueSerError::DepthExceeded))
+        ));
+
+        let mut store = Store::deser_with_max_depth(&bytes, usize::MAX).unwrap();
+
+        //taken apart one level at a time, as dropping it all at once would recurse
+        let mut value = store.remove("nested").unwrap();
+        let mut depth = 0;
+        while let Value::Array(mut items) = value {
+            assert_eq!(items.len(), 1);
+            value = items.pop().unwrap();
+            depth += 1;
+        }
+        assert_eq!(depth, DEPTH);
+        assert_eq!(value, Value::Null(()));
+    }
+
+    #[test]
+    fn test_prune_empty() {
+        let mut store = Store::from_json(json!({
+            "empty": {},
+            "empty_array": [],
+            "only_empties": {"a": {}, "b": [{}, []]},
+            "kept": {"a": {}, "b": 1},
+            "array": [[], 2, {"c": {}}],
+            "null": null
+        }));
+        store.prune_empty();
+
+        assert_eq!(
+            store.to_json().unwrap(),
+            json!({
+                "kept": {"b": 1},
+                "array": [2],
+                "null": null
+            })
+        );
+    }
+
+    #[test]
+    fn test_depth_histogram() {
+        let store = Store::from_json(json!({
+            "a": 1,
+            "b": {"c": 2, "d": [3, 4, {"e": 5}]},
+            "f": []
+        }));
+        assert_eq!(store.depth_histogram(), vec![3, 2, 3, 1]);
+
+        assert!(Store::default().depth_histogram().is_empty());
+    }
+
+    #[test]
+    fn test_to_json_object() {
+        let mut store = Store::default();
+        store.insert(
+            JSON_KEY.to_string(),
+            Value::Array(vec![Value::Boolean(true)]),
+        );
+
+        assert_eq!(store.clone().to_json().unwrap(), json!([true]));
+        assert_eq!(store.to_json_object().unwrap(), json!({"JSON": [true]}));
+
+        let store = Store::from_json(json!({"a": 1}));
+        assert_eq!(store.clone().to_json(), store.to_json_object());
+    }
+
+    #[test]
+    fn test_to_json_string_pretty() {
+        let json = json!({"a": 1, "b": {"c": [true, null, "d"]}});
+        let store = Store::from_json(json.clone());
+
+        let pretty = store.to_json_string_pretty().unwrap();
+        assert!(pretty.contains('\n'));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&pretty).unwrap(),
+            json
+        );
+
+        let mut store = Store::default();
+        store.insert("nan".to_string(), Value::DoubleFloat(f64::NAN));
+        assert!(matches!(
+            store.to_json_string_pretty(),
+            Err(StoreSerError::UnableToConvertToJson)
+        ));
+    }
+
+    #[test]
+    fn test_take() {
+        let mut store = Store::from_json(json!({"a": 1, "b": "c"}));
+        assert_eq!(store.take("a"), Some(Value::Integer(1.into())));
+        assert_eq!(store.take("a"), None);
+        assert_eq!(store.take("z"), None);
+        assert_eq!(store.to_json(), Some(json!({"b": "c"})));
+
+        let mut store = Store::from_json(json!([1, 2, 3]));
+        assert_eq!(store.take("0"), Some(Value::Integer(1.into())));
+        assert_eq!(store.take("2"), None);
+        assert_eq!(store.take(JSON_KEY), None);
+        assert_eq!(store.to_json(), Some(json!([2, 3])));
+    }
+
+    #[test]
+    fn test_get_as() {
+        let store = Store::from_json(json!({"a": 1, "b": "c", "d": -300}));
+
+        assert_eq!(store.get_as::<i64>("a").unwrap(), Some(1));
+        assert_eq!(store.get_as::<String>("b").unwrap(), Some("c".to_string()));
+        assert_eq!(store.get_as::<i64>("z").unwrap(), None);
+        assert!(matches!(
+            store.get_as::<i64>("b"),
+            Err(ValueSerError::UnexpectedValueType(
+                ValueTy::String,
+                ValueTy::Integer
+            ))
+        ));
+        assert!(matches!(
+            store.get_as::<u8>("d"),
+            Err(ValueSerError::IntegerSerError(_))
+        ));
+    }
+
+    #[test]
+    fn test_nest() {
+        let store = Store::from_json(json!({"a": 1, "b": {"c": true}}));
+        let nested = store.clone().nest("inner".into());
+
+        assert_eq!(nested.len(), 1);
+        let inner = nested.get("inner").and_then(Value::as_map).unwrap();
+        assert_eq!(inner.get("a"), Some(&Value::Integer(1.into())));
+        assert_eq!(Store(inner.clone(), HashMap::new()), store);
+        assert_eq!(
+            nested.to_json(),
+            Some(json!({"inner": {"a": 1, "b": {"c": true}}}))
+        );
+
+        let nested = Store::from_json(json!([1, 2])).nest("list".into());
+        assert_eq!(nested.to_json(), Some(json!({"list": [1, 2]})));
+    }
+
+    #[test]
+    fn test_unnest() {
+        let mut store = Store::from_json(json!({"a": 1, "inner": {"b": 2, "inner": 3}}));
+        store.unnest("inner").unwrap();
+        assert_eq!(store.to_json(), Some(json!({"a": 1, "b": 2, "inner": 3})));
+
+        let mut store = Store::from_json(json!({"a": 1})).nest("wrapper".into());
+        store.unnest("wrapper").unwrap();
+        assert_eq!(store.to_json(), Some(json!({"a": 1})));
+
+        let mut store = Store::from_json(json!({"a": 1, "b": 2, "inner": {"b": 3}}));
+        assert!(matches!(
+            store.unnest("inner"),
+            Err(StoreSerError::KeyAlreadyExists(k)) if k == "b"
+        ));
+        assert!(matches!(
+            store.unnest("a"),
+            Err(StoreSerError::ExpectedMap(ValueTy::Integer))
+        ));
+        assert!(matches!(
+            store.unnest("z"),
+            Err(StoreSerError::MissingKey(k)) if k == "z"
+        ));
+        assert_eq!(
+            store.to_json(),
+            Some(json!({"a": 1, "b": 2, "inner": {"b": 3}}))
+        );
+    }
+
+    #[test]
+    fn test_ser_chunks() {
+        let store = Store::from_json(json!({"a": 1, "b": "hello world", "c": [true, null]}));
+        let bytes = store.ser().unwrap();
+
+        for chunk_size in [1, 3, 7, bytes.len(), bytes.len() + 10] {
+            let chunks = store
+                .ser_chunks(NonZeroUsize::new(chunk_size).unwrap())
+                .unwrap()
+                .collect::<Vec<_>>();
+            assert_eq!(chunks.len(), bytes.len().div_ceil(chunk_size));
+            assert!(chunks
+                .iter()
+                .all(|c| !c.is_empty() && c.len() <= chunk_size));
+            assert_eq!(chunks.concat(), bytes);
+        }
+    }
+
+    #[test]
+    fn test_from_range() {
+        let store = Store::from_range(0..5);
+        let expected = (0..5_i64)
+            .map(|i| Value::Integer(i.into()))
+            .collect::<Vec<_>>();
+        assert_eq!(store.as_array(), Some(&expected));
+        assert_eq!(store.to_json(), Some(json!([0, 1, 2, 3, 4])));
+
+        assert_eq!(Store::from_range(-2..0).to_json(), Some(json!([-2, -1])));
+        assert_eq!(Store::from_range(3..3).as_array(), Some(&vec![]));
+    }
+
+    #[test]
+    fn test_transform() {
+        let store = Store::from_json(json!({"a": 1, "b": -2, "c": "three"}));
+
+        let transformed = store
+            .clone()
+            .transform(|k, v| {
+                let v = match v.as_int().and_then(|i| i64::try_from(*i).ok()) {
+                    Some(i) => Value::from(i * 2),
+                    None => v,
+                };
+                (format!("new_{k}"), v)
+            })
+            .unwrap();
+        assert_eq!(
+            transformed.to_json(),
+            Some(json!({"new_a": 2, "new_b": -4, "new_c": "three"}))
+        );
+
+        assert!(matches!(
+            store.transform(|k, v| (if k == "c" { k } else { "x".into() }, v)),
+            Err(StoreSerError::KeyAlreadyExists(k)) if k == "x"
+        ));
+    }
+
+    #[test]
+    fn test_ttl() {
+        let start = NaiveDate::from_ymd_opt(2024, 6, 1)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap();
+        let mut clock = start;
+
+        let mut store = Store::from_json(json!({"forever": 0}));
+        store.insert_with_ttl(
+            "short".into(),
+            Value::from(1),
+            TimeDelta::seconds(10),
+            clock,
+        );
+        store.insert_with_ttl("long".into(), Value::from(2), TimeDelta::hours(1), clock);
+        store.insert_with_ttl(
+            "cleared".into(),
+            Value::from(3),
+            TimeDelta::seconds(1),
+            clock,
+        );
+        assert_eq!(store.expiry("short"), Some(start + TimeDelta::seconds(10)));
+        assert_eq!(store.expiry("forever"), None);
+        assert!(store.clear_ttl("cleared").is_some());
+
+        //expiry times survive being serialised
+        let mut store = Store::deser(&store.ser().unwrap()).unwrap();
+        assert_eq!(store.expiry("long"), Some(start + TimeDelta::hours(1)));
+
+        clock += TimeDelta::seconds(9);
+        assert_eq!(store.purge_expired(clock), 0);
+        assert_eq!(store.len(), 4);
+
+        clock += TimeDelta::seconds(1);
+        assert_eq!(store.purge_expired(clock), 1);
+        assert!(!store.contains_key("short"));
+        assert_eq!(store.expiry("short"), None);
+
+        clock += TimeDelta::days(1);
+        assert_eq!(store.purge_expired(clock), 1);
+        assert_eq!(store.to_json(), Some(json!({"forever": 0, "cleared": 3})));
+    }
+
+    #[test]
+    fn test_ttl_cleared_by_insert_and_remove() {
+        let now = NaiveDateTime::default();
+        let later = now + TimeDelta::days(1);
+
+        let mut store = Store::default();
+        store.insert_with_ttl("a".into(), Value::from(1), TimeDelta::seconds(1), now);
+        store.remove("a");
+        store.insert("a".into(), Value::from(2));
+        assert_eq!(store.expiry("a"), None);
+        assert_eq!(store.purge_expired(later), 0);
+        assert_eq!(store.get("a"), Some(&Value::from(2)));
+
+        store.insert_with_ttl("b".into(), Value::from(1), TimeDelta::seconds(1), now);
+        store.insert("b".into(), Value::from(2));
+        assert_eq!(store.expiry("b"), None);
+
+        //removing through the map directly leaves the expiry time behind, but it is ignored
+        store.insert_with_ttl("c".into(), Value::from(1), TimeDelta::seconds(1), now);
+        (*store).retain(|k, _| k != "c");
+        assert_eq!(store.expiry("c"), None);
+        let mut expected = Store::default();
+        expected.insert("a".into(), Value::from(2));
+        expected.insert("b".into(), Value::from(2));
+        assert_eq!(store, expected);
+
+        store.insert("c".into(), Value::from(2));
+        assert_eq!(store.purge_expired(later), 0);
+        assert_eq!(store.get("c"), Some(&Value::from(2)));
+    }
+
+    #[test]
+    fn test_ttl_kept_in_step_with_keys() {
+        let now = NaiveDateTime::default();
+        let later = now + TimeDelta::days(1);
+        //a store where `"a"` had an expiry time, and was then removed and re-inserted by `f`
+        let check = |f: &dyn Fn(&mut Store)| {
+            let mut store = Store::default();
+            store.insert_with_ttl("a".into(), Value::from(1), TimeDelta::seconds(1), now);
+            store.insert_with_ttl("b".into(), Value::from(1), TimeDelta::seconds(1), now);
+            f(&mut store);
+            store.insert_with_ttl("b".into(), Value::from(1), TimeDelta::seconds(1), now);
+            (*store).insert("a".into(), Value::from(2));
+
+            assert_eq!(store.expiry("a"), None);
+            assert_eq!(store.purge_expired(later), 1);
+            assert_eq!(store.get("a"), Some(&Value::from(2)));
+        };
+
+        check(&|store| {
+            store.take("a");
+        });
+        check(&|store| store.retain_keys(&["b"]));
+        check(&|store| store.retain(|k, _| k != "a"));
+        check(&|store| {
+            store.remove_entry("a");
+        });
+        check(&|store| {
+            store.drain();
+        });
+        check(&|store| {
+            store.insert("a".into(), Value::Array(vec![]));
+            store.prune_empty();
+        });
+
+        //paths which insert a key mustn't pick up a stale expiry time either
+        let mut store = Store::default();
+        store.insert_with_ttl("a".into(), Value::from(1), TimeDelta::seconds(1), now);
+        (*store).remove("a");
+        store.get_or_insert_with("a".into(), || Value::from(2));
+        assert_eq!(store.expiry("a"), None);
+        assert_eq!(store.purge_expired(later), 0);
+
+        store.insert_with_ttl("a".into(), Value::from(1), TimeDelta::seconds(1), now);
+        store.replace("a".into(), Value::from(2));
+        assert_eq!(store.expiry("a"), None);
+
+        let mut store = Store::default();
+        store.insert_with_ttl("b".into(), Value::from(1), TimeDelta::seconds(1), now);
+        (*store).remove("b");
+        store.insert_with_ttl(
+            "a".into(),
+            Value::Map([("b".to_string(), Value::from(2))].into_iter().collect()),
+            TimeDelta::seconds(1),
+            now,
+        );
+        store.unnest("a").unwrap();
+        assert_eq!(store.expiry("a"), None);
+        assert_eq!(store.expiry("b"), None);
+        assert_eq!(store.purge_expired(later), 0);
+        assert_eq!(store.get("b"), Some(&Value::from(2)));
+
+        let mut store = Store::default();
+        store.insert_with_ttl("p.a".into(), Value::from(1), TimeDelta::seconds(1), now);
+        (*store).remove("p.a");
+        let mut other = Store::default();
+        other.insert("a".into(), Value::from(2));
+        other.insert_with_ttl("b".into(), Value::from(2), TimeDelta::seconds(1), now);
+        store.join_prefixed("p", other).unwrap();
+        assert_eq!(store.expiry("p.a"), None);
+        assert_eq!(store.expiry("p.b"), Some(now + TimeDelta::seconds(1)));
+        assert_eq!(store.purge_expired(later), 1);
+        assert_eq!(store.get("p.a"), Some(&Value::from(2)));
+    }
+
+    #[test]
+    fn test_no_expiries_keeps_format() {
+        let store = Store::from_json(json!({"a": 1, "b": "c"}));
+        let bytes = store.ser().unwrap();
+        assert_eq!(bytes[8] & EXPIRY_FLAG, 0);
+
+        let mut with_stale_expiry = store.clone();
+        with_stale_expiry.insert_with_ttl(
+            "z".into(),
+            Value::Null(()),
+            TimeDelta::seconds(1),
+            NaiveDateTime::default(),
+        );
+        with_stale_expiry.remove("z");
+        assert_eq!(with_stale_expiry.ser().unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_expiries_are_versioned() {
+        let mut store = Store::from_json(json!({"a": 1, "b": "c"}));
+        store.insert_with_ttl(
+            "a".into(),
+            Value::from(1),
+            TimeDelta::seconds(1),
+            NaiveDateTime::default(),
+        );
+
+        for mut bytes in [store.ser().unwrap(), store.canonical_bytes().unwrap()] {
+            assert_eq!(bytes[9], FORMAT_VERSION);
+            //readers from before format versions only looked at the bottom four bits for the compression type
+            assert!(matches!(
+                CompressionType::try_from(bytes[8] & 0b1111),
+                Err(StoreSerError::UnsupportedCompression(_))
+            ));
+            assert_eq!(Store::deser(&bytes).unwrap(), store);
+
+            bytes[9] = FORMAT_VERSION + 1;
+            assert!(matches!(
+                Store::deser(&bytes),
+                Err(StoreSerError::UnsupportedFormatVersion(v)) if v == FORMAT_VERSION + 1
+            ));
+        }
+    }
+
+    #[test]
+    fn test_value_entropy() {
+        let mut store = Store::default();
+        store.insert("constant".into(), Value::String("aaaaaaaa".into()));
+        store.insert("two".into(), Value::String("abababab".into()));
+        store.insert("four".into(), Value::Binary(vec![0, 1, 2, 3, 3, 2, 1, 0]));
+        store.insert("all".into(), Value::Binary((0..=255).collect()));
+        store.insert("empty".into(), Value::Binary(vec![]));
+        store.insert("int".into(), Value::from(1));
+
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        assert!(close(store.value_entropy("constant").unwrap(), 0.0));
+        assert!(close(store.value_entropy("two").unwrap(), 1.0));
+        assert!(close(store.value_entropy("four").unwrap(), 2.0));
+        assert!(close(store.value_entropy("all").unwrap(), 8.0));
+        assert!(close(store.value_entropy("empty").unwrap(), 0.0));
+        assert_eq!(store.value_entropy("int"), None);
+        assert_eq!(store.value_entropy("missing"), None);
+
+        let summary = store.entropy_summary();
+        assert_eq!(summary.len(), 5);
+        assert!(close(summary["four"], 2.0));
+        assert!(!summary.contains_key("int"));
+    }
+
+    #[test]
+    fn test_is_homogeneous_array() {
+        let homogeneous = Store::from_json(json!([
+            {"a": 1, "b": "x"},
+            {"b": "y", "a": 2},
+            {"a": null, "b": true},
+        ]));
+        assert!(homogeneous.is_homogeneous_array());
+        assert!(Store::from_json(json!([])).is_homogeneous_array());
+        assert!(Store::from_json(json!([{}, {}])).is_homogeneous_array());
+
+        assert!(!Store::from_json(json!([{"a": 1}, {"a": 2, "b": 3}])).is_homogeneous_array());
+        assert!(!Store::from_json(json!([{"a": 1, "b": 2}, {"a": 2}])).is_homogeneous_array());
+        assert!(!Store::from_json(json!([{"a": 1}, {"b": 1}])).is_homogeneous_array());
+        assert!(!Store::from_json(json!([{"a": 1}, 2])).is_homogeneous_array());
+
+        assert!(!Store::from_json(json!({"a": 1})).is_homogeneous_array());
+        assert!(!Store::default().is_homogeneous_array());
+    }
+
+    #[test]
+    fn test_deser_strict() {
+        let store = Store::from_json(json!({"a": 1, "b": true}));
+        let mut bytes = store.ser().unwrap();
+        assert_eq!(bytes[8] & 0b1111, u8::from(CompressionType::None));
+
+        assert_eq!(Store::deser_strict(&bytes).unwrap(), store);
+
+        bytes.extend([1, 2, 3]);
+        assert_eq!(Store::deser(&bytes).unwrap(), store);
+        assert!(matches!(
+            Store::deser_strict(&bytes),
+            Err(StoreSerError::TrailingData(3))
+        ));
+
+        //built by hand, as which compression `ser` picks depends on the contents
+        let store = Store::from_range(0..1_000);
+        let raw = Value::Map(store.0.clone()).ser(None).unwrap();
+        let mut lz4 = crate::types::integer::Integer::from(raw.len()).ser().1;
+        lz4.extend(lz4_flex::compress(&raw));
+        let miniz = miniz_oxide::deflate::compress_to_vec(&raw, 10);
+
+        for (compressed, ty) in [(lz4, CompressionType::Lz4), (miniz, CompressionType::Miniz)] {
+            let mut bytes = b"SOURISDB".to_vec();
+            bytes.push(u8::from(ty));
+            bytes.extend(compressed);
+            assert_eq!(Store::deser_strict(&bytes).unwrap(), store);
+
+            bytes.extend([1, 2, 3]);
+            assert_eq!(Store::deser(&bytes).unwrap(), store);
+            assert!(matches!(
+                Store::deser_strict(&bytes),
+                Err(StoreSerError::TrailingData(3))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_deser_with_max_len() {
+        //a one byte lz4 block which claims to decompress to 16 TiB
+        let mut bomb = b"SOURISDB".to_vec();
+        bomb.push(u8::from(CompressionType::Lz4));
+        bomb.extend(crate::types::integer::Integer::from(1_usize << 44).ser().1);
+        bomb.push(0);
+        assert!(matches!(
+            Store::deser(&bomb),
+            Err(StoreSerError::DecompressedTooLarge(255))
+        ));
+
+        //very repetitive, so it compresses as well as lz4 can
+        let mut store = Store::default();
+        store.insert("a".into(), Value::Binary(vec![0; 1_000_000]));
+        let raw = Value::Map(store.0.clone()).ser(None).unwrap();
+        let mut lz4 = crate::types::integer::Integer::from(raw.len()).ser().1;
+        lz4.extend(lz4_flex::compress(&raw));
+        let miniz = miniz_oxide::deflate::compress_to_vec(&raw, 10);
+
+        for (compressed, ty) in [
+            (raw.clone(), CompressionType::None),
+            (lz4, CompressionType::Lz4),
+            (miniz, CompressionType::Miniz),
+        ] {
+            let mut bytes = b"SOURISDB".to_vec();
+            bytes.push(u8::from(ty));
+            bytes.extend(compressed);
+
+            assert_eq!(Store::deser(&bytes).unwrap(), store);
+            assert_eq!(Store::deser_with_max_len(&bytes, raw.len()).unwrap(), store);
+            assert!(matches!(
+                Store::deser_with_max_len(&bytes, raw.len() - 1),
+                Err(StoreSerError::DecompressedTooLarge(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_deser_all() {
+        let small = Store::from_json(json!({"a": 1, "b": "c"}));
+        let mut with_magic = Store::default();
+        with_magic.insert("magic".into(), Value::Binary(b"xSOURISDBx".to_vec()));
+        let large = Store::from_range(0..1_000);
+
+        let mut bytes = vec![];
+        for store in [&small, &with_magic, &large, &small] {
+            bytes.extend(store.ser().unwrap());
+        }
+
+        let stores = Store::deser_all(&bytes).unwrap();
+        assert_eq!(
+            stores,
+            vec![small.clone(), with_magic, large.clone(), small.clone()]
+        );
+
+        assert_eq!(Store::deser_all(&[]).unwrap(), vec![]);
+        assert_eq!(
+            Store::deser_all(&small.ser().unwrap()).unwrap(),
+            vec![small.clone()]
+        );
+
+        let mut bytes = small.ser().unwrap();
+        bytes.extend(b"garbage");
+        assert!(matches!(
+            Store::deser_all(&bytes),
+            Err(StoreSerError::TrailingData(7))
+        ));
+
+        let compressed = large.ser().unwrap();
+        assert_ne!(compressed[8] & 0b1111, u8::from(CompressionType::None));
+        let mut bytes = small.ser().unwrap();
+        bytes.extend(compressed);
+        bytes.extend(b"garbage");
+        assert!(matches!(
+            Store::deser_all(&bytes),
+            Err(StoreSerError::TrailingData(7))
+        ));
+        assert!(matches!(
+            Store::deser_all(b"not a store"),
+            Err(StoreSerError::ExpectedMagicBytes)
+        ));
+    }
+
+    #[test]
+    fn test_sample_keys() {
+        let mut store = Store::default();
+        for k in ["j", "c", "a", "h", "e", "b", "i", "d", "g", "f"] {
+            store.insert(k.into(), Value::Null(()));
+        }
+
+        assert_eq!(store.sample_keys(3), vec!["a", "b", "c"]);
+        assert_eq!(store.sample_keys(0), Vec::<String>::new());
+        assert_eq!(store.sample_keys(100).len(), 10);
+
+        let store = Store::from_range(0..10);
+        assert_eq!(store.sample_keys(3), vec!["0", "1", "2"]);
+        assert_eq!(store.sample_keys(20).len(), 10);
+    }
+
+    #[test]
+    fn test_keys_matching() {
+        let mut store = Store::default();
+        for k in [
+            "user:alice",
+            "user:bob",
+            "user:",
+            "users:carol",
+            "session:alice",
+            "log1",
+            "log2",
+            "log",
+            "log10",
+            "what?",
+            "whats",
+            "a*b",
+            "a\\b",
+        ] {
+            store.insert(k.into(), Value::Null(()));
+        }
+
+        assert_eq!(
+            store.keys_matching("user:*"),
+            vec!["user:", "user:alice", "user:bob"]
+        );
+        assert_eq!(store.keys_matching("log?"), vec!["log1", "log2"]);
+        assert_eq!(
+            store.keys_matching("log*"),
+            vec!["log", "log1", "log10", "log2"]
+        );
+        assert_eq!(
+            store.keys_matching("*:*ice"),
+            vec!["session:alice", "user:alice"]
+        );
+        assert_eq!(store.keys_matching("u*r*b"), vec!["user:bob"]);
+        assert_eq!(store.keys_matching("*").len(), store.len());
+        assert_eq!(store.keys_matching("user"), Vec::<String>::new());
+
+        assert_eq!(store.keys_matching("what?"), vec!["what?", "whats"]);
+        assert_eq!(store.keys_matching("what\\?"), vec!["what?"]);
+        assert_eq!(store.keys_matching("a\\*b"), vec!["a*b"]);
+        assert_eq!(store.keys_matching("a\\\\b"), vec!["a\\b"]);
+
+        let store = Store::from_range(0..12);
+        assert_eq!(store.keys_matching("1?"), vec!["10", "11"]);
+    }
+
+    #[test]
+    fn test_deser_visit() {
+        #[derive(Default)]
+        struct Counter {
+            entries: usize,
+            integers: usize,
+            keys: Vec<String>,
+        }
+
+        impl StoreVisitor for Counter {
+            fn visit_entry(&mut self, key: &str, value: Value) {
+                self.entries += 1;
+                if matches!(value, Value::Integer(_)) {
+                    self.integers += 1;
+                }
+                self.keys.push(key.to_string());
+            }
+        }
+
+        let mut store = Store::default();
+        for i in 0..100 {
+            store.insert(format!("key{i}"), Value::from(i));
+        }
+        let nested = Value::Map(store.0.clone());
+        store.insert("nested".into(), nested);
+        store.insert("text".into(), Value::String("some text".repeat(20)));
+
+        for bytes in [
+            store.ser().unwrap(),
+            store.ser_with_metadata(&store).unwrap(),
+        ] {
+            let mut counter = Counter::default();
+            Store::deser_visit(&bytes, &mut counter).unwrap();
+
+            assert_eq!(counter.entries, 102);
+            assert_eq!(counter.integers, 100);
+            counter.keys.sort_unstable();
+            let mut expected = store.keys().cloned().collect::<Vec<_>>();
+            expected.sort_unstable();
+            assert_eq!(counter.keys, expected);
+        }
+
+        let mut count = 0;
+        Store::deser_visit(&Store::default().ser().unwrap(), &mut |_: &str, _| {
+            count += 1;
+        })
+        .unwrap();
+        assert_eq!(count, 0);
+
+        let bytes = store.ser().unwrap();
+        assert!(Store::deser_visit(&bytes[..bytes.len() / 2], &mut |_: &str, _| {}).is_err());
     }
-}
 
-#[derive(Debug)]
-#[allow(clippy::module_name_repetitions)]
-pub enum StoreSerError {
-    ExpectedMap(ValueTy),
-    ExpectedMagicBytes,
-    NotEnoughBytes,
-    Value(ValueSerError),
-    Integer(IntegerSerError),
-    SerdeJson(SJError),
-    UnableToConvertToJson,
-    UnsupportedCompression(u8),
-    Lz4Decompress(Lz4DecompressError),
-    MinizDecompresss(MinizDecompressError),
-    Huffman(HuffmanSerError),
-}
+    #[test]
+    fn test_to_kv_pairs() {
+        let store = Store::from_json(json!({
+            "user": {"name": "alice", "address": {"city": "paris", "zip": 75001}},
+            "tags": ["a", "b"],
+            "empty": {},
+            "active": true
+        }));
 
-impl Display for StoreSerError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        match self {
-            StoreSerError::ExpectedMap(t) => write!(
-                f,
-                "Expected to find a map when deserialising, found {t:?} instead"
+        let ser = |v: Value| v.ser(None).unwrap();
+        let expected = vec![
+            ("active".to_string(), ser(Value::Boolean(true))),
+            ("empty".to_string(), ser(Value::Map(HashMap::new()))),
+            (
+                "tags".to_string(),
+                ser(Value::Array(vec![
+                    Value::String("a".into()),
+                    Value::String("b".into()),
+                ])),
             ),
-            StoreSerError::NotEnoughBytes => write!(f, "Not enough bytes"),
-            StoreSerError::ExpectedMagicBytes => write!(f, "Unable to find starting magic bytes"),
-            StoreSerError::Integer(i) => write!(f, "Error with integer: {i}"),
-            StoreSerError::Value(e) => write!(f, "Error with values: {e}"),
-            StoreSerError::SerdeJson(e) => write!(f, "Error with serde_json: {e}"),
-            StoreSerError::UnableToConvertToJson => write!(f, "Unable to convert self to JSON"),
-            StoreSerError::UnsupportedCompression(b) => {
-                write!(f, "Unable to read compression type: {b:#b}")
+            (
+                "user:address:city".to_string(),
+                ser(Value::String("paris".into())),
+            ),
+            ("user:address:zip".to_string(), ser(Value::from(75_001_u64))),
+            ("user:name".to_string(), ser(Value::String("alice".into()))),
+        ];
+        assert_eq!(store.to_kv_pairs(":").unwrap(), expected);
+
+        let keys = store
+            .to_kv_pairs("/")
+            .unwrap()
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            vec![
+                "active",
+                "empty",
+                "tags",
+                "user/address/city",
+                "user/address/zip",
+                "user/name"
+            ]
+        );
+
+        assert!(Store::default().to_kv_pairs(".").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_from_kv_pairs() {
+        let store = Store::from_json(json!({
+            "user": {"name": "alice", "address": {"city": "paris", "zip": 75001}},
+            "tags": ["a", "b"],
+            "empty": {},
+            "active": true
+        }));
+
+        for separator in [".", ":", "::"] {
+            let pairs = store.to_kv_pairs(separator).unwrap();
+            assert_eq!(Store::from_kv_pairs(pairs, separator).unwrap(), store);
+        }
+        assert_eq!(Store::from_kv_pairs(vec![], ".").unwrap(), Store::default());
+        assert!(matches!(
+            Store::from_kv_pairs(store.to_kv_pairs(".").unwrap(), ""),
+            Err(StoreSerError::EmptySeparator)
+        ));
+
+        let ser = |v: Value| v.ser(None).unwrap();
+        let pair = |k: &str| (k.to_string(), ser(Value::Null(())));
+
+        assert!(matches!(
+            Store::from_kv_pairs(vec![pair("a"), pair("a.b")], "."),
+            Err(StoreSerError::ConflictingPath(k)) if k == "a"
+        ));
+        assert!(matches!(
+            Store::from_kv_pairs(vec![pair("a.b.c"), pair("a.b")], "."),
+            Err(StoreSerError::ConflictingPath(k)) if k == "a.b"
+        ));
+        assert!(matches!(
+            Store::from_kv_pairs(vec![pair("a.b"), pair("a.b")], "."),
+            Err(StoreSerError::KeyAlreadyExists(k)) if k == "a.b"
+        ));
+        assert!(matches!(
+            Store::from_kv_pairs(vec![("a".to_string(), vec![])], "."),
+            Err(StoreSerError::Value(_))
+        ));
+
+        let mut trailing = ser(Value::Boolean(true));
+        trailing.push(0);
+        assert!(matches!(
+            Store::from_kv_pairs(vec![("a".to_string(), trailing)], "."),
+            Err(StoreSerError::Value(ValueSerError::TrailingBytes(1)))
+        ));
+    }
+
+    #[test]
+    fn test_into_ordered_iter() {
+        let keys = ["m", "c", "x", "a", "q", "b", "z", "k"];
+        let build = |order: &[usize]| {
+            let mut store = Store::default();
+            for &i in order {
+                store.insert(keys[i].to_string(), Value::from(i));
             }
-            StoreSerError::Lz4Decompress(d) => write!(f, "Error with Lz4 decompression: {d}"),
-            StoreSerError::MinizDecompresss(d) => write!(f, "Error with miniz decompression: {d}"),
-            StoreSerError::Huffman(h) => write!(f, "Error with huffman: {h}"),
+            store
+        };
+
+        let forwards = build(&[0, 1, 2, 3, 4, 5, 6, 7]);
+        let backwards = build(&[7, 6, 5, 4, 3, 2, 1, 0]);
+        let expected = forwards.clone().into_sorted_vec();
+
+        for _ in 0..10 {
+            assert_eq!(
+                forwards.clone().into_ordered_iter().collect::<Vec<_>>(),
+                expected
+            );
+            assert_eq!(
+                backwards.clone().into_ordered_iter().collect::<Vec<_>>(),
+                expected
+            );
         }
+
+        let mut iter = forwards.into_ordered_iter();
+        assert_eq!(iter.len(), keys.len());
+        assert_eq!(iter.next(), Some(("a".to_string(), Value::from(3_usize))));
+        assert_eq!(
+            iter.next_back(),
+            Some(("z".to_string(), Value::from(6_usize)))
+        );
+        assert_eq!(iter.len(), keys.len() - 2);
+
+        let array = Store::from_json(json!(["x", "y", "z"]))
+            .into_ordered_iter()
+            .map(|(k, _)| k)
+            .collect::<Vec<_>>();
+        assert_eq!(array, vec!["0", "1", "2"]);
     }
-}
 
-impl From<ValueSerError> for StoreSerError {
-    fn from(value: ValueSerError) -> Self {
-        Self::Value(value)
+    #[test]
+    fn test_diff_json_patch() {
+        let a = Store::from_json(json!({
+            "name": "alice",
+            "age": 30,
+            "tags": ["a", "b", "c"],
+            "address": {"city": "paris", "zip": "75001"},
+            "old": true,
+            "a/b~c": 1
+        }));
+        let b = Store::from_json(json!({
+            "name": "alice",
+            "age": 31,
+            "tags": ["a", "x"],
+            "address": {"city": "paris", "country": "france"},
+            "new": [1, 2],
+            "a/b~c": 2
+        }));
+
+        let patch = a.diff_json_patch(&b).unwrap();
+        assert_eq!(
+            patch,
+            json!([
+                {"op": "remove", "path": "/old"},
+                {"op": "replace", "path": "/a~1b~0c", "value": 2},
+                {"op": "remove", "path": "/address/zip"},
+                {"op": "add", "path": "/address/country", "value": "france"},
+                {"op": "replace", "path": "/age", "value": 31},
+                {"op": "add", "path": "/new", "value": [1, 2]},
+                {"op": "replace", "path": "/tags/1", "value": "x"},
+                {"op": "remove", "path": "/tags/2"},
+            ])
+        );
+
+        let mut patched = a.clone();
+        patched.apply_json_patch(&patch).unwrap();
+        assert_eq!(patched, b);
+
+        let short = Store::from_json(json!([1]));
+        let long = Store::from_json(json!([1, 2, 3]));
+        for (from, to) in [(&a, &b), (&short, &long), (&long, &short), (&a, &long)] {
+            let patch = from.diff_json_patch(to).unwrap();
+
+            let mut patched = from.clone();
+            patched.apply_json_patch(&patch).unwrap();
+            assert_eq!(&patched, to);
+
+            //check against a reference implementation too
+            let mut doc = from.clone().to_json_object().unwrap();
+            let patch: json_patch::Patch = serde_json::from_value(patch).unwrap();
+            json_patch::patch(&mut doc, &patch).unwrap();
+            assert_eq!(doc, to.clone().to_json_object().unwrap());
+
+            let mut patched = from.clone();
+            patched
+                .apply_json_patch(
+                    &serde_json::to_value(json_patch::diff(
+                        &from.clone().to_json_object().unwrap(),
+                        &to.clone().to_json_object().unwrap(),
+                    ))
+                    .unwrap(),
+                )
+                .unwrap();
+            assert_eq!(&patched, to);
+        }
+
+        assert_eq!(a.diff_json_patch(&a).unwrap(), json!([]));
     }
-}
-impl From<SJError> for StoreSerError {
-    fn from(value: SJError) -> Self {
-        Self::SerdeJson(value)
+
+    #[test]
+    fn test_apply_json_patch() {
+        let original = Store::from_json(json!({
+            "name": "alice",
+            "tags": ["a", "b"],
+            "address": {"city": "paris"}
+        }));
+        let patched = |patch: serde_json::Value| {
+            let mut store = original.clone();
+            store.apply_json_patch(&patch).map(|()| store.to_json())
+        };
+
+        assert_eq!(
+            patched(json!([
+                {"op": "add", "path": "/age", "value": 30},
+                {"op": "add", "path": "/tags/1", "value": "x"},
+                {"op": "add", "path": "/tags/-", "value": "z"},
+                {"op": "add", "path": "/address/zip", "value": "75001"}
+            ]))
+            .unwrap(),
+            Some(json!({
+                "name": "alice",
+                "age": 30,
+                "tags": ["a", "x", "b", "z"],
+                "address": {"city": "paris", "zip": "75001"}
+            }))
+        );
+        assert_eq!(
+            patched(json!([
+                {"op": "remove", "path": "/tags/0"},
+                {"op": "remove", "path": "/address"}
+            ]))
+            .unwrap(),
+            Some(json!({"name": "alice", "tags": ["b"]}))
+        );
+        assert_eq!(
+            patched(json!([
+                {"op": "replace", "path": "/name", "value": "bob"},
+                {"op": "replace", "path": "/tags/1", "value": {"nested": true}}
+            ]))
+            .unwrap(),
+            Some(json!({
+                "name": "bob",
+                "tags": ["a", {"nested": true}],
+                "address": {"city": "paris"}
+            }))
+        );
+        assert_eq!(
+            patched(json!([
+                {"op": "move", "from": "/address/city", "path": "/city"},
+                {"op": "move", "from": "/tags/0", "path": "/tags/1"}
+            ]))
+            .unwrap(),
+            Some(json!({"name": "alice", "tags": ["b", "a"], "address": {}, "city": "paris"}))
+        );
+        assert_eq!(
+            patched(json!([
+                {"op": "copy", "from": "/tags", "path": "/address/tags"},
+                {"op": "copy", "from": "/name", "path": "/tags/0"}
+            ]))
+            .unwrap(),
+            Some(json!({
+                "name": "alice",
+                "tags": ["alice", "a", "b"],
+                "address": {"city": "paris", "tags": ["a", "b"]}
+            }))
+        );
+        assert_eq!(
+            patched(json!([
+                {"op": "test", "path": "/name", "value": "alice"},
+                {"op": "test", "path": "/tags", "value": ["a", "b"]},
+                {"op": "test", "path": "", "value": original.clone().to_json()}
+            ]))
+            .unwrap(),
+            original.clone().to_json()
+        );
+        assert_eq!(
+            patched(json!([
+                {"op": "replace", "path": "", "value": {"whole": "store"}}
+            ]))
+            .unwrap(),
+            Some(json!({"whole": "store"}))
+        );
     }
-}
-impl From<IntegerSerError> for StoreSerError {
-    fn from(value: IntegerSerError) -> Self {
-        Self::Integer(value)
+
+    #[test]
+    fn test_apply_json_patch_clears_expiries() {
+        let now = NaiveDateTime::default();
+        let mut store = Store::default();
+        for k in ["removed", "moved", "replaced", "nested", "kept"] {
+            store.insert_with_ttl(
+                k.into(),
+                Value::convert_from_json(json!({"a": 1})),
+                TimeDelta::seconds(1),
+                now,
+            );
+        }
+
+        store
+            .apply_json_patch(&json!([
+                {"op": "remove", "path": "/removed"},
+                {"op": "move", "from": "/moved", "path": "/elsewhere"},
+                {"op": "replace", "path": "/replaced", "value": 2},
+                {"op": "replace", "path": "/nested/a", "value": 2},
+                {"op": "add", "path": "/removed", "value": 3}
+            ]))
+            .unwrap();
+        for k in ["removed", "moved", "elsewhere", "replaced"] {
+            assert_eq!(store.expiry(k), None, "{k}");
+        }
+        for k in ["nested", "kept"] {
+            assert_eq!(store.expiry(k), Some(now + TimeDelta::seconds(1)), "{k}");
+        }
+
+        store
+            .apply_json_patch(&json!([
+                {"op": "replace", "path": "", "value": {"kept": 1}}
+            ]))
+            .unwrap();
+        assert_eq!(store.expiry("kept"), None);
+        assert_eq!(store.purge_expired(now + TimeDelta::days(1)), 0);
     }
-}
-impl From<Lz4DecompressError> for StoreSerError {
-    fn from(value: Lz4DecompressError) -> Self {
-        Self::Lz4Decompress(value)
+
+    #[test]
+    fn test_apply_json_patch_errors() {
+        let original = Store::from_json(json!({
+            "name": "alice",
+            "tags": ["a", "b"],
+            "address": {"city": "paris"}
+        }));
+        let patched = |patch: serde_json::Value| {
+            let mut store = original.clone();
+            store.apply_json_patch(&patch).map(|()| store)
+        };
+
+        //a failing test stops the whole patch, including anything before it
+        let mut store = original.clone();
+        assert!(matches!(
+            store.apply_json_patch(&json!([
+                {"op": "replace", "path": "/name", "value": "bob"},
+                {"op": "test", "path": "/tags/0", "value": "not a"}
+            ])),
+            Err(StoreSerError::JsonPatchTestFailed(path)) if path == "/tags/0"
+        ));
+        assert_eq!(store, original);
+
+        assert!(matches!(
+            patched(json!([{"op": "remove", "path": "/missing"}])),
+            Err(StoreSerError::MissingKey(path)) if path == "/missing"
+        ));
+        assert!(matches!(
+            patched(json!([{"op": "add", "path": "/tags/5", "value": 1}])),
+            Err(StoreSerError::IndexOutOfBounds(5, 2))
+        ));
+        assert!(matches!(
+            patched(json!([{"op": "add", "path": "/tags/01", "value": 1}])),
+            Err(StoreSerError::MissingKey(_))
+        ));
+        assert!(matches!(
+            patched(json!([{"op": "move", "from": "/address", "path": "/address/inner"}])),
+            Err(StoreSerError::InvalidJsonPatch(_))
+        ));
+        assert!(matches!(
+            patched(json!([{"op": "frobnicate", "path": "/name"}])),
+            Err(StoreSerError::InvalidJsonPatch(_))
+        ));
+        assert!(matches!(
+            patched(json!([{"op": "add", "path": "name", "value": 1}])),
+            Err(StoreSerError::InvalidJsonPatch(_))
+        ));
+        assert!(matches!(
+            patched(json!({"op": "add"})),
+            Err(StoreSerError::InvalidJsonPatch(_))
+        ));
+        assert!(matches!(
+            patched(json!([{"op": "replace", "path": "", "value": [1]}])),
+            Err(StoreSerError::ExpectedMap(ValueTy::Array))
+        ));
+        assert!(matches!(
+            patched(json!([{"op": "add", "path": "", "value": [1]}])),
+            Err(StoreSerError::ExpectedMap(ValueTy::Array))
+        ));
     }
-}
-impl From<MinizDecompressError> for StoreSerError {
-    fn from(value: MinizDecompressError) -> Self {
-        Self::MinizDecompresss(value)
+
+    #[test]
+    fn test_enforce_key_limits() {
+        let build = || {
+            let mut store = Store::default();
+            for k in [
+                "short",
+                "exactly8",
+                "abcdefghij",
+                "abcdefghik",
+                "abcdefgh~1x",
+                "clé-très-longue",
+            ] {
+                store.insert(k.into(), Value::String(k.into()));
+            }
+            store
+        };
+
+        let mut store = build();
+        assert!(matches!(
+            store.enforce_key_limits(8, LimitAction::Error),
+            Err(StoreSerError::KeyTooLong(k)) if k == "abcdefghij"
+        ));
+        assert_eq!(store, build());
+        store.enforce_key_limits(100, LimitAction::Error).unwrap();
+        assert_eq!(store, build());
+
+        let mut store = build();
+        store.insert_with_ttl(
+            "abcdefghij".into(),
+            Value::Null(()),
+            TimeDelta::seconds(1),
+            NaiveDateTime::default(),
+        );
+        store.enforce_key_limits(8, LimitAction::Drop).unwrap();
+        let mut keys = store.keys().cloned().collect::<Vec<_>>();
+        keys.sort_unstable();
+        assert_eq!(keys, vec!["exactly8", "short"]);
+        assert_eq!(store.expiry("abcdefghij"), None);
+
+        let mut store = build();
+        store.insert_with_ttl(
+            "abcdefghik".into(),
+            Value::Null(()),
+            TimeDelta::seconds(1),
+            NaiveDateTime::default(),
+        );
+        store.enforce_key_limits(8, LimitAction::Truncate).unwrap();
+        assert_eq!(store.len(), 6);
+        assert!(store.keys().all(|k| k.len() <= 8));
+        //truncated in sorted order, and "~" sorts after letters
+        assert_eq!(
+            store.get("abcdefgh"),
+            Some(&Value::String("abcdefghij".into()))
+        );
+        assert_eq!(store.get("abcdef~1"), Some(&Value::Null(())));
+        assert!(store.expiry("abcdef~1").is_some());
+        assert_eq!(
+            store.get("abcdef~2"),
+            Some(&Value::String("abcdefgh~1x".into()))
+        );
+        //"é" is two bytes, so can't be split
+        assert!(store.contains_key("clé-tr"));
+        assert_eq!(
+            store.get("exactly8"),
+            Some(&Value::String("exactly8".into()))
+        );
+
+        let mut store = build();
+        assert!(matches!(
+            store.enforce_key_limits(1, LimitAction::Truncate),
+            Err(StoreSerError::KeyAlreadyExists(_))
+        ));
+        assert_eq!(store, build());
     }
-}
-impl From<HuffmanSerError> for StoreSerError {
-    fn from(value: HuffmanSerError) -> Self {
-        Self::Huffman(value)
+
+    #[test]
+    fn test_canonical_bytes() {
+        let mut a = Store::default();
+        for i in 0..50_u64 {
+            a.insert(format!("key{i}"), Value::from(i * 1_000));
+        }
+        a.insert(
+            "nested".into(),
+            Value::Map(
+                [("x", Value::from(75_001_i64)), ("y", Value::from(-3))]
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), v))
+                    .collect(),
+            ),
+        );
+        a.insert_with_ttl(
+            "expires".into(),
+            Value::String("soon".into()),
+            TimeDelta::seconds(5),
+            NaiveDateTime::default(),
+        );
+
+        //same entries, built in the opposite order with differently-typed integers
+        let mut b = Store::default();
+        b.insert_with_ttl(
+            "expires".into(),
+            Value::String("soon".into()),
+            TimeDelta::seconds(5),
+            NaiveDateTime::default(),
+        );
+        b.insert(
+            "nested".into(),
+            Value::Map(
+                [("y", Value::from(-3_i8)), ("x", Value::from(75_001_u32))]
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), v))
+                    .collect(),
+            ),
+        );
+        for i in (0..50_i64).rev() {
+            b.insert(format!("key{i}"), Value::from(i * 1_000));
+        }
+        //an expiry for a key which doesn't exist any more isn't part of the data
+        b.insert_with_ttl(
+            "gone".into(),
+            Value::Null(()),
+            TimeDelta::seconds(5),
+            NaiveDateTime::default(),
+        );
+        b.remove("gone");
+
+        let bytes = a.canonical_bytes().unwrap();
+        assert_eq!(bytes, b.canonical_bytes().unwrap());
+        assert_eq!(
+            bytes[8],
+            u8::from(CompressionType::None) | EXPIRY_FLAG | VERSIONED_FLAG
+        );
+        assert_eq!(bytes[9], FORMAT_VERSION);
+        assert_eq!(Store::deser(&bytes).unwrap(), a);
+
+        b.insert("key0".into(), Value::from(1));
+        assert_ne!(bytes, b.canonical_bytes().unwrap());
+
+        assert_eq!(
+            Store::default().canonical_bytes().unwrap(),
+            Store::default().canonical_bytes().unwrap()
+        );
     }
-}
 
-#[cfg(feature = "std")]
-impl std::error::Error for StoreSerError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        match self {
-            Self::Integer(i) => Some(i),
-            Self::Value(e) => Some(e),
-            Self::SerdeJson(e) => Some(e),
-            Self::Lz4Decompress(d) => Some(d),
-            Self::MinizDecompresss(d) => Some(d),
-            Self::Huffman(h) => Some(h),
-            _ => None,
+    #[test]
+    fn test_merge_logged() {
+        let ours = Store::from_json(json!({"a": 1, "b": "same", "c": true}));
+        let mut theirs = Store::from_json(json!({"a": 2, "b": "same", "c": false, "d": null}));
+        theirs.insert_with_ttl(
+            "c".into(),
+            Value::Boolean(false),
+            TimeDelta::seconds(5),
+            NaiveDateTime::default(),
+        );
+
+        let mut kept_ours = ours.clone();
+        let conflicts = kept_ours.merge_logged(theirs.clone(), MergeStrategy::KeepOurs);
+        assert_eq!(
+            conflicts,
+            vec![
+                Conflict {
+                    key: "a".into(),
+                    kept: Value::from(1_u64),
+                    discarded: Value::from(2_u64),
+                },
+                Conflict {
+                    key: "c".into(),
+                    kept: Value::Boolean(true),
+                    discarded: Value::Boolean(false),
+                },
+            ]
+        );
+        assert_eq!(kept_ours.get("a"), Some(&Value::from(1_u64)));
+        assert_eq!(kept_ours.get("c"), Some(&Value::Boolean(true)));
+        assert_eq!(kept_ours.expiry("c"), None);
+        assert!(kept_ours.contains_key("d"));
+
+        let mut kept_theirs = ours;
+        let conflicts = kept_theirs.merge_logged(theirs.clone(), MergeStrategy::KeepTheirs);
+        assert_eq!(conflicts.len(), 2);
+        assert!(conflicts
+            .iter()
+            .all(|c| theirs.get(&c.key) == Some(&c.kept)));
+        assert_eq!(conflicts[0].discarded, Value::from(1_u64));
+        assert_eq!(kept_theirs.get("a"), Some(&Value::from(2_u64)));
+        assert!(kept_theirs.expiry("c").is_some());
+        assert_eq!(kept_theirs.len(), 4);
+
+        let mut store = Store::from_json(json!({"x": 1}));
+        assert!(store
+            .merge_logged(Store::from_json(json!({"y": 2})), MergeStrategy::KeepOurs)
+            .is_empty());
+        assert_eq!(store.len(), 2);
+    }
+
+    #[test]
+    fn test_memory_footprint() {
+        let empty = Store::default().memory_footprint();
+        assert_eq!(empty, core::mem::size_of::<Store>());
+
+        let mut small = Store::default();
+        small.insert("a".into(), Value::from(1));
+        let small = small.memory_footprint();
+        assert!(small > empty);
+
+        let mut large = Store::default();
+        for i in 0..100 {
+            large.insert(format!("key{i}"), Value::String("x".repeat(1_000)));
+        }
+        large.insert(
+            "nested".into(),
+            Value::Array(vec![Value::Binary(vec![0; 10_000])]),
+        );
+        let large = large.memory_footprint();
+        //every string and the binary are owned by the store, so must be counted
+        assert!(large >= 100 * 1_000 + 10_000);
+        assert!(large > small);
+    }
+
+    #[test]
+    fn test_single_entry() {
+        assert!(matches!(
+            Store::default().single_entry(),
+            Err(StoreSerError::ExpectedSingleEntry(0))
+        ));
+
+        let mut store = Store::default();
+        store.insert("only".into(), Value::Boolean(true));
+        assert_eq!(
+            store.single_entry().unwrap(),
+            (&"only".to_string(), &Value::Boolean(true))
+        );
+
+        store.insert("another".into(), Value::Null(()));
+        assert!(matches!(
+            store.single_entry(),
+            Err(StoreSerError::ExpectedSingleEntry(2))
+        ));
+    }
+
+    #[test]
+    fn test_keys_sorted_by() {
+        let mut store = Store::default();
+        for (k, v) in [("a", 5), ("b", -2), ("c", 40), ("d", 5), ("e", 0)] {
+            store.insert(k.into(), Value::from(v));
+        }
+        store.insert("not a number".into(), Value::Null(()));
+
+        let as_int = |v: &Value| v.as_int().and_then(|i| i64::try_from(*i).ok());
+        let descending = store.keys_sorted_by(|(_, a), (_, b)| as_int(b).cmp(&as_int(a)));
+        assert_eq!(descending, vec!["c", "a", "d", "e", "b", "not a number"]);
+
+        assert!(Store::default()
+            .keys_sorted_by(|_, _| core::cmp::Ordering::Equal)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_from_vars() {
+        let vars = [
+            ("APP__DB__HOST", "localhost"),
+            ("APP__DB__PORT", "5432"),
+            ("APP__DEBUG", "true"),
+            ("APP__NAME", "-12abc"),
+            ("APP__CACHE", "1"),
+            ("APP__CACHE__SIZE", "-1"),
+            ("APP__ZIP", "01234"),
+            ("APP__OFFSET", "+5"),
+            ("APP__ZERO", "0"),
+            ("APP", "skipped"),
+            ("OTHER__DB__HOST", "skipped"),
+            ("APPLE__DB__HOST", "skipped"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+
+        let store = Store::from_vars(vars.clone(), "APP", "__").unwrap();
+        assert_eq!(
+            Store::from_vars(vars.clone(), "APP__", "__").unwrap(),
+            store
+        );
+        assert_eq!(
+            store.to_json(),
+            Some(json!({
+                "DB": {"HOST": "localhost", "PORT": 5432},
+                "DEBUG": true,
+                "NAME": "-12abc",
+                "CACHE": {"SIZE": -1},
+                "ZIP": "01234",
+                "OFFSET": "+5",
+                "ZERO": 0,
+            }))
+        );
+
+        assert!(Store::from_vars([], "APP", "__").unwrap().is_empty());
+        assert!(matches!(
+            Store::from_vars(vars, "APP", ""),
+            Err(StoreSerError::EmptySeparator)
+        ));
+    }
+
+    #[test]
+    fn test_deser_in() {
+        let mut with_expiry = Store::from_json(json!({"a": 1, "b": "two"}));
+        with_expiry.insert_with_ttl(
+            "c".into(),
+            Value::Null(()),
+            TimeDelta::seconds(1),
+            NaiveDateTime::default(),
+        );
+        let repetitive = Store::from_range(0..1_000);
+        let stores = [
+            Store::default(),
+            Store::from_json(json!({"nested": {"list": [1, 2, 3], "text": "hello"}})),
+            with_expiry,
+            repetitive,
+            Store::from_json(json!([true, false])),
+        ];
+
+        //reused across every store, going from small to large and back again
+        let mut buf = vec![];
+        for store in stores.iter().chain(stores.iter().rev()) {
+            let bytes = store.ser().unwrap();
+            assert_eq!(
+                Store::deser_in(&bytes, &mut buf).unwrap(),
+                Store::deser(&bytes).unwrap()
+            );
+        }
+
+        assert!(matches!(
+            Store::deser_in(b"NOTASTORE", &mut buf),
+            Err(StoreSerError::ExpectedMagicBytes)
+        ));
+        let bytes = stores[1].ser().unwrap();
+        assert_eq!(Store::deser_in(&bytes, &mut buf).unwrap(), stores[1]);
+
+        //uncompressed bodies are read straight from the input, so the buffer isn't touched
+        let bytes = stores[0].ser().unwrap();
+        assert_eq!(bytes[8] & 0b0111, u8::from(CompressionType::None));
+        let mut buf = vec![];
+        assert_eq!(Store::deser_in(&bytes, &mut buf).unwrap(), stores[0]);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_strip_large_binaries() {
+        let mut store = Store::default();
+        store.insert("big".into(), Value::Binary(vec![0xAB; 1_000]));
+        store.insert("small".into(), Value::Binary(vec![0xCD; 16]));
+        store.insert(
+            "nested".into(),
+            Value::Array(vec![
+                Value::Binary(vec![0; 17]),
+                Value::Map([("deeper".into(), Value::Binary(vec![0; 64]))].into()),
+            ]),
+        );
+        store.insert("other".into(), Value::from(1));
+
+        assert_eq!(store.strip_large_binaries(16), 3);
+
+        assert_eq!(
+            store.get("big"),
+            Some(&Value::String("<binary: 1000 bytes>".into()))
+        );
+        assert_eq!(store.get("small"), Some(&Value::Binary(vec![0xCD; 16])));
+        assert_eq!(
+            store.get("nested"),
+            Some(&Value::Array(vec![
+                Value::String("<binary: 17 bytes>".into()),
+                Value::Map([("deeper".into(), Value::String("<binary: 64 bytes>".into()))].into()),
+            ]))
+        );
+        assert_eq!(store.get("other"), Some(&Value::from(1)));
+
+        assert_eq!(store.strip_large_binaries(16), 0);
+    }
+
+    #[test]
+    fn test_group_by() {
+        let store = Store::from_json(json!([
+            {"name": "apple", "category": "fruit"},
+            {"name": "carrot", "category": "vegetable"},
+            {"name": "banana", "category": "fruit"},
+            {"name": "rock"},
+            {"name": "seven", "category": 7},
+        ]));
+
+        let grouped = store.group_by("category", Some("other")).unwrap();
+        assert_eq!(grouped.len(), 4);
+        assert_eq!(
+            grouped.get("fruit"),
+            Some(&Value::Array(vec![
+                Value::convert_from_json(json!({"name": "apple", "category": "fruit"})),
+                Value::convert_from_json(json!({"name": "banana", "category": "fruit"})),
+            ]))
+        );
+        assert_eq!(
+            grouped.get("vegetable"),
+            Some(&Value::Array(vec![Value::convert_from_json(
+                json!({"name": "carrot", "category": "vegetable"})
+            )]))
+        );
+        assert_eq!(
+            grouped.get("other"),
+            Some(&Value::Array(vec![Value::convert_from_json(
+                json!({"name": "rock"})
+            )]))
+        );
+        assert!(grouped.get("7").is_some());
+
+        assert!(matches!(
+            store.group_by("category", None),
+            Err(StoreSerError::MissingKey(k)) if k == "category"
+        ));
+        assert!(matches!(
+            Store::from_json(json!({"a": 1})).group_by("category", None),
+            Err(StoreSerError::ExpectedArray)
+        ));
+    }
+
+    #[test]
+    fn test_count_by() {
+        let store = Store::from_json(json!([
+            {"colour": "red", "size": 1},
+            {"colour": "blue", "size": 2},
+            {"colour": "red", "size": 3},
+            {"colour": "green", "size": 1},
+            {"colour": "red", "size": 2},
+            {"size": 5},
+        ]));
+
+        let counts = store.count_by("colour", Some("unknown")).unwrap();
+        assert_eq!(counts.len(), 4);
+        assert_eq!(counts.get("red"), Some(&Value::from(3)));
+        assert_eq!(counts.get("blue"), Some(&Value::from(1)));
+        assert_eq!(counts.get("green"), Some(&Value::from(1)));
+        assert_eq!(counts.get("unknown"), Some(&Value::from(1)));
+
+        let by_size = store.count_by("size", None).unwrap();
+        assert_eq!(by_size.get("1"), Some(&Value::from(2)));
+        assert_eq!(by_size.get("5"), Some(&Value::from(1)));
+
+        assert!(matches!(
+            store.count_by("colour", None),
+            Err(StoreSerError::MissingKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_remove_prefix() {
+        let mut store = Store::default();
+        for k in ["session:a", "session:b", "session:c", "sessions", "user:a"] {
+            store.insert(k.into(), Value::Boolean(true));
+        }
+        store.insert_with_ttl(
+            "session:d".into(),
+            Value::Null(()),
+            TimeDelta::seconds(1),
+            NaiveDateTime::default(),
+        );
+
+        assert_eq!(store.remove_prefix("session:"), 4);
+        assert_eq!(store.expiry("session:d"), None);
+
+        let mut remaining = store.keys().cloned().collect::<Vec<_>>();
+        remaining.sort_unstable();
+        assert_eq!(
+            remaining,
+            vec!["sessions".to_string(), "user:a".to_string()]
+        );
+
+        assert_eq!(store.remove_prefix("session:"), 0);
+        assert_eq!(store.remove_prefix(""), 2);
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_into_parts() {
+        let array = vec![Value::from(1), Value::String("two".into())];
+        assert_eq!(
+            Store::from_array(array.clone()).into_parts(),
+            StoreParts::Array(array)
+        );
+
+        let mut store = Store::from_json(json!({"a": 1, "b": true}));
+        store.insert_with_ttl(
+            "c".into(),
+            Value::Null(()),
+            TimeDelta::seconds(1),
+            NaiveDateTime::default(),
+        );
+        let StoreParts::Map(map) = store.into_parts() else {
+            panic!("expected a map");
+        };
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get("a"), Some(&Value::from(1)));
+        assert_eq!(map.get("c"), Some(&Value::Null(())));
+
+        //only a lone array under the key counts as an array
+        let not_array = Store::from_json(json!({"JSON": 1}));
+        assert!(matches!(not_array.into_parts(), StoreParts::Map(m) if m.len() == 1));
+    }
+
+    #[test]
+    fn test_find() {
+        let store = Store::from_json(json!({
+            "d": "four",
+            "a": 1,
+            "c": "three",
+            "b": true,
+        }));
+
+        assert_eq!(
+            store.find(|_, v| v.is_str()),
+            Some((&"c".to_string(), &Value::String("three".into())))
+        );
+        assert_eq!(
+            store.find(|k, _| k > "a"),
+            Some((&"b".to_string(), &Value::Boolean(true)))
+        );
+        assert_eq!(store.find(|_, v| v.is_null()), None);
+        assert_eq!(Store::default().find(|_, _| true), None);
+    }
+
+    #[test]
+    fn test_partition() {
+        let mut store = Store::from_json(json!({
+            "a": 1,
+            "b": "two",
+            "c": 3,
+            "d": [4],
+            "e": null,
+        }));
+        store.insert_with_ttl(
+            "f".into(),
+            Value::from(6),
+            TimeDelta::seconds(1),
+            NaiveDateTime::default(),
+        );
+
+        let (ints, others) = store.partition(|_, v| v.is_int());
+        assert!(ints.expiry("f").is_some());
+        assert_eq!(others.expiry("f"), None);
+        assert_eq!(ints.to_json(), Some(json!({"a": 1, "c": 3, "f": 6})));
+        assert_eq!(
+            others.to_json(),
+            Some(json!({"b": "two", "d": [4], "e": null}))
+        );
+
+        let array = Store::from_json(json!([1, "x", 2, true, 3]));
+        let (ints, others) = array.clone().partition(|_, v| v.is_int());
+        assert_eq!(ints.to_json(), Some(json!([1, 2, 3])));
+        assert_eq!(others.to_json(), Some(json!(["x", true])));
+
+        let (even, odd) = array.partition(|i, _| i.parse::<usize>().unwrap() % 2 == 0);
+        assert_eq!(even.to_json(), Some(json!([1, 2, 3])));
+        assert_eq!(odd.to_json(), Some(json!(["x", true])));
+    }
+
+    #[test]
+    fn test_metadata() {
+        let store = Store::from_json(json!({"a": 1, "b": "some text", "c": [true, null]}));
+        let metadata = Store::from_json(json!({"creator": "souris", "schema": 3}));
+
+        let bytes = store.ser_with_metadata(&metadata).unwrap();
+        assert_ne!(bytes[8] & METADATA_FLAG, 0);
+        assert_eq!(
+            Store::deser_with_metadata(&bytes).unwrap(),
+            (store.clone(), Some(metadata))
+        );
+        assert_eq!(Store::deser(&bytes).unwrap(), store);
+        assert_eq!(Store::deser_strict(&bytes).unwrap(), store);
+
+        let bytes = store.ser().unwrap();
+        assert_eq!(bytes[8] & METADATA_FLAG, 0);
+        assert_eq!(
+            Store::deser_with_metadata(&bytes).unwrap(),
+            (store.clone(), None)
+        );
+
+        let bytes = store.ser_with_metadata(&Store::default()).unwrap();
+        assert_eq!(
+            Store::deser_with_metadata(&bytes).unwrap(),
+            (store, Some(Store::default()))
+        );
+    }
+
+    #[test]
+    fn test_retain_keys() {
+        let mut store = Store::from_json(json!({"a": 1, "b": 2, "c": 3, "d": 4, "e": 5}));
+        store.retain_keys(&["b", "d", "z"]);
+        assert_eq!(store.to_json(), Some(json!({"b": 2, "d": 4})));
+
+        let mut store = Store::from_json(json!([1, 2, 3, 4, 5]));
+        store.retain_keys(&["3", "1", "not an index", "10"]);
+        assert_eq!(store.to_json(), Some(json!([2, 4])));
+    }
+
+    #[test]
+    fn test_get_or_insert_with() {
+        let mut store = Store::from_json(json!({"a": 1}));
+
+        let a = store.get_or_insert_with("a".to_string(), || unreachable!("a already exists"));
+        assert_eq!(a, &mut Value::Integer(1.into()));
+        *a = Value::Boolean(true);
+
+        let mut calls = 0;
+        let b = store.get_or_insert_with("b".to_string(), || {
+            calls += 1;
+            Value::Null(())
+        });
+        assert_eq!(b, &mut Value::Null(()));
+        assert_eq!(calls, 1);
+
+        assert_eq!(store.to_json(), Some(json!({"a": true, "b": null})));
+    }
+
+    #[test]
+    fn test_reset_values() {
+        let store = Store::from_json(json!({"a": 1, "b": {"c": "d", "e": [true, 2.5]}}));
+
+        let mut shallow = store.clone();
+        shallow.reset_values(&Value::Null(()), false);
+        assert_eq!(shallow.to_json(), Some(json!({"a": null, "b": null})));
+
+        let mut deep = store;
+        deep.reset_values(&Value::Null(()), true);
+        assert_eq!(
+            deep.to_json(),
+            Some(json!({"a": null, "b": {"c": null, "e": [null, null]}}))
+        );
+    }
+
+    #[test]
+    fn test_join_prefixed() {
+        let mut store = Store::default();
+        store
+            .join_prefixed("users", Store::from_json(json!({"a": 1, "b": 2})))
+            .unwrap();
+        store
+            .join_prefixed("groups", Store::from_json(json!({"a": [1, 2]})))
+            .unwrap();
+        assert_eq!(
+            store.clone().to_json(),
+            Some(json!({"users.a": 1, "users.b": 2, "groups.a": [1, 2]}))
+        );
+
+        let before = store.clone();
+        assert!(matches!(
+            store.join_prefixed("users", Store::from_json(json!({"c": 3, "a": 4}))),
+            Err(StoreSerError::KeyAlreadyExists(k)) if k == "users.a"
+        ));
+        assert_eq!(store, before);
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let store = Store::from_json(json!([
+            {"id": 1, "name": "Alice", "tags": ["a", "b"]},
+            {"id": 2, "name": "Bob, Jr.", "note": "said \"hi\""},
+            {"id": 3, "name": null},
+        ]));
+
+        let mut out = vec![];
+        store.to_csv(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "id,name,note,tags\n\
+            1,Alice,,\"[\"\"a\"\",\"\"b\"\"]\"\n\
+            2,\"Bob, Jr.\",\"said \"\"hi\"\"\",\n\
+            3,,,\n"
+        );
+
+        assert!(matches!(
+            Store::from_json(json!({"a": 1})).to_csv(vec![]),
+            Err(StoreSerError::ExpectedArray)
+        ));
+        assert!(matches!(
+            Store::from_json(json!([{"a": 1}, 2])).to_csv(vec![]),
+            Err(StoreSerError::ExpectedMap(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_json_with_options() {
+        let json = json!({"Name": "  Alice ", "Nested": {"Inner": [" a", "b "]}, "n": 1});
+
+        assert_eq!(
+            Store::from_json_with_options(json.clone(), IngestOptions::default()),
+            Store::from_json(json.clone())
+        );
+
+        let trimmed = IngestOptions {
+            trim_strings: true,
+            ..IngestOptions::default()
+        };
+        assert_eq!(
+            Store::from_json_with_options(json.clone(), trimmed).to_json(),
+            Some(json!({"Name": "Alice", "Nested": {"Inner": ["a", "b"]}, "n": 1}))
+        );
+
+        let lowercased = IngestOptions {
+            lowercase_keys: true,
+            ..IngestOptions::default()
+        };
+        assert_eq!(
+            Store::from_json_with_options(json, lowercased).to_json(),
+            Some(json!({"name": "  Alice ", "nested": {"inner": [" a", "b "]}, "n": 1}))
+        );
+
+        let array = Store::from_json_with_options(json!([{"A": " b "}]), lowercased);
+        assert!(array.as_array().is_some());
+        assert_eq!(array.to_json(), Some(json!([{"a": " b "}])));
+    }
+
+    #[test]
+    fn test_null_policy() {
+        let json = json!({"a": null, "b": 1, "nested": {"c": null, "d": [null, true]}});
+        let with_policy = |null_policy, json| {
+            Store::from_json_with_options(
+                json,
+                IngestOptions {
+                    null_policy,
+                    ..IngestOptions::default()
+                },
+            )
+        };
+
+        let as_null = with_policy(NullPolicy::AsNull, json.clone());
+        assert_eq!(as_null, Store::from_json(json.clone()));
+        assert_eq!(as_null.get("a"), Some(&Value::Null(())));
+
+        assert_eq!(
+            with_policy(NullPolicy::Skip, json.clone()).to_json(),
+            Some(json!({"b": 1, "nested": {"d": [null, true]}}))
+        );
+        assert!(with_policy(NullPolicy::Skip, json!(null)).is_empty());
+
+        assert_eq!(
+            with_policy(NullPolicy::AsEmptyString, json).to_json(),
+            Some(json!({"a": "", "b": 1, "nested": {"c": "", "d": ["", true]}}))
+        );
+        assert_eq!(
+            with_policy(NullPolicy::AsEmptyString, json!(null)).get(JSON_KEY),
+            Some(&Value::String(String::new()))
+        );
+    }
+
+    #[test]
+    fn test_sequential_maps_to_arrays() {
+        let options = IngestOptions {
+            sequential_maps_to_arrays: true,
+            ..IngestOptions::default()
+        };
+
+        let store = Store::from_json_with_options(json!({"1": "b", "0": "a", "2": "c"}), options);
+        assert_eq!(
+            store.as_array(),
+            Some(&vec![
+                Value::String("a".into()),
+                Value::String("b".into()),
+                Value::String("c".into())
+            ])
+        );
+
+        let nested = json!({"list": {"0": {"0": true}, "1": null}, "other": 1});
+        assert_eq!(
+            Store::from_json_with_options(nested.clone(), options).to_json(),
+            Some(json!({"list": [[true], null], "other": 1}))
+        );
+        assert_eq!(Store::from_json(nested.clone()).to_json(), Some(nested));
+
+        for not_sequential in [
+            json!({"0": 1, "2": 2}),
+            json!({"1": 1, "2": 2}),
+            json!({"0": 1, "01": 2}),
+            json!({"0": 1, "1": 2, "x": 3}),
+            json!({"-0": 1}),
+            json!({}),
+        ] {
+            let store = Store::from_json_with_options(not_sequential.clone(), options);
+            assert!(store.as_array().is_none(), "{not_sequential}");
+            assert_eq!(store.to_json(), Some(not_sequential));
+        }
+    }
+
+    #[test]
+    fn test_value_hash() {
+        let a = Store::from_json(json!({"x": {"a": 1, "b": [1, 2], "c": "d"}, "y": 1}));
+        let b = Store::from_json(json!({"x": {"c": "d", "b": [1, 2], "a": 1}, "y": 2}));
+
+        assert!(a.value_hash("x").is_some());
+        assert_eq!(a.value_hash("x"), b.value_hash("x"));
+        assert_ne!(a.value_hash("y"), b.value_hash("y"));
+        assert_ne!(a.value_hash("x"), a.value_hash("y"));
+        assert_eq!(a.value_hash("z"), None);
+
+        //equal integers which were created from different types
+        let mut a = Store::default();
+        a.insert("n".into(), Value::Integer(Integer::from(1_u8)));
+        a.insert(
+            "nested".into(),
+            Value::Array(vec![Value::Integer(Integer::from(300_u16))]),
+        );
+        let mut b = Store::default();
+        b.insert("n".into(), Value::Integer(Integer::from(1_i32)));
+        b.insert(
+            "nested".into(),
+            Value::Array(vec![Value::Integer(Integer::from(300_i64))]),
+        );
+        assert_eq!(a, b);
+        assert_eq!(a.value_hash("n"), b.value_hash("n"));
+        assert_eq!(a.value_hash("nested"), b.value_hash("nested"));
+        assert_ne!(
+            a.value_hash("n"),
+            Store::from_json(json!({"n": -1})).value_hash("n")
+        );
+    }
+
+    #[test]
+    fn test_into_sorted_vec() {
+        let keys = ["m", "c", "x", "a", "q", "b", "z", "k"];
+        let mut store = Store::default();
+        for (i, k) in keys.iter().enumerate() {
+            store.insert((*k).to_string(), Value::Integer(i.into()));
+        }
+
+        let sorted = store.into_sorted_vec();
+        let mut expected_keys = keys.to_vec();
+        expected_keys.sort_unstable();
+        assert_eq!(
+            sorted.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>(),
+            expected_keys
+        );
+        assert_eq!(sorted[0], ("a".to_string(), Value::Integer(3.into())));
+
+        let array = Store::from_json(json!([10, 20, 30])).into_sorted_vec();
+        assert_eq!(
+            array,
+            vec![
+                ("0".to_string(), Value::Integer(10.into())),
+                ("1".to_string(), Value::Integer(20.into())),
+                ("2".to_string(), Value::Integer(30.into())),
+            ]
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_decompress_round_trips (
+            data in prop_oneof![vec(any::<u8>(), 0..2_000), vec(0..4_u8, 0..20_000)],
+            trailing in vec(any::<u8>(), 0..16),
+            cut in any::<Index>(),
+        ) {
+            let mut lz4 = Integer::from(data.len()).ser().1;
+            lz4.extend(lz4_flex::compress(&data));
+            let miniz = miniz_oxide::deflate::compress_to_vec(&data, 10);
+
+            for (mut compressed, ty) in [(lz4, CompressionType::Lz4), (miniz, CompressionType::Miniz)] {
+                let len = compressed.len();
+                compressed.extend(&trailing);
+
+                let mut out = vec![];
+                prop_assert_eq!(
+                    Store::decompress_into(&compressed, ty, &mut out, usize::MAX).unwrap(),
+                    len
+                );
+                prop_assert_eq!(&out, &data);
+
+                let cut = cut.index(len);
+                prop_assert!(
+                    Store::decompress_into(&compressed[..cut], ty, &mut out, usize::MAX).is_err()
+                );
+            }
+        }
+
+        #[test]
+        fn test_decompress_garbage (
+            bytes in vec(any::<u8>(), 0..512),
+            lz4 in any::<bool>(),
+            max_len in 0..100_000_usize,
+        ) {
+            let ty = if lz4 { CompressionType::Lz4 } else { CompressionType::Miniz };
+            let mut out = vec![];
+            if let Ok(used) = Store::decompress_into(&bytes, ty, &mut out, max_len) {
+                prop_assert!(used <= bytes.len());
+                prop_assert!(out.len() <= max_len);
+            }
         }
     }
 }
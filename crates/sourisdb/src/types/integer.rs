@@ -8,7 +8,7 @@ use alloc::{
 use core::{
     fmt::{Debug, Display, Formatter},
     hash::{Hash, Hasher},
-    num::ParseIntError,
+    num::{IntErrorKind, ParseIntError},
     ops::{Add, Div, Mul, Sub},
     str::FromStr,
 };
@@ -48,6 +48,35 @@ impl TryFrom<u8> for SignedState {
     }
 }
 
+///The narrowest primitive integer width which can hold an [`Integer`] - see [`Integer::width`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+pub enum IntWidth {
+    ///Fits into a [`u8`] or an [`i8`].
+    W8,
+    ///Fits into a [`u16`] or an [`i16`].
+    W16,
+    ///Fits into a [`u32`] or an [`i32`].
+    W32,
+    ///Fits into a [`u64`] or an [`i64`].
+    W64,
+    ///Needs a [`u128`] or an [`i128`].
+    W128,
+}
+
+impl IntWidth {
+    ///Gets the number of bits in this width.
+    #[must_use]
+    pub const fn bits(self) -> u32 {
+        match self {
+            Self::W8 => 8,
+            Self::W16 => 16,
+            Self::W32 => 32,
+            Self::W64 => 64,
+            Self::W128 => 128,
+        }
+    }
+}
+
 ///The largest unsigned integer that can be stored using [`Integer`].
 pub type BiggestInt = u128;
 ///The largest signed integer that can be stored using [`Integer`].
@@ -75,9 +104,8 @@ pub struct Integer {
 
 impl PartialEq for Integer {
     fn eq(&self, other: &Self) -> bool {
-        if self.content[0..self.number_of_bytes_used]
-            != other.content[0..other.number_of_bytes_used]
-        {
+        //unused bytes are always filled to match the sign, so the whole content can be compared - `number_of_bytes_used` differs for eg. zeroes created in different ways
+        if self.content != other.content {
             return false;
         }
 
@@ -99,8 +127,7 @@ impl Hash for Integer {
             SignedState::SignedPositive
         };
         ss_to_be_hashed.hash(state);
-        self.content[0..self.number_of_bytes_used].hash(state);
-        self.number_of_bytes_used.hash(state);
+        self.content.hash(state);
     }
 }
 
@@ -117,6 +144,76 @@ impl Integer {
         self.signed_state != SignedState::SignedNegative
     }
 
+    ///Gets whether the number was created from a signed or unsigned integer, and its sign if it was signed.
+    #[must_use]
+    pub fn signed_state(&self) -> SignedState {
+        self.signed_state
+    }
+
+    ///Gets the narrowest width of primitive integer which can hold the number, using signed integers if [`Integer::signed_state`] is signed and unsigned ones otherwise - eg. `200` fits into a [`u8`] if it was created from an unsigned integer, but needs an [`i16`] if it was created from a signed one.
+    ///
+    /// This only depends on the value and the [`SignedState`], not on how many bytes are used to store it or how it was created.
+    #[must_use]
+    pub fn width(&self) -> IntWidth {
+        if self.signed_state == SignedState::Unsigned {
+            if u8::try_from(*self).is_ok() {
+                IntWidth::W8
+            } else if u16::try_from(*self).is_ok() {
+                IntWidth::W16
+            } else if u32::try_from(*self).is_ok() {
+                IntWidth::W32
+            } else if u64::try_from(*self).is_ok() {
+                IntWidth::W64
+            } else {
+                IntWidth::W128
+            }
+        } else if i8::try_from(*self).is_ok() {
+            IntWidth::W8
+        } else if i16::try_from(*self).is_ok() {
+            IntWidth::W16
+        } else if i32::try_from(*self).is_ok() {
+            IntWidth::W32
+        } else if i64::try_from(*self).is_ok() {
+            IntWidth::W64
+        } else {
+            IntWidth::W128
+        }
+    }
+
+    ///Adds two integers, returning [`None`] if the sum doesn't fit into a [`BiggestInt`] (or a [`BiggestIntButSigned`] if it is negative).
+    ///
+    /// Unlike the [`Add`] implementation, this doesn't need both integers to fit into the same primitive - eg. `u128::MAX` plus `-1` works, as it is worked out using the magnitude of the negative integer.
+    #[must_use]
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        match (self.is_negative(), rhs.is_negative()) {
+            (false, false) => BiggestInt::try_from(self)
+                .ok()?
+                .checked_add(BiggestInt::try_from(rhs).ok()?)
+                .map(Self::from),
+            (true, true) => BiggestIntButSigned::try_from(self)
+                .ok()?
+                .checked_add(BiggestIntButSigned::try_from(rhs).ok()?)
+                .map(Self::from),
+            (lhs_negative, _) => {
+                let (negative, positive) = if lhs_negative {
+                    (self, rhs)
+                } else {
+                    (rhs, self)
+                };
+                let negative = BiggestIntButSigned::try_from(negative).ok()?.unsigned_abs();
+                let positive = BiggestInt::try_from(positive).ok()?;
+
+                if positive >= negative {
+                    Some(Self::from(positive - negative))
+                } else {
+                    0_i128
+                        .checked_sub_unsigned(negative - positive)
+                        .map(Self::from)
+                }
+            }
+        }
+    }
+
     ///Converts the `Integer` to a [`serde_json::Value`].
     ///
     /// This can fail if the integer doesn't fit into i64 or u64 as those are the limits for [`Number`].
@@ -146,7 +243,21 @@ impl Integer {
 
 impl Display for Integer {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        //formatting 128-bit integers is noticeably slower than 64-bit ones, and most integers are small.
+        //signed integers need a spare byte so that the sign bit of an `i64` isn't clobbered.
         match self.signed_state {
+            SignedState::SignedPositive | SignedState::SignedNegative
+                if self.number_of_bytes_used < 8 =>
+            {
+                match i64::try_from(*self) {
+                    Ok(i) => write!(f, "{i}"),
+                    Err(e) => write!(f, "{e}"),
+                }
+            }
+            SignedState::Unsigned if self.number_of_bytes_used <= 8 => match u64::try_from(*self) {
+                Ok(i) => write!(f, "{i}"),
+                Err(e) => write!(f, "{e}"),
+            },
             SignedState::SignedPositive | SignedState::SignedNegative => {
                 match BiggestIntButSigned::try_from(*self) {
                     Ok(i) => write!(f, "{i}"),
@@ -213,10 +324,11 @@ macro_rules! from_signed {
                         }
                     }
 
+                    //-1 is all filled bytes, but still needs one byte so that it doesn't serialise as an empty multi-byte integer
                     Self {
                         signed_state: SignedState::SignedNegative,
                         content,
-                        number_of_bytes_used: last_non_filled_byte,
+                        number_of_bytes_used: last_non_filled_byte.max(1),
                     }
                 } else {
                     let mut content = [0; INTEGER_MAX_SIZE];
@@ -275,7 +387,13 @@ macro_rules! from_signed {
                     start
                 };
 
-                Ok(<$t>::from_le_bytes(out))
+                //eg. 250 fits into the byte of an i8, but would come out as -6
+                let out = <$t>::from_le_bytes(out);
+                if (out < 0) != (i.signed_state == SignedState::SignedNegative) {
+                    return Err(IntegerSerError::TooBigToFit);
+                }
+
+                Ok(out)
             }
         }
         )+
@@ -335,7 +453,23 @@ macro_rules! from_unsigned {
 new_x!(u8 => u8, i8 => i8, u16 => u16, i16 => i16, u32 => u32, i32 => i32, usize => usize, isize => isize, u64 => u64, i64 => i64, u128 => u128, i128 => i128);
 
 from_signed!(i8, i16, i32, i64, isize, i128);
-from_unsigned!(u8, u16, u32, u64, usize, u128);
+from_unsigned!(u8, u16, u32, u64, u128);
+
+impl From<usize> for Integer {
+    fn from(n: usize) -> Self {
+        Self::from(n as BiggestInt)
+    }
+}
+
+///Lengths are stored as [`Integer`]s, so a length written on a 64-bit target may not fit into a `usize` on a 32-bit target. These fail with [`IntegerSerError::OutOfRange`] rather than [`IntegerSerError::TooBigToFit`], so that it's clear that the data is fine but can't be used on this target.
+impl TryFrom<Integer> for usize {
+    type Error = IntegerSerError;
+
+    fn try_from(i: Integer) -> Result<Self, Self::Error> {
+        let n = BiggestInt::try_from(i)?;
+        Self::try_from(n).map_err(|_| IntegerSerError::OutOfRange(n))
+    }
+}
 
 impl From<Integer> for f64 {
     #[allow(clippy::cast_precision_loss)]
@@ -603,30 +737,53 @@ impl<'de> serde::Deserialize<'de> for Integer {
     }
 }
 
-impl FromStr for Integer {
-    type Err = IntegerSerError;
-
-    #[allow(clippy::cast_possible_truncation)]
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+impl Integer {
+    ///Parses an integer from a string in the given `radix`, with an optional leading `+` or `-`. Like the [`From`] implementations, this picks the smallest representation that fits.
+    ///
+    /// ## Errors
+    /// - [`IntegerSerError::InvalidRadix`] if `radix` isn't in `2..=36`.
+    /// - [`IntegerSerError::NotEnoughBytes`] if `s` is empty.
+    /// - [`IntegerSerError::TooBigToFit`] if the number is bigger than [`BiggestInt::MAX`] or smaller than [`BiggestIntButSigned::MIN`].
+    /// - [`IntegerSerError::IntegerParseError`] if `s` contains any invalid digits.
+    pub fn from_str_radix(s: &str, radix: u32) -> Result<Self, IntegerSerError> {
+        if !(2..=36).contains(&radix) {
+            return Err(IntegerSerError::InvalidRadix(radix));
+        }
         if s.is_empty() {
             return Err(IntegerSerError::NotEnoughBytes);
+        }
+
+        let map_parse_error = |e: ParseIntError| match e.kind() {
+            IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => IntegerSerError::TooBigToFit,
+            _ => IntegerSerError::IntegerParseError(e),
         };
 
-        if s == "0" {
+        let int = if s.as_bytes()[0] == b'-' {
+            let content = BiggestIntButSigned::from_str_radix(s, radix).map_err(map_parse_error)?;
+            Self::from(content)
+        } else {
+            let content = BiggestInt::from_str_radix(s, radix).map_err(map_parse_error)?;
+            Self::from(content)
+        };
+
+        //zero is always unsigned and takes no bytes, to match what is deserialised
+        if int.content == [0; INTEGER_MAX_SIZE] {
             return Ok(Self {
                 signed_state: SignedState::Unsigned,
                 content: [0; INTEGER_MAX_SIZE],
                 number_of_bytes_used: 0,
             });
         }
+        Ok(int)
+    }
+}
 
-        if s.as_bytes()[0] == b'-' {
-            let content: BiggestIntButSigned = s.parse()?;
-            Ok(Self::from(content))
-        } else {
-            let content: BiggestInt = s.parse()?;
-            Ok(Self::from(content))
-        }
+impl FromStr for Integer {
+    type Err = IntegerSerError;
+
+    ///Parses a base-10 integer - see [`Integer::from_str_radix`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_str_radix(s, 10)
     }
 }
 
@@ -644,8 +801,12 @@ pub enum IntegerSerError {
     SignError,
     ///Error parsing an integer from a string using the standard library.
     IntegerParseError(ParseIntError),
+    ///A radix outside of `2..=36` was used when parsing an integer from a string.
+    InvalidRadix(u32),
     ///Custom Serde error for use serialising and deserialising with `serde`.
     SerdeCustom(String),
+    ///An integer (usually a length) was larger than [`usize::MAX`] on this target.
+    OutOfRange(BiggestInt),
 }
 
 impl From<ParseIntError> for IntegerSerError {
@@ -666,9 +827,17 @@ impl Display for IntegerSerError {
             }
             IntegerSerError::SignError => write!(f, "Tried to fit integer into incorrect sign"),
             IntegerSerError::IntegerParseError(e) => {
-                write!(f, "Error parsing from base-10 string: {e}")
+                write!(f, "Error parsing from string: {e}")
+            }
+            IntegerSerError::InvalidRadix(r) => {
+                write!(f, "Radix must be between 2 and 36 inclusive, found {r}")
             }
             IntegerSerError::SerdeCustom(s) => write!(f, "Error in serde: {s}"),
+            IntegerSerError::OutOfRange(n) => write!(
+                f,
+                "{n} doesn't fit into a usize on this target, which has a maximum of {}",
+                usize::MAX
+            ),
         }
     }
 }
@@ -713,6 +882,34 @@ impl Integer {
         (self.signed_state, res)
     }
 
+    ///Serialises an integer using zigzag encoding (as used by protobuf), which interleaves positive and negative numbers (`0, -1, 1, -2, 2...`) so that the sign doesn't need to be stored separately. Small numbers of either sign then take up the fewest bytes. The result can be read back using [`Integer::deser_zigzag`].
+    ///
+    /// ## Errors
+    /// - [`IntegerSerError::TooBigToFit`] if the integer doesn't fit into a [`BiggestIntButSigned`].
+    #[allow(clippy::cast_sign_loss)]
+    pub fn ser_zigzag(self) -> Result<Vec<u8>, IntegerSerError> {
+        let n = if self.signed_state == SignedState::SignedNegative {
+            BiggestIntButSigned::try_from(self)?
+        } else {
+            BiggestIntButSigned::try_from(BiggestInt::try_from(self)?)
+                .map_err(|_| IntegerSerError::TooBigToFit)?
+        };
+
+        let zigzagged = ((n << 1) ^ (n >> (BiggestIntButSigned::BITS - 1))) as BiggestInt;
+        Ok(Self::from(zigzagged).ser().1)
+    }
+
+    ///Deserialises a zigzag-encoded integer from [`Integer::ser_zigzag`].
+    ///
+    /// ## Errors
+    /// - Can fail with [`IntegerSerError`] if there aren't enough bytes
+    #[allow(clippy::cast_possible_wrap)]
+    pub fn deser_zigzag(reader: &mut Cursor<u8>) -> Result<Self, IntegerSerError> {
+        let n = BiggestInt::try_from(Self::deser(SignedState::Unsigned, reader)?)?;
+        let n = ((n >> 1) as BiggestIntButSigned) ^ -((n & 1) as BiggestIntButSigned);
+        Ok(Self::from(n))
+    }
+
     ///Deserialise bytes inside a [`Cursor`] into an Integer.
     ///
     /// ## Errors
@@ -772,11 +969,206 @@ mod tests {
     use proptest::prelude::*;
 
     use crate::{
-        types::integer::{BiggestInt, BiggestIntButSigned, Integer},
+        types::integer::{
+            BiggestInt, BiggestIntButSigned, IntWidth, Integer, IntegerSerError, SignedState,
+        },
         utilities::cursor::Cursor,
     };
 
+    #[test]
+    fn test_checked_add() {
+        let add = |a: Integer, b: Integer| a.checked_add(b);
+
+        assert_eq!(add(40.into(), 2_u8.into()), Some(42.into()));
+        assert_eq!(add((-40).into(), (-2).into()), Some((-42).into()));
+        assert_eq!(add((-50).into(), 8.into()), Some((-42).into()));
+
+        //mixed signs where the positive side doesn't fit into an i128
+        assert_eq!(
+            add(u128::MAX.into(), (-1).into()),
+            Some((u128::MAX - 1).into())
+        );
+        assert_eq!(
+            add((-1).into(), u128::MAX.into()),
+            Some((u128::MAX - 1).into())
+        );
+        assert_eq!(
+            add(i128::MIN.into(), u128::MAX.into()),
+            Some((u128::MAX - i128::MIN.unsigned_abs()).into())
+        );
+        assert_eq!(add(i128::MIN.into(), 0_u8.into()), Some(i128::MIN.into()));
+
+        assert_eq!(add(u128::MAX.into(), 1.into()), None);
+        assert_eq!(add(i128::MIN.into(), (-1).into()), None);
+    }
+
+    #[test]
+    fn test_width_and_signed_state() {
+        for (i, width, signed_state) in [
+            (Integer::from(0_u8), IntWidth::W8, SignedState::Unsigned),
+            (Integer::from(200_u64), IntWidth::W8, SignedState::Unsigned),
+            (
+                Integer::from(200_i64),
+                IntWidth::W16,
+                SignedState::SignedPositive,
+            ),
+            (
+                Integer::from(-128_i32),
+                IntWidth::W8,
+                SignedState::SignedNegative,
+            ),
+            (
+                Integer::from(-129_i32),
+                IntWidth::W16,
+                SignedState::SignedNegative,
+            ),
+            (
+                Integer::from(70_000_u32),
+                IntWidth::W32,
+                SignedState::Unsigned,
+            ),
+            (
+                Integer::from(u64::MAX),
+                IntWidth::W64,
+                SignedState::Unsigned,
+            ),
+            (
+                Integer::from(i64::MIN),
+                IntWidth::W64,
+                SignedState::SignedNegative,
+            ),
+            (
+                Integer::from(BiggestInt::MAX),
+                IntWidth::W128,
+                SignedState::Unsigned,
+            ),
+            (
+                Integer::from(BiggestIntButSigned::MIN),
+                IntWidth::W128,
+                SignedState::SignedNegative,
+            ),
+        ] {
+            assert_eq!(i.width(), width, "{i}");
+            assert_eq!(i.signed_state(), signed_state, "{i}");
+        }
+
+        assert_eq!(IntWidth::W8.bits(), u8::BITS);
+        assert_eq!(IntWidth::W128.bits(), BiggestInt::BITS);
+    }
+
+    #[test]
+    fn test_zigzag_sizes() {
+        //zigzag interleaves signs, so 0, -1, 1, -2, 2 become 0, 1, 2, 3, 4
+        for (i, expected) in [(0_i64, 0_u8), (-1, 1), (1, 2), (-2, 3), (2, 4)] {
+            assert_eq!(Integer::from(i).ser_zigzag().unwrap(), [expected]);
+        }
+
+        //everything which zigzags to at most `ONE_BYTE_MAX_SIZE` only takes one byte
+        for i in -120_i64..=119 {
+            let sered = Integer::from(i).ser_zigzag().unwrap();
+            assert_eq!(sered.len(), 1, "{i} should fit in one byte");
+            assert_eq!(
+                Integer::deser_zigzag(&mut Cursor::new(&sered)).unwrap(),
+                Integer::from(i)
+            );
+        }
+        assert_eq!(Integer::from(120_i64).ser_zigzag().unwrap().len(), 2);
+        assert_eq!(Integer::from(-1_000_i64).ser_zigzag().unwrap().len(), 3);
+
+        //unsigned integers are zigzagged too, as long as they fit into a signed integer
+        assert_eq!(Integer::from(2_u8).ser_zigzag().unwrap(), [4]);
+        assert!(matches!(
+            Integer::from(BiggestInt::MAX).ser_zigzag(),
+            Err(IntegerSerError::TooBigToFit)
+        ));
+    }
+
+    #[test]
+    fn test_usize_out_of_range() {
+        assert_eq!(
+            usize::try_from(Integer::from(usize::MAX)).unwrap(),
+            usize::MAX
+        );
+
+        let too_big = usize::MAX as BiggestInt + 1;
+        let (signed_state, bytes) = Integer::from(too_big).ser();
+        let deserialised = Integer::deser(signed_state, &mut Cursor::new(&bytes)).unwrap();
+        assert!(matches!(
+            usize::try_from(deserialised),
+            Err(IntegerSerError::OutOfRange(n)) if n == too_big
+        ));
+
+        assert!(matches!(
+            usize::try_from(Integer::from(-1_i8)),
+            Err(IntegerSerError::SignError)
+        ));
+    }
+
+    //eg. a 5GiB length written on a 64-bit target
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_64_bit_length_on_32_bit_target() {
+        let length = 5 * 1024 * 1024 * 1024_u64;
+        assert!(matches!(
+            usize::try_from(Integer::from(length)),
+            Err(IntegerSerError::OutOfRange(n)) if n == BiggestInt::from(length)
+        ));
+    }
+
+    #[test]
+    fn test_zeroes_are_equal() {
+        let zeroes = [
+            Integer::from(0_u8),
+            Integer::from(0_i64),
+            Integer::from_str("0").unwrap(),
+            Integer::deser(SignedState::Unsigned, &mut Cursor::new(&[0])).unwrap(),
+        ];
+        for a in zeroes {
+            for b in zeroes {
+                assert_eq!(a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_signed_conversion_checks_sign() {
+        assert_eq!(i8::try_from(Integer::from(127_u8)).unwrap(), 127);
+        assert!(matches!(
+            i8::try_from(Integer::from(250_u8)),
+            Err(IntegerSerError::TooBigToFit)
+        ));
+        assert!(matches!(
+            i8::try_from(Integer::from(250_i64)),
+            Err(IntegerSerError::TooBigToFit)
+        ));
+        assert!(matches!(
+            i64::try_from(Integer::from(u64::MAX)),
+            Err(IntegerSerError::TooBigToFit)
+        ));
+        assert_eq!(i8::try_from(Integer::from(-128_i64)).unwrap(), -128);
+        assert!(matches!(
+            i8::try_from(Integer::from(-129_i64)),
+            Err(IntegerSerError::TooBigToFit)
+        ));
+    }
+
     proptest! {
+        #[test]
+        fn display_matches_primitive_signed (n in any::<i128>()) {
+            prop_assert_eq!(Integer::from(n).to_string(), n.to_string());
+        }
+
+        #[test]
+        fn display_matches_primitive_unsigned (n in any::<u128>()) {
+            prop_assert_eq!(Integer::from(n).to_string(), n.to_string());
+        }
+
+        #[test]
+        fn display_matches_primitive_small (n in any::<i64>(), u in any::<u64>()) {
+            prop_assert_eq!(Integer::from(n).to_string(), n.to_string());
+            prop_assert_eq!(Integer::from(u).to_string(), u.to_string());
+        }
+
         #[test]
         fn doesnt_crash (s in "\\PC*") {
             let _ = Integer::from_str(&s);
@@ -806,6 +1198,28 @@ mod tests {
             prop_assert_eq!(BiggestIntButSigned::try_from(got_back).expect("unable to get BIBS from integer"), i);
         }
 
+        #[test]
+        fn zigzag_round_trip (i in any::<BiggestIntButSigned>()) {
+            let int = Integer::from(i);
+            let sered = int.ser_zigzag().expect("unable to zigzag integer");
+
+            let mut cursor = Cursor::new(&sered);
+            let got_back = Integer::deser_zigzag(&mut cursor).expect("unable to parse zigzagged integer");
+            prop_assert!(cursor.is_finished());
+            prop_assert_eq!(int, got_back);
+            prop_assert_eq!(BiggestIntButSigned::try_from(got_back).expect("unable to get BIBS from integer"), i);
+        }
+
+        #[test]
+        fn back_to_original_small_negative (i in -300_i64..0) {
+            let int = Integer::from(i);
+            let (s, sered) = int.ser();
+            let got_back = Integer::deser(s, &mut Cursor::new(&sered)).expect("unable to parse integer from bytes");
+            prop_assert_eq!(int, got_back);
+
+            prop_assert_eq!(i64::try_from(got_back).expect("unable to get i64 from integer"), i);
+        }
+
         #[test]
         fn back_to_original_other_size (i in any::<u8>()) {
             let s = i.to_string();
@@ -864,4 +1278,65 @@ mod tests {
             prop_assert_eq!(i, from_serde);
         }
     }
+
+    #[test]
+    fn test_from_str_radix() {
+        assert_eq!(
+            Integer::from_str_radix("123", 10).unwrap(),
+            Integer::from(123_u8)
+        );
+        assert_eq!(
+            Integer::from_str_radix("+123", 10).unwrap(),
+            Integer::from(123_u8)
+        );
+        assert_eq!(
+            Integer::from_str_radix("-123", 10).unwrap(),
+            Integer::from(-123_i8)
+        );
+        assert_eq!(
+            Integer::from_str_radix("ff", 16).unwrap(),
+            Integer::from(255_u8)
+        );
+        assert_eq!(
+            Integer::from_str_radix("-FF", 16).unwrap(),
+            Integer::from(-255_i16)
+        );
+        assert_eq!(
+            Integer::from_str_radix("-0", 10).unwrap(),
+            Integer::from_str_radix("0", 2).unwrap()
+        );
+        assert_eq!(
+            Integer::from_str(&BiggestIntButSigned::MIN.to_string()).unwrap(),
+            Integer::from(BiggestIntButSigned::MIN)
+        );
+        assert_eq!(
+            Integer::from_str(&BiggestInt::MAX.to_string()).unwrap(),
+            Integer::from(BiggestInt::MAX)
+        );
+
+        assert!(matches!(
+            Integer::from_str(&format!("{}0", BiggestInt::MAX)),
+            Err(IntegerSerError::TooBigToFit)
+        ));
+        assert!(matches!(
+            Integer::from_str(&format!("{}0", BiggestIntButSigned::MIN)),
+            Err(IntegerSerError::TooBigToFit)
+        ));
+        assert!(matches!(
+            Integer::from_str_radix("12g", 16),
+            Err(IntegerSerError::IntegerParseError(_))
+        ));
+        assert!(matches!(
+            Integer::from_str_radix("-+1", 10),
+            Err(IntegerSerError::IntegerParseError(_))
+        ));
+        assert!(matches!(
+            Integer::from_str_radix("1", 37),
+            Err(IntegerSerError::InvalidRadix(37))
+        ));
+        assert!(matches!(
+            Integer::from_str(""),
+            Err(IntegerSerError::NotEnoughBytes)
+        ));
+    }
 }
@@ -13,9 +13,13 @@
 //!
 //! ## `huffman`
 //! [`huffman::Huffman`] is a huffman coder.
+//!
+//! ## `serialise`
+//! [`serialise::Serialise`] is a trait implemented by both [`crate::values::Value`] and [`crate::store::Store`], so generic code can serialise either.
 
 pub mod bits;
 pub mod cursor;
 pub mod huffman;
+pub mod serialise;
 #[cfg(feature = "std")]
 pub mod value_utils;
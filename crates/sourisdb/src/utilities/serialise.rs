@@ -0,0 +1,160 @@
+//! `serialise` provides the [`Serialise`] trait, which allows generic code to serialise and deserialise both [`Value`]s and [`Store`]s.
+//!
+//! ```rust
+//! use sourisdb::{
+//!     store::Store,
+//!     utilities::{cursor::Cursor, serialise::Serialise},
+//!     values::Value,
+//! };
+//!
+//! fn round_trip<T: Serialise>(t: &T) -> Result<T, T::Error> {
+//!     let bytes = t.serialise()?;
+//!     T::deserialise(&mut Cursor::new(&bytes))
+//! }
+//!
+//! let value = Value::Boolean(true);
+//! assert_eq!(round_trip(&value).unwrap(), value);
+//!
+//! let store = Store::from_range(0..5);
+//! assert_eq!(round_trip(&store).unwrap(), store);
+//! ```
+
+use alloc::vec::Vec;
+
+use crate::{
+    store::{Store, StoreSerError},
+    utilities::cursor::Cursor,
+    values::{Value, ValueSerError},
+};
+
+///A type which can be serialised to and deserialised from bytes.
+pub trait Serialise: Sized {
+    ///The error returned when serialising or deserialising fails.
+    type Error;
+
+    ///Serialises `self`, appending the bytes to `out`.
+    ///
+    /// ## Errors
+    /// Any error from serialising - if this fails, `out` may have had some bytes appended.
+    fn serialise_into(&self, out: &mut Vec<u8>) -> Result<(), Self::Error>;
+
+    ///Deserialises a value from `bytes`, moving the cursor past the bytes used.
+    ///
+    /// ## Errors
+    /// Any error from deserialising.
+    fn deserialise(bytes: &mut Cursor<u8>) -> Result<Self, Self::Error>;
+
+    ///Serialises `self` into a new [`Vec`].
+    ///
+    /// ## Errors
+    /// Any error from [`Serialise::serialise_into`].
+    fn serialise(&self) -> Result<Vec<u8>, Self::Error> {
+        let mut out = Vec::new();
+        self.serialise_into(&mut out)?;
+        Ok(out)
+    }
+}
+
+///Serialises values without a huffman tree, using [`Value::ser`] and [`Value::deser`].
+impl Serialise for Value {
+    type Error = ValueSerError;
+
+    fn serialise_into(&self, out: &mut Vec<u8>) -> Result<(), Self::Error> {
+        out.extend(self.ser(None)?);
+        Ok(())
+    }
+
+    fn deserialise(bytes: &mut Cursor<u8>) -> Result<Self, Self::Error> {
+        Self::deser(bytes, None)
+    }
+}
+
+///Serialises stores using [`Store::ser`] and [`Store::deser`]. Stores don't record their own length, but work out where they end as they are read (see [`Store::deser_all`]), so deserialising only moves the cursor past the store itself.
+impl Serialise for Store {
+    type Error = StoreSerError;
+
+    fn serialise_into(&self, out: &mut Vec<u8>) -> Result<(), Self::Error> {
+        out.extend(self.ser()?);
+        Ok(())
+    }
+
+    fn deserialise(bytes: &mut Cursor<u8>) -> Result<Self, Self::Error> {
+        let (store, used) = Self::deser_framed(bytes.peek_remaining())?;
+        bytes.move_forwards(used);
+        Ok(store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use core::fmt::Debug;
+
+    use serde_json::json;
+
+    use super::Serialise;
+    use crate::{store::Store, utilities::cursor::Cursor, values::Value};
+
+    fn round_trip<T: Serialise + PartialEq + Debug>(t: &T)
+    where
+        T::Error: Debug,
+    {
+        let mut bytes = vec![0xAB];
+        t.serialise_into(&mut bytes).unwrap();
+        assert_eq!(&bytes[1..], t.serialise().unwrap().as_slice());
+
+        let mut cursor = Cursor::new(&bytes);
+        cursor.move_forwards(1);
+        assert_eq!(&T::deserialise(&mut cursor).unwrap(), t);
+        assert!(cursor.is_finished());
+    }
+
+    #[test]
+    fn test_generic_round_trip() {
+        round_trip(&Value::Boolean(true));
+        round_trip(&Value::String("hello".into()));
+        round_trip(&Value::Array(vec![Value::from(1), Value::Null(())]));
+
+        round_trip(&Store::default());
+        round_trip(&Store::from_json(json!({"a": 1, "b": [true, "c"]})));
+    }
+
+    #[test]
+    fn test_values_are_framed() {
+        let values = [Value::from(1), Value::String("a".into()), Value::Null(())];
+
+        let mut bytes = vec![];
+        for v in &values {
+            v.serialise_into(&mut bytes).unwrap();
+        }
+
+        let mut cursor = Cursor::new(&bytes);
+        for v in &values {
+            assert_eq!(&Value::deserialise(&mut cursor).unwrap(), v);
+        }
+        assert!(cursor.is_finished());
+    }
+
+    #[test]
+    fn test_stores_are_framed() {
+        //one uncompressed and one compressed, as they find their ends differently
+        let stores = [
+            Store::from_json(json!({"a": 1, "b": "c"})),
+            Store::from_range(0..1_000),
+            Store::default(),
+        ];
+
+        let mut bytes = vec![];
+        for s in &stores {
+            s.serialise_into(&mut bytes).unwrap();
+        }
+        Value::from(5).serialise_into(&mut bytes).unwrap();
+
+        let mut cursor = Cursor::new(&bytes);
+        for s in &stores {
+            assert_eq!(&Store::deserialise(&mut cursor).unwrap(), s);
+        }
+        assert_eq!(Value::deserialise(&mut cursor).unwrap(), Value::from(5));
+        assert!(cursor.is_finished());
+    }
+}
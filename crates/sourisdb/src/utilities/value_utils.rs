@@ -42,6 +42,7 @@ pub fn get_value_from_stdin(
         ValueTy::Integer,
         ValueTy::Imaginary,
         ValueTy::Timestamp,
+        ValueTy::Duration,
         ValueTy::JSON,
         ValueTy::Null,
         ValueTy::DoubleFloat,
@@ -161,6 +162,12 @@ pub fn get_value_from_stdin(
 
             Value::Timestamp(ts)
         }
+        ValueTy::Duration => {
+            let nanos = Input::with_theme(theme)
+                .with_prompt("Nanoseconds (may be negative): ")
+                .interact()?;
+            Value::duration_from_nanos(nanos)
+        }
         ValueTy::JSON => {
             let v: SJValue = Input::with_theme(theme).with_prompt("JSON: ").interact()?;
             Value::JSON(v)
@@ -1,4 +1,5 @@
 use alloc::{
+    format,
     string::{FromUtf8Error, String, ToString},
     vec,
     vec::Vec,
@@ -8,17 +9,19 @@ use core::{
     hash::{Hash, Hasher},
     net::{Ipv4Addr, Ipv6Addr},
     num::FpCategory,
+    ops::RangeInclusive,
     str::FromStr,
 };
 
 use cfg_if::cfg_if;
-use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Timelike};
 use chrono_tz::Tz;
 use hashbrown::HashMap;
 use serde_json::{Error as SJError, Map as SJMap, Number, Value as SJValue};
 
 use crate::{
     display_bytes_as_hex_array,
+    store::Store,
     types::{
         imaginary::Imaginary,
         integer::{Integer, IntegerSerError, SignedState},
@@ -26,8 +29,12 @@ use crate::{
     utilities::{bits::Bits, cursor::Cursor, huffman::Huffman},
 };
 
+///The maximum nesting depth used by [`Value::deser`] and [`crate::store::Store::deser`].
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
 #[derive(Clone)]
 pub enum Value {
+    ///A single Unicode scalar value - **not** a grapheme cluster, so something which looks like one character (eg. `é` written as `e` followed by a combining accent, or most emoji) may need several. Use [`Value::String`] to keep text whole, or [`Value::chars_from_str`] to split it into scalars.
     Character(char),
     String(String),
     Binary(Vec<u8>),
@@ -35,6 +42,7 @@ pub enum Value {
     Integer(Integer),
     Imaginary(Imaginary),
     Timestamp(NaiveDateTime),
+    Duration(TimeDelta),
     JSON(SJValue),
     Null(()),
     SingleFloat(f32),
@@ -101,7 +109,7 @@ macro_rules! as_ty {
     };
 }
 
-as_ty!(Character char -> char, String str -> String, Boolean bool -> bool, Integer int -> Integer, Imaginary imaginary -> Imaginary, Timestamp timestamp -> NaiveDateTime, JSON json -> SJValue, Null null -> (), DoubleFloat double_float -> f64, SingleFloat single_float -> f32, Array array -> Vec<Value>, Map map -> HashMap<String, Value>, Timezone tz -> Tz, Ipv4Addr ipv4 -> Ipv4Addr, Ipv6Addr ipv6 -> Ipv6Addr, Binary binary -> Vec<u8>);
+as_ty!(Character char -> char, String str -> String, Boolean bool -> bool, Integer int -> Integer, Imaginary imaginary -> Imaginary, Timestamp timestamp -> NaiveDateTime, Duration duration -> TimeDelta, JSON json -> SJValue, Null null -> (), DoubleFloat double_float -> f64, SingleFloat single_float -> f32, Array array -> Vec<Value>, Map map -> HashMap<String, Value>, Timezone tz -> Tz, Ipv4Addr ipv4 -> Ipv4Addr, Ipv6Addr ipv6 -> Ipv6Addr, Binary binary -> Vec<u8>);
 
 macro_rules! from_integer {
     ($($t:ty),+) => {
@@ -139,6 +147,7 @@ impl PartialEq for Value {
             (Self::Integer(i), Self::Integer(i2)) => i.eq(i2),
             (Self::Imaginary(i), Self::Imaginary(i2)) => i.eq(i2),
             (Self::Timestamp(t), Self::Timestamp(t2)) => t.eq(t2),
+            (Self::Duration(d), Self::Duration(d2)) => d.eq(d2),
             (Self::JSON(j), Self::JSON(j2)) => j.eq(j2),
             (Self::Null(()), Self::Null(())) => true,
             (Self::DoubleFloat(f), Self::DoubleFloat(f2)) => f.eq(f2),
@@ -180,18 +189,26 @@ impl Hash for Value {
             Value::Timestamp(v) => {
                 v.hash(state);
             }
+            Value::Duration(d) => {
+                d.hash(state);
+            }
             Value::JSON(j) => {
                 j.to_string().hash(state);
             }
             Value::Map(m) => {
-                for k in m.keys() {
+                //length-prefixed like the array below
+                m.len().hash(state);
+                //sorted so that equal maps always hash the same, regardless of iteration order
+                let mut keys: Vec<&String> = m.keys().collect();
+                keys.sort_unstable();
+                for k in keys {
                     k.hash(state);
-                }
-                for v in m.values() {
-                    v.hash(state);
+                    m[k].hash(state);
                 }
             }
             Value::Array(a) => {
+                //length-prefixed, so that elements can't move between nested arrays without changing the hash - eg. `[[1], 2]` and `[[1, 2]]`
+                a.len().hash(state);
                 for v in a {
                     v.hash(state);
                 }
@@ -235,22 +252,36 @@ impl Hash for Value {
 #[allow(clippy::missing_fields_in_debug)]
 impl Debug for Value {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        //only this many bytes of binary are shown, so large values don't flood test output
+        const BINARY_PREVIEW_LEN: usize = 32;
+
         let mut s = f.debug_struct("Value");
         s.field("ty", &self.as_ty());
 
         match &self {
             Self::Character(ch) => s.field("content", ch),
-            Self::String(str) => s.field("content", str),
-            Self::Binary(b) => s.field("content", &display_bytes_as_hex_array(b)),
+            Self::String(str) => s
+                .field("len", &str.len())
+                .field("chars", &str.chars().count())
+                .field("content", str),
+            Self::Binary(b) => {
+                let mut preview = display_bytes_as_hex_array(&b[..b.len().min(BINARY_PREVIEW_LEN)]);
+                if b.len() > BINARY_PREVIEW_LEN {
+                    preview.pop();
+                    preview.push_str(", ...]");
+                }
+                s.field("len", &b.len()).field("content", &preview)
+            }
             Self::Boolean(b) => s.field("content", b),
             Self::Integer(i) => s.field("content", i),
             Self::Imaginary(i) => s.field("content", i),
             Self::Timestamp(ndt) => s.field("content", ndt),
+            Self::Duration(d) => s.field("content", d),
             Self::JSON(v) => s.field("content", v),
             Self::DoubleFloat(f) => s.field("content", f),
             Self::Null(o) => s.field("content", o),
-            Self::Array(a) => s.field("content", a),
-            Self::Map(m) => s.field("content", m),
+            Self::Array(a) => s.field("len", &a.len()).field("content", a),
+            Self::Map(m) => s.field("len", &m.len()).field("content", m),
             Self::Ipv4Addr(m) => s.field("content", m),
             Self::Ipv6Addr(m) => s.field("content", m),
             Self::SingleFloat(m) => s.field("content", m),
@@ -261,8 +292,71 @@ impl Debug for Value {
     }
 }
 
+///How [`Value::DoubleFloat`]s and [`Value::SingleFloat`]s are written out by [`Value::display_with`] and [`Value::convert_to_json_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FloatFormat {
+    ///The shortest decimal which reads back in as exactly the same float, eg. `0.1`. This is what [`Display`] and [`Value::convert_to_json`] use.
+    #[default]
+    Shortest,
+    ///Like [`FloatFormat::Shortest`], but in scientific notation, eg. `1e-1`.
+    ///
+    /// JSON numbers are written out by `serde_json`, which picks its own notation, so for JSON this is the same as [`FloatFormat::Shortest`].
+    Scientific,
+    ///Rounded to this many digits after the decimal point, eg. `0.100` for 3 digits. This can lose precision, so the float read back in may differ.
+    Fixed(usize),
+}
+
+impl FloatFormat {
+    ///Writes out `f` using this format.
+    #[must_use]
+    pub fn format_f64(self, f: f64) -> String {
+        match self {
+            Self::Shortest => format!("{f}"),
+            Self::Scientific => format!("{f:e}"),
+            Self::Fixed(precision) => format!("{f:.precision$}"),
+        }
+    }
+
+    ///Writes out `f` using this format - as an [`f32`], so eg. `0.1_f32` is written as `0.1` rather than the digits of the closest [`f64`].
+    #[must_use]
+    pub fn format_f32(self, f: f32) -> String {
+        match self {
+            Self::Shortest => format!("{f}"),
+            Self::Scientific => format!("{f:e}"),
+            Self::Fixed(precision) => format!("{f:.precision$}"),
+        }
+    }
+
+    ///Gets the JSON number which reads back in as the number written out, or [`None`] for NaN and infinities.
+    fn to_json_number(formatted: &str) -> Option<Number> {
+        Number::from_f64(formatted.parse().ok()?)
+    }
+}
+
+///Shows a [`Value`] with floats written out using a [`FloatFormat`] - see [`Value::display_with`].
+#[derive(Debug, Clone, Copy)]
+pub struct DisplayWith<'a>(&'a Value, FloatFormat);
+
+impl Display for DisplayWith<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        self.0.fmt_with(f, self.1)
+    }
+}
+
 impl Display for Value {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        self.fmt_with(f, FloatFormat::default())
+    }
+}
+
+impl Value {
+    ///Shows the value like its [`Display`] implementation, but with floats (including any nested in maps and arrays) written out using `float_format`.
+    #[must_use]
+    pub fn display_with(&self, float_format: FloatFormat) -> DisplayWith<'_> {
+        DisplayWith(self, float_format)
+    }
+
+    fn fmt_with(&self, f: &mut Formatter<'_>, float_format: FloatFormat) -> core::fmt::Result {
         match &self {
             Self::Character(ch) => write!(f, "{ch:?}"),
             Self::String(str) => write!(f, "{str:?}"),
@@ -273,8 +367,9 @@ impl Display for Value {
             Self::Integer(i) => write!(f, "{i}"),
             Self::Imaginary(i) => write!(f, "{i}"),
             Self::Timestamp(ndt) => write!(f, "{ndt}"),
+            Self::Duration(d) => Self::fmt_duration(*d, f),
             Self::JSON(v) => write!(f, "{v}"),
-            Self::DoubleFloat(fl) => write!(f, "{fl}"),
+            Self::DoubleFloat(fl) => write!(f, "{}", float_format.format_f64(*fl)),
             Self::Null(_o) => write!(f, "null"),
             Self::Map(m) => {
                 cfg_if! {
@@ -289,7 +384,7 @@ impl Display for Value {
                             .set_content_arrangement(comfy_table::ContentArrangement::Dynamic);
 
                         for (k, v) in m {
-                            table.add_row(vec![format!("{k}"), format!("{v}")]);
+                            table.add_row(vec![format!("{k}"), format!("{}", v.display_with(float_format))]);
                         }
                         write!(f, "\n{table}")
                     } else {
@@ -300,9 +395,9 @@ impl Display for Value {
                             if first {
                                 first = false;
 
-                                write!(f, "{k}: {v}")?;
+                                write!(f, "{k}: {}", v.display_with(float_format))?;
                             } else {
-                                write!(f, ", {k}: {v}")?;
+                                write!(f, ", {k}: {}", v.display_with(float_format))?;
                             }
                         }
                         write!(f, "}}")
@@ -315,9 +410,9 @@ impl Display for Value {
                 for v in a {
                     if first {
                         first = false;
-                        write!(f, "{v}")?;
+                        write!(f, "{}", v.display_with(float_format))?;
                     } else {
-                        write!(f, ", {v}")?;
+                        write!(f, ", {}", v.display_with(float_format))?;
                     }
                 }
                 write!(f, "]")
@@ -325,11 +420,220 @@ impl Display for Value {
             Self::Timezone(v) => write!(f, "{v}"),
             Self::Ipv4Addr(v) => write!(f, "{v}"),
             Self::Ipv6Addr(v) => write!(f, "{v}"),
-            Self::SingleFloat(v) => write!(f, "{v}"),
+            Self::SingleFloat(v) => write!(f, "{}", float_format.format_f32(*v)),
+        }
+    }
+}
+
+impl Value {
+    ///Set in the type byte of a [`Value::Duration`] to tell it apart from a [`Value::Timestamp`], as the two share a discriminant.
+    pub const DURATION_FLAG: u8 = 0b0000_1000;
+
+    ///Creates a [`Value::Duration`] from a number of nanoseconds, which may be negative.
+    #[must_use]
+    pub const fn duration_from_nanos(nanos: i64) -> Self {
+        Self::Duration(TimeDelta::nanoseconds(nanos))
+    }
+
+    ///Writes a duration out like `1h30m`, `-2d0.5s` or `0s`, skipping any units which are zero. Seconds have a fractional part if needed.
+    fn fmt_duration(d: TimeDelta, f: &mut Formatter<'_>) -> core::fmt::Result {
+        const NANOS_PER_SEC: u128 = 1_000_000_000;
+        const UNITS: [(&str, u128); 3] = [("d", 86_400), ("h", 3_600), ("m", 60)];
+
+        let total = Self::duration_to_nanos(d);
+        if total == 0 {
+            return write!(f, "0s");
+        }
+        if total < 0 {
+            write!(f, "-")?;
+        }
+
+        let total = total.unsigned_abs();
+        let mut secs = total / NANOS_PER_SEC;
+        let nanos = total % NANOS_PER_SEC;
+
+        for (unit, size) in UNITS {
+            if secs >= size {
+                write!(f, "{}{unit}", secs / size)?;
+                secs %= size;
+            }
+        }
+
+        match (secs, nanos) {
+            (0, 0) => Ok(()),
+            (secs, 0) => write!(f, "{secs}s"),
+            (secs, mut nanos) => {
+                let mut width = 9;
+                while nanos % 10 == 0 {
+                    nanos /= 10;
+                    width -= 1;
+                }
+                write!(f, "{secs}.{nanos:0width$}s")
+            }
+        }
+    }
+
+    ///Gets the total number of nanoseconds in `d` - this needs an `i128`, as a [`TimeDelta`] can hold more than [`i64::MAX`] nanoseconds.
+    fn duration_to_nanos(d: TimeDelta) -> i128 {
+        i128::from(d.num_seconds()) * 1_000_000_000 + i128::from(d.subsec_nanos())
+    }
+
+    ///The inverse of [`Value::duration_to_nanos`], returning [`None`] if `nanos` is out of the range of a [`TimeDelta`].
+    fn duration_from_total_nanos(nanos: i128) -> Option<TimeDelta> {
+        let secs = i64::try_from(nanos.div_euclid(1_000_000_000)).ok()?;
+        let nanos = u32::try_from(nanos.rem_euclid(1_000_000_000)).ok()?;
+        TimeDelta::new(secs, nanos)
+    }
+}
+
+impl Value {
+    ///Splits `s` into a store holding an array (see [`Store::as_array`]) of one [`Value::Character`] per Unicode scalar value, for character-level processing.
+    ///
+    /// This splits by [`str::chars`], not by grapheme cluster - so combining characters (eg. accents written separately) and multi-scalar emoji are split into several [`Value::Character`]s, and joining them back together in order gives back the original string.
+    #[must_use]
+    pub fn chars_from_str(s: &str) -> Store {
+        Store::from_array(s.chars().map(Value::Character).collect())
+    }
+
+    ///Checks whether a [`Value::Character`] is alphanumeric, using [`char::is_alphanumeric`]. Returns [`None`] for any other kind of value.
+    #[must_use]
+    pub fn is_alphanumeric(&self) -> Option<bool> {
+        self.as_char().map(|ch| ch.is_alphanumeric())
+    }
+
+    ///Checks whether a [`Value::Character`] is whitespace, using [`char::is_whitespace`]. Returns [`None`] for any other kind of value.
+    #[must_use]
+    pub fn is_whitespace(&self) -> Option<bool> {
+        self.as_char().map(|ch| ch.is_whitespace())
+    }
+
+    ///Converts a [`Value::Character`] to uppercase. Returns [`None`] for any other kind of value.
+    ///
+    /// Some characters become more than one character when uppercased (eg. `'ß'` becomes `"SS"`), and these are returned as a [`Value::String`] - everything else stays as a [`Value::Character`].
+    #[must_use]
+    pub fn to_uppercase(&self) -> Option<Value> {
+        self.as_char()
+            .map(|ch| Self::from_case_mapping(ch.to_uppercase()))
+    }
+
+    ///Converts a [`Value::Character`] to lowercase. Returns [`None`] for any other kind of value.
+    ///
+    /// Like [`Value::to_uppercase`], characters which become more than one character are returned as a [`Value::String`].
+    #[must_use]
+    pub fn to_lowercase(&self) -> Option<Value> {
+        self.as_char()
+            .map(|ch| Self::from_case_mapping(ch.to_lowercase()))
+    }
+
+    fn from_case_mapping(mut chars: impl ExactSizeIterator<Item = char>) -> Value {
+        if chars.len() == 1 {
+            if let Some(ch) = chars.next() {
+                return Value::Character(ch);
+            }
+        }
+        Value::String(chars.collect())
+    }
+
+    ///Gets the number of bytes this value takes up when serialised with [`Value::ser`] without huffman encoding.
+    ///
+    /// ## Errors
+    /// Any error from [`Value::ser`].
+    pub fn serialised_size(&self) -> Result<usize, ValueSerError> {
+        Ok(self.ser(None)?.len())
+    }
+
+    ///Buckets this value by its [`Value::serialised_size`] - see [`SizeClass`] for the boundaries. Useful for spotting heavy fields or deciding how to store or compress values.
+    ///
+    /// ## Errors
+    /// Any error from [`Value::ser`].
+    pub fn size_class(&self) -> Result<SizeClass, ValueSerError> {
+        Ok(SizeClass::from_size(self.serialised_size()?))
+    }
+
+    ///Combines two values of the same type into one, eg. for accumulating counts from several places without needing to pick a winner:
+    /// - two [`Value::Integer`]s are added together.
+    /// - two [`Value::String`]s are concatenated, with `self` first.
+    /// - two [`Value::Array`]s are concatenated, with `self` first.
+    /// - two [`Value::Map`]s are deep-merged - keys which are only in one map are kept as they are, and the values of keys which are in both are merged using this method.
+    ///
+    /// ## Errors
+    /// - [`ValueSerError::CannotMerge`] with the types of `self` and `other` for any other pair of types, including values of the same type which can't be combined (eg. two [`Value::Boolean`]s). For maps, this could be from a nested value.
+    /// - [`ValueSerError::IntegerSerError`] if adding two integers overflows.
+    pub fn try_merge(self, other: Value) -> Result<Value, ValueSerError> {
+        match (self, other) {
+            (Value::Integer(a), Value::Integer(b)) => a
+                .checked_add(b)
+                .map(Value::Integer)
+                .ok_or(ValueSerError::IntegerSerError(IntegerSerError::TooBigToFit)),
+            (Value::String(mut a), Value::String(b)) => {
+                a.push_str(&b);
+                Ok(Value::String(a))
+            }
+            (Value::Array(mut a), Value::Array(b)) => {
+                a.extend(b);
+                Ok(Value::Array(a))
+            }
+            (Value::Map(mut a), Value::Map(b)) => {
+                for (k, theirs) in b {
+                    let merged = match a.remove(&k) {
+                        Some(ours) => ours.try_merge(theirs)?,
+                        None => theirs,
+                    };
+                    a.insert(k, merged);
+                }
+                Ok(Value::Map(a))
+            }
+            (a, b) => Err(ValueSerError::CannotMerge(a.as_ty(), b.as_ty())),
         }
     }
 }
 
+///A rough bucket for how large a serialised value is - see [`Value::size_class`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub enum SizeClass {
+    ///At most [`SizeClass::TINY_MAX`] bytes - eg. booleans, small numbers and single characters.
+    Tiny,
+    ///At most [`SizeClass::SMALL_MAX`] bytes - eg. short strings.
+    Small,
+    ///At most [`SizeClass::MEDIUM_MAX`] bytes.
+    Medium,
+    ///Anything larger than [`SizeClass::MEDIUM_MAX`] bytes.
+    Large,
+}
+
+impl SizeClass {
+    ///The largest size in bytes of a [`SizeClass::Tiny`] value.
+    pub const TINY_MAX: usize = 8;
+    ///The largest size in bytes of a [`SizeClass::Small`] value.
+    pub const SMALL_MAX: usize = 256;
+    ///The largest size in bytes of a [`SizeClass::Medium`] value.
+    pub const MEDIUM_MAX: usize = 64 * 1024;
+
+    ///Gets the class for a value which serialises to `bytes` bytes.
+    #[must_use]
+    pub fn from_size(bytes: usize) -> Self {
+        if bytes <= Self::TINY_MAX {
+            Self::Tiny
+        } else if bytes <= Self::SMALL_MAX {
+            Self::Small
+        } else if bytes <= Self::MEDIUM_MAX {
+            Self::Medium
+        } else {
+            Self::Large
+        }
+    }
+}
+
+impl From<TimeDelta> for Value {
+    fn from(d: TimeDelta) -> Self {
+        Self::Duration(d)
+    }
+}
+
+///The type of a [`Value`], which is stored in the top 4 bits of the type byte at the start of every serialised value. The bottom 4 bits hold extra information depending on the type - see [`ValueTy::niche_value`].
+///
+/// ## Compatibility
+/// Small [`Value::Integer`]s are niched into the bottom 4 bits of the type byte as `3..=15`, which is a breaking change to the serialised format. Readers from before integers were niched read those bits as the [`SignedState`] of a full integer (only looking at the bottom 2 bits) and then read the following bytes as that integer, so they misread most niched integers without any error - data with niched integers must only be read by readers which understand them.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum ValueTy {
     Character,
@@ -339,6 +643,8 @@ pub enum ValueTy {
     Integer,
     Imaginary,
     Timestamp,
+    ///Shares its discriminant with [`ValueTy::Timestamp`] - durations are told apart by [`Value::DURATION_FLAG`] being set in the type byte.
+    Duration,
     JSON,
     Null,
     DoubleFloat,
@@ -350,6 +656,31 @@ pub enum ValueTy {
     SingleFloat,
 }
 
+impl Display for ValueTy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            ValueTy::Character => "Character",
+            ValueTy::String => "String",
+            ValueTy::Binary => "Binary",
+            ValueTy::Boolean => "Boolean",
+            ValueTy::Integer => "Integer",
+            ValueTy::Imaginary => "Imaginary",
+            ValueTy::Timestamp => "Timestamp",
+            ValueTy::Duration => "Duration",
+            ValueTy::JSON => "JSON",
+            ValueTy::Null => "Null",
+            ValueTy::DoubleFloat => "DoubleFloat",
+            ValueTy::Array => "Array",
+            ValueTy::Map => "Map",
+            ValueTy::Timezone => "Timezone",
+            ValueTy::Ipv4Addr => "Ipv4Addr",
+            ValueTy::Ipv6Addr => "Ipv6Addr",
+            ValueTy::SingleFloat => "SingleFloat",
+        };
+        write!(f, "{name}")
+    }
+}
+
 impl From<ValueTy> for u8 {
     fn from(value: ValueTy) -> Self {
         match value {
@@ -359,7 +690,7 @@ impl From<ValueTy> for u8 {
             ValueTy::Boolean => 3,
             ValueTy::Integer => 4,
             ValueTy::Imaginary => 5,
-            ValueTy::Timestamp => 6,
+            ValueTy::Timestamp | ValueTy::Duration => 6,
             ValueTy::JSON => 7,
             ValueTy::Map => 8,
             ValueTy::Null => 9,
@@ -398,6 +729,57 @@ impl TryFrom<u8> for ValueTy {
     }
 }
 
+impl ValueTy {
+    ///The unsigned integers (see [`SignedState::Unsigned`]) which can be niche-optimised - see [`ValueTy::niche_value`].
+    pub const NICHED_UNSIGNED_INTEGERS: RangeInclusive<u8> = 0..=5;
+    ///The signed integers which can be niche-optimised - see [`ValueTy::niche_value`].
+    pub const NICHED_SIGNED_INTEGERS: RangeInclusive<i8> = -3..=3;
+    ///Added to niched unsigned integers so that their niche bits are always above the bits used for the [`SignedState`] of integers which aren't niched (`0..=2`).
+    const NICHED_UNSIGNED_OFFSET: u8 = 3;
+    ///Added to niched signed integers so that their niche bits come straight after those of the unsigned integers.
+    const NICHED_SIGNED_OFFSET: i8 = 12;
+
+    ///Gets the bits used to store a value entirely within the bottom 4 bits of the type byte, or [`None`] if the value cannot be niche-optimised.
+    ///
+    /// Currently, only [`Value::Boolean`], [`Value::Null`], and [`Value::Integer`]s in [`ValueTy::NICHED_UNSIGNED_INTEGERS`] or [`ValueTy::NICHED_SIGNED_INTEGERS`] (depending on their [`SignedState`]) can be stored this way. Unsigned integers are niched as `3..=8` and signed ones as `9..=15`, so they never clash with the [`SignedState`] stored there by integers which aren't niched, and the [`SignedState`] of niched integers survives a round trip. Older readers can't read niched integers - see [`ValueTy`].
+    #[must_use]
+    pub fn niche_value(value: &Value) -> Option<u8> {
+        match value {
+            Value::Boolean(b) => Some(u8::from(*b)),
+            Value::Null(()) => Some(0),
+            Value::Integer(i) if i.signed_state() == SignedState::Unsigned => u8::try_from(*i)
+                .ok()
+                .filter(|i| Self::NICHED_UNSIGNED_INTEGERS.contains(i))
+                .map(|i| i + Self::NICHED_UNSIGNED_OFFSET),
+            Value::Integer(i) => i8::try_from(*i)
+                .ok()
+                .filter(|i| Self::NICHED_SIGNED_INTEGERS.contains(i))
+                .and_then(|i| u8::try_from(i + Self::NICHED_SIGNED_OFFSET).ok()),
+            _ => None,
+        }
+    }
+
+    ///Reconstructs a value from the niche bits found in the type byte - the inverse of [`ValueTy::niche_value`].
+    ///
+    /// Returns [`None`] if the type cannot be niche-optimised or the bits are not valid for that type.
+    #[must_use]
+    pub fn from_niche(self, bits: u8) -> Option<Value> {
+        match (self, bits) {
+            (ValueTy::Boolean, 0) => Some(Value::Boolean(false)),
+            (ValueTy::Boolean, 1) => Some(Value::Boolean(true)),
+            (ValueTy::Null, 0) => Some(Value::Null(())),
+            (ValueTy::Integer, 3..=8) => Some(Value::Integer(Integer::from(
+                bits - Self::NICHED_UNSIGNED_OFFSET,
+            ))),
+            (ValueTy::Integer, 9..=15) => {
+                let i = i8::try_from(bits).ok()? - Self::NICHED_SIGNED_OFFSET;
+                Some(Value::Integer(Integer::from(i)))
+            }
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum ValueSerError {
     InvalidType(u8),
@@ -415,6 +797,12 @@ pub enum ValueSerError {
     SerdeCustom(String),
     NoHuffman,
     UnableToDecodeHuffman,
+    InvalidNiche(ValueTy, u8),
+    DepthExceeded,
+    OddLengthHex(usize),
+    InvalidHexCharacter(char),
+    TrailingBytes(usize),
+    CannotMerge(ValueTy, ValueTy),
 }
 
 impl Display for ValueSerError {
@@ -434,7 +822,7 @@ impl Display for ValueSerError {
             ValueSerError::NonUTF8String(e) => write!(f, "Error converting to UTF-8: {e}"),
             ValueSerError::SerdeJson(e) => write!(f, "Error de/ser-ing serde_json: {e}"),
             ValueSerError::UnexpectedValueType(found, ex) => {
-                write!(f, "Expected {ex:?}, found: {found:?}")
+                write!(f, "Expected {ex}, found {found}")
             }
             ValueSerError::TzError(e) => write!(f, "Error parsing timezone: {e}"),
             ValueSerError::InvalidDateOrTime => write!(f, "Error with invalid time given"),
@@ -448,6 +836,28 @@ impl Display for ValueSerError {
                     "Encountered huffman-encoded string but was unable to decode it"
                 )
             }
+            ValueSerError::InvalidNiche(ty, bits) => {
+                write!(f, "Invalid niche bits found for {ty}: {bits:#b}")
+            }
+            ValueSerError::DepthExceeded => {
+                write!(f, "Exceeded maximum nesting depth while deserialising")
+            }
+            ValueSerError::OddLengthHex(len) => {
+                write!(
+                    f,
+                    "Hex strings must have an even length, found length {len}"
+                )
+            }
+            ValueSerError::InvalidHexCharacter(ch) => {
+                write!(f, "Invalid hex character found: {ch:?}")
+            }
+            ValueSerError::TrailingBytes(remaining) => {
+                write!(
+                    f,
+                    "Found {remaining} trailing byte(s) after deserialising value"
+                )
+            }
+            ValueSerError::CannotMerge(a, b) => write!(f, "Unable to merge {a} with {b}"),
         }
     }
 }
@@ -488,8 +898,17 @@ impl std::error::Error for ValueSerError {
 
 impl Value {
     ///if it is an integer outside the bounds of [`i64::MIN`] to [`u64::MAX`], then it will fail. it will also fail if it was a float that wasn't NaN or infinity
-    #[allow(clippy::too_many_lines)]
+    #[must_use]
     pub fn convert_to_json(self) -> Option<SJValue> {
+        self.convert_to_json_with(FloatFormat::default())
+    }
+
+    ///Converts the value to JSON like [`Value::convert_to_json`], but with floats (including any nested in maps and arrays) rounded according to `float_format` - see [`FloatFormat`].
+    ///
+    /// With [`FloatFormat::Shortest`], every float is converted to the JSON number which reads back in as exactly the same float.
+    #[must_use]
+    #[allow(clippy::too_many_lines)]
+    pub fn convert_to_json_with(self, float_format: FloatFormat) -> Option<SJValue> {
         Some(match self {
             Value::Character(c) => SJValue::String(c.into()),
             Value::String(s) => SJValue::String(s),
@@ -497,16 +916,20 @@ impl Value {
             Value::Integer(i) => i.to_json()?,
             Value::JSON(j) => j,
             Value::Null(()) => SJValue::Null,
-            Value::SingleFloat(f) => SJValue::Number(Number::from_f64(f64::from(f))?),
-            Value::DoubleFloat(f) => SJValue::Number(Number::from_f64(f)?),
+            Value::SingleFloat(f) => {
+                SJValue::Number(FloatFormat::to_json_number(&float_format.format_f32(f))?)
+            }
+            Value::DoubleFloat(f) => {
+                SJValue::Number(FloatFormat::to_json_number(&float_format.format_f64(f))?)
+            }
             Value::Array(arr) => SJValue::Array(
                 arr.into_iter()
-                    .map(Value::convert_to_json)
+                    .map(|v| v.convert_to_json_with(float_format))
                     .collect::<Option<Vec<_>>>()?,
             ),
             Value::Map(m) => SJValue::Object(
                 m.into_iter()
-                    .map(|(k, v)| Value::convert_to_json(v).map(|v| (k, v)))
+                    .map(|(k, v)| v.convert_to_json_with(float_format).map(|v| (k, v)))
                     .collect::<Option<SJMap<_, _>>>()?,
             ),
             Value::Imaginary(im) => {
@@ -551,6 +974,21 @@ impl Value {
 
                 SJValue::Object(obj)
             }
+            Value::Duration(d) => {
+                let mut obj = SJMap::new();
+                obj.insert(
+                    "souris_type".into(),
+                    SJValue::Number(Number::from(u8::from(ValueTy::Duration))),
+                );
+
+                obj.insert("seconds".into(), SJValue::Number(d.num_seconds().into()));
+                obj.insert(
+                    "nanoseconds".into(),
+                    SJValue::Number(d.subsec_nanos().into()),
+                );
+
+                SJValue::Object(obj)
+            }
             Value::Timezone(tz) => {
                 let mut obj = SJMap::new();
                 obj.insert(
@@ -672,12 +1110,25 @@ impl Value {
                                     }
                                 }
                             }
-                            ValueTy::Timestamp => {
+                            ValueTy::Timestamp | ValueTy::Duration => {
                                 if let Some(SJValue::String(timestamp)) = obj.get("timestamp") {
                                     if let Ok(timestamp) = NaiveDateTime::from_str(timestamp) {
                                         return Value::Timestamp(timestamp);
                                     }
                                 }
+
+                                //durations share a discriminant with timestamps, so are told apart by their fields
+                                if let Some((secs, nanos)) = obj
+                                    .get("seconds")
+                                    .and_then(SJValue::as_i64)
+                                    .zip(obj.get("nanoseconds").and_then(SJValue::as_i64))
+                                {
+                                    if let Some(d) = Self::duration_from_total_nanos(
+                                        i128::from(secs) * 1_000_000_000 + i128::from(nanos),
+                                    ) {
+                                        return Value::Duration(d);
+                                    }
+                                }
                             }
                             ValueTy::Timezone => {
                                 if let Some(SJValue::String(tz)) = obj.get("timezone") {
@@ -738,6 +1189,65 @@ impl Value {
     }
 }
 
+impl Value {
+    ///Compares two values, treating a [`Value::String`] and a [`Value::Binary`] as equal if the string's UTF-8 bytes are the same as the binary. This is useful for reconciling data which has been round-tripped as the wrong one of the two.
+    ///
+    /// All other values are compared using [`PartialEq::eq`].
+    #[must_use]
+    pub fn eq_as_bytes(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Self::String(s), Self::Binary(b)) | (Self::Binary(b), Self::String(s)) => {
+                s.as_bytes() == b.as_slice()
+            }
+            _ => self == other,
+        }
+    }
+}
+
+impl Value {
+    ///Writes out a [`Value::Binary`] as a plain lowercase hex string (eg. `deadbeef`), which can be read back in using [`Value::binary_from_hex`]. Returns [`None`] for any other kind of value.
+    #[must_use]
+    pub fn binary_to_hex(&self) -> Option<String> {
+        const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+        let Self::Binary(b) = self else {
+            return None;
+        };
+
+        let mut out = String::with_capacity(b.len() * 2);
+        for byte in b {
+            out.push(char::from(HEX_DIGITS[usize::from(byte >> 4)]));
+            out.push(char::from(HEX_DIGITS[usize::from(byte & 0xf)]));
+        }
+        Some(out)
+    }
+
+    ///Reads a plain hex string (eg. `deadbeef` or `DEADBEEF`) into a [`Value::Binary`] - the opposite of [`Value::binary_to_hex`].
+    ///
+    /// ## Errors
+    /// - [`ValueSerError::OddLengthHex`] if `s` doesn't have an even number of characters.
+    /// - [`ValueSerError::InvalidHexCharacter`] if `s` contains anything other than hex digits.
+    pub fn binary_from_hex(s: &str) -> Result<Value, ValueSerError> {
+        let digits = s
+            .chars()
+            .map(|ch| {
+                ch.to_digit(16)
+                    .ok_or(ValueSerError::InvalidHexCharacter(ch))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if !digits.len().is_multiple_of(2) {
+            return Err(ValueSerError::OddLengthHex(digits.len()));
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        let bytes = digits
+            .chunks_exact(2)
+            .map(|pair| ((pair[0] << 4) | pair[1]) as u8)
+            .collect();
+        Ok(Value::Binary(bytes))
+    }
+}
+
 impl Value {
     pub(crate) const fn as_ty(&self) -> ValueTy {
         match self {
@@ -748,6 +1258,7 @@ impl Value {
             Self::Integer(_) => ValueTy::Integer,
             Self::Imaginary(_) => ValueTy::Imaginary,
             Self::Timestamp(_) => ValueTy::Timestamp,
+            Self::Duration(_) => ValueTy::Duration,
             Self::JSON(_) => ValueTy::JSON,
             Self::Map(_) => ValueTy::Map,
             Self::Array(_) => ValueTy::Array,
@@ -789,6 +1300,11 @@ impl Value {
 
         let mut ty = u8::from(self.as_ty()) << 4;
 
+        if let Some(niche) = ValueTy::niche_value(self) {
+            res.push(ty | niche);
+            return Ok(res);
+        }
+
         match self {
             Self::Character(ch) => {
                 let (_, bytes) = Integer::from(*ch as u32).ser();
@@ -821,9 +1337,8 @@ impl Value {
                 res.extend(len_bytes);
                 res.extend(b.iter());
             }
-            Self::Boolean(b) => {
-                ty |= u8::from(*b);
-                res.push(ty);
+            Self::Boolean(_) | Self::Null(()) => {
+                unreachable!("niche-able values are serialised above")
             }
             Self::Integer(i) => {
                 let (signed_state, bytes) = i.ser();
@@ -865,12 +1380,17 @@ impl Value {
                 res.extend(sec.iter());
                 res.extend(nanos.iter());
             }
-            Self::JSON(v) => {
+            Self::Duration(d) => {
+                let (signed_state, bytes) = Integer::from(Self::duration_to_nanos(*d)).ser();
+
+                ty |= Self::DURATION_FLAG | u8::from(signed_state);
+
                 res.push(ty);
-                res.extend(Value::String(v.to_string()).ser(huffman)?);
+                res.extend(bytes.iter());
             }
-            Self::Null(()) => {
+            Self::JSON(v) => {
                 res.push(ty);
+                res.extend(Value::String(v.to_string()).ser(huffman)?);
             }
             Self::SingleFloat(f) => {
                 res.push(ty);
@@ -892,9 +1412,12 @@ impl Value {
                     res.extend(integer_bytes);
                 }
 
-                for (k, v) in m.clone() {
-                    res.extend(Value::String(k).ser(huffman)?);
-                    res.extend(v.ser(huffman)?);
+                //sorted so that equal maps always serialise to the same bytes
+                let mut keys = m.keys().collect::<Vec<_>>();
+                keys.sort_unstable();
+                for k in keys {
+                    res.extend(Value::String(k.clone()).ser(huffman)?);
+                    res.extend(m[k].ser(huffman)?);
                 }
             }
             Self::Array(a) => {
@@ -932,20 +1455,193 @@ impl Value {
         Ok(res)
     }
 
-    #[allow(clippy::many_single_char_names, clippy::too_many_lines)]
+    ///Deserialises a value, limiting nesting to [`DEFAULT_MAX_DEPTH`] levels - see [`Value::deser_with_max_depth`].
+    ///
+    /// ## Errors
+    /// See [`Value::deser_with_max_depth`].
     pub fn deser(
         bytes: &mut Cursor<u8>,
         huffman: Option<&Huffman<char>>,
     ) -> Result<Self, ValueSerError> {
+        Self::deser_with_max_depth(bytes, huffman, DEFAULT_MAX_DEPTH)
+    }
+
+    ///Reads the next `len` bytes from the cursor and deserialises exactly one value from them using [`Value::deser`], checking that the value used up every byte. This is useful for catching framing bugs where the length given for a value is wrong.
+    ///
+    /// The cursor is moved on by `len` bytes, even if deserialising fails.
+    ///
+    /// ## Errors
+    /// - [`ValueSerError::NotEnoughBytes`] if the cursor has fewer than `len` bytes left.
+    /// - [`ValueSerError::TrailingBytes`] with the number of unused bytes if the value didn't use all `len` of them.
+    /// - Any error from [`Value::deser`].
+    pub fn deser_exact(
+        bytes: &mut Cursor<u8>,
+        len: usize,
+        huffman: Option<&Huffman<char>>,
+    ) -> Result<Self, ValueSerError> {
+        Self::deser_exact_with_max_depth(bytes, len, huffman, DEFAULT_MAX_DEPTH)
+    }
+
+    ///Deserialises exactly one value from the next `len` bytes like [`Value::deser_exact`], but with nesting limited to `max_depth` levels like [`Value::deser_with_max_depth`].
+    ///
+    /// ## Errors
+    /// Any error from [`Value::deser_exact`] or [`Value::deser_with_max_depth`].
+    pub fn deser_exact_with_max_depth(
+        bytes: &mut Cursor<u8>,
+        len: usize,
+        huffman: Option<&Huffman<char>>,
+        max_depth: usize,
+    ) -> Result<Self, ValueSerError> {
+        let Some(bytes) = bytes.read(len) else {
+            return Err(ValueSerError::NotEnoughBytes);
+        };
+        let mut bytes = Cursor::new(&bytes);
+
+        let value = Self::deser_with_max_depth(&mut bytes, huffman, max_depth)?;
+        match bytes.items_remaining() {
+            0 => Ok(value),
+            remaining => Err(ValueSerError::TrailingBytes(remaining)),
+        }
+    }
+
+    ///Deserialises a value, returning [`ValueSerError::DepthExceeded`] if values are nested more than `max_depth` levels deep.
+    ///
+    /// Every value which contains other values ([`Value::Map`], [`Value::Array`], [`Value::JSON`] and [`Value::Timezone`]) counts as one level, so a `max_depth` of `0` only allows values which don't contain any others.
+    ///
+    /// Nested values are deserialised using an explicit stack rather than by recursing, so even with a `max_depth` of [`usize::MAX`] crafted input cannot overflow the stack here. The limit is still worth keeping, as dropping, cloning or serialising a value all recurse.
+    ///
+    /// ## Errors
+    /// - [`ValueSerError::DepthExceeded`] if values are nested more than `max_depth` levels deep.
+    /// - Any other [`ValueSerError`] if the bytes aren't a valid serialised value.
+    pub fn deser_with_max_depth(
+        bytes: &mut Cursor<u8>,
+        huffman: Option<&Huffman<char>>,
+        max_depth: usize,
+    ) -> Result<Self, ValueSerError> {
+        enum Partial {
+            Array {
+                remaining: usize,
+                items: Vec<Value>,
+            },
+            Map {
+                remaining: usize,
+                map: HashMap<String, Value>,
+                key: String,
+            },
+        }
+
+        let mut stack: Vec<Partial> = vec![];
+
+        loop {
+            let byte = bytes.next().ok_or(ValueSerError::NotEnoughBytes).copied()?;
+            let ty = ValueTy::try_from((byte & 0b1111_0000) >> 4)?;
+
+            let mut value = match ty {
+                ValueTy::Map | ValueTy::Array | ValueTy::JSON | ValueTy::Timezone
+                    if stack.len() >= max_depth =>
+                {
+                    return Err(ValueSerError::DepthExceeded);
+                }
+                ValueTy::Map => {
+                    let len = Self::deser_array_or_map_len(byte, bytes, ty)?;
+                    if len == 0 {
+                        Value::Map(HashMap::new())
+                    } else {
+                        let key = Self::deser_string(bytes, huffman)?;
+                        stack.push(Partial::Map {
+                            remaining: len,
+                            map: HashMap::with_capacity(len),
+                            key,
+                        });
+                        continue;
+                    }
+                }
+                ValueTy::Array => {
+                    let len = Self::deser_array_or_map_len(byte, bytes, ty)?;
+                    if len == 0 {
+                        Value::Array(vec![])
+                    } else {
+                        stack.push(Partial::Array {
+                            remaining: len,
+                            items: Vec::with_capacity(len),
+                        });
+                        continue;
+                    }
+                }
+                _ => Self::deser_scalar(byte, ty, bytes, huffman)?,
+            };
+
+            //hand the finished value up to its parent, finishing the parent too if that was its last child
+            loop {
+                let Some(parent) = stack.last_mut() else {
+                    return Ok(value);
+                };
+
+                let remaining = match parent {
+                    Partial::Array { remaining, items } => {
+                        items.push(value);
+                        *remaining -= 1;
+                        *remaining
+                    }
+                    Partial::Map {
+                        remaining,
+                        map,
+                        key,
+                    } => {
+                        map.insert(core::mem::take(key), value);
+                        *remaining -= 1;
+                        if *remaining > 0 {
+                            *key = Self::deser_string(bytes, huffman)?;
+                        }
+                        *remaining
+                    }
+                };
+                if remaining > 0 {
+                    break;
+                }
+
+                value = match stack.pop() {
+                    Some(Partial::Array { items, .. }) => Value::Array(items),
+                    Some(Partial::Map { map, .. }) => Value::Map(map),
+                    None => unreachable!("just checked that there's a parent"),
+                };
+            }
+        }
+    }
+
+    ///Deserialises a value which must be a [`Value::String`], as used for map keys and inside [`Value::JSON`] and [`Value::Timezone`].
+    pub(crate) fn deser_string(
+        bytes: &mut Cursor<u8>,
+        huffman: Option<&Huffman<char>>,
+    ) -> Result<String, ValueSerError> {
         let byte = bytes.next().ok_or(ValueSerError::NotEnoughBytes).copied()?;
+        let ty = ValueTy::try_from((byte & 0b1111_0000) >> 4)?;
+        if ty != ValueTy::String {
+            return Err(ValueSerError::UnexpectedValueType(ty, ValueTy::String));
+        }
 
-        let ty = (byte & 0b1111_0000) >> 4;
-        let ty = ValueTy::try_from(ty)?;
+        let Value::String(s) = Self::deser_scalar(byte, ty, bytes, huffman)? else {
+            unreachable!("just checked that it was a string")
+        };
+        Ok(s)
+    }
 
+    ///Deserialises any value other than a [`Value::Map`] or [`Value::Array`], given the type byte has already been read.
+    #[allow(clippy::many_single_char_names, clippy::too_many_lines)]
+    fn deser_scalar(
+        byte: u8,
+        ty: ValueTy,
+        bytes: &mut Cursor<u8>,
+        huffman: Option<&Huffman<char>>,
+    ) -> Result<Self, ValueSerError> {
         //for lengths or single integers
 
         Ok(match ty {
             ValueTy::Integer => {
+                if let Some(niched) = ty.from_niche(byte & 0b0000_1111) {
+                    return Ok(niched);
+                }
+
                 let signed_state = SignedState::try_from(byte & 0b0000_0011)?;
                 let int = Integer::deser(signed_state, bytes)?;
                 Self::Integer(int)
@@ -960,7 +1656,16 @@ impl Value {
                     .ok_or(ValueSerError::InvalidCharacter)?;
                 Self::Character(ch)
             }
-            ValueTy::Timestamp => {
+            ValueTy::Timestamp | ValueTy::Duration if byte & Self::DURATION_FLAG > 0 => {
+                let signed_state = SignedState::try_from(byte & 0b0000_0011)?;
+                let nanos = Integer::deser(signed_state, bytes)?.try_into()?;
+
+                Self::Duration(
+                    Self::duration_from_total_nanos(nanos)
+                        .ok_or(ValueSerError::InvalidDateOrTime)?,
+                )
+            }
+            ValueTy::Timestamp | ValueTy::Duration => {
                 let year_signed_state = SignedState::try_from(byte & 0b0000_0001)?;
 
                 let year = Integer::deser(year_signed_state, bytes)?.try_into()?;
@@ -1002,13 +1707,7 @@ impl Value {
                 }
             }
             ValueTy::JSON => {
-                let val = Value::deser(bytes, huffman)?;
-                let Value::String(s) = val else {
-                    return Err(ValueSerError::UnexpectedValueType(
-                        val.as_ty(),
-                        ValueTy::String,
-                    ));
-                };
+                let s = Self::deser_string(bytes, huffman)?;
                 let value: SJValue = serde_json::from_str(&s)?;
                 Self::JSON(value)
             }
@@ -1020,8 +1719,11 @@ impl Value {
                     .to_vec();
                 Self::Binary(bytes)
             }
-            ValueTy::Boolean => Self::Boolean((byte & 0b0000_0001) > 0),
-            ValueTy::Null => Self::Null(()),
+            ValueTy::Boolean | ValueTy::Null => {
+                let bits = byte & 0b0000_1111;
+                ty.from_niche(bits)
+                    .ok_or(ValueSerError::InvalidNiche(ty, bits))?
+            }
             ValueTy::SingleFloat => {
                 let Some(bytes) = bytes.read_exact() else {
                     return Err(ValueSerError::NotEnoughBytes);
@@ -1034,42 +1736,11 @@ impl Value {
                 };
                 Self::DoubleFloat(f64::from_le_bytes(*bytes))
             }
-            ValueTy::Map => {
-                let len = Self::deser_array_or_map_len(byte, bytes, ty)?;
-
-                let mut map = HashMap::with_capacity(len);
-
-                for _ in 0..len {
-                    let key = Value::deser(bytes, huffman)?;
-                    let Value::String(key) = key else {
-                        return Err(ValueSerError::UnexpectedValueType(
-                            key.as_ty(),
-                            ValueTy::String,
-                        ));
-                    };
-                    let value = Value::deser(bytes, huffman)?;
-                    map.insert(key, value);
-                }
-
-                Value::Map(map)
-            }
-            ValueTy::Array => {
-                let len = Self::deser_array_or_map_len(byte, bytes, ty)?;
-
-                Value::Array(
-                    (0..len)
-                        .map(|_| Value::deser(bytes, huffman))
-                        .collect::<Result<_, _>>()?,
-                )
+            ValueTy::Map | ValueTy::Array => {
+                unreachable!("containers are deserialised in Value::deser_with_max_depth")
             }
             ValueTy::Timezone => {
-                let val = Value::deser(bytes, huffman)?;
-                let Value::String(val) = val else {
-                    return Err(ValueSerError::UnexpectedValueType(
-                        val.as_ty(),
-                        ValueTy::String,
-                    ));
-                };
+                let val = Self::deser_string(bytes, huffman)?;
                 let tz = Tz::from_str(&val)?;
                 Self::Timezone(tz)
             }
@@ -1101,19 +1772,528 @@ mod tests {
     use alloc::{
         format,
         string::{String, ToString},
+        vec,
         vec::Vec,
     };
 
+    use chrono::{NaiveDateTime, TimeDelta};
+    use hashbrown::HashMap;
     use proptest::{arbitrary::any, prop_assert_eq, proptest};
 
     use crate::{
-        types::{imaginary::Imaginary, integer::BiggestIntButSigned},
+        types::{
+            imaginary::Imaginary,
+            integer::{BiggestIntButSigned, Integer},
+        },
         utilities::cursor::Cursor,
     };
 
-    use super::Value;
+    use super::{FloatFormat, SizeClass, Value, ValueSerError, ValueTy};
+
+    #[test]
+    fn test_value_ty_display() {
+        assert_eq!(ValueTy::Integer.to_string(), "Integer");
+        assert_eq!(ValueTy::String.to_string(), "String");
+        assert_eq!(ValueTy::Ipv6Addr.to_string(), "Ipv6Addr");
+
+        assert_eq!(
+            ValueSerError::UnexpectedValueType(ValueTy::Integer, ValueTy::String).to_string(),
+            "Expected String, found Integer"
+        );
+    }
+
+    #[test]
+    fn test_deser_exact() {
+        let mut bytes = Value::Boolean(true).ser(None).unwrap();
+        let len = bytes.len();
+        bytes.extend([0xAB, 0xCD]);
+
+        let mut cursor = Cursor::new(&bytes);
+        assert_eq!(
+            Value::deser_exact(&mut cursor, len, None).unwrap(),
+            Value::Boolean(true)
+        );
+        assert_eq!(cursor.pos(), len);
+
+        let mut cursor = Cursor::new(&bytes);
+        assert!(matches!(
+            Value::deser_exact(&mut cursor, len + 2, None),
+            Err(ValueSerError::TrailingBytes(2))
+        ));
+        assert!(cursor.is_finished());
+
+        assert!(matches!(
+            Value::deser_exact(&mut Cursor::new(&bytes), len + 3, None),
+            Err(ValueSerError::NotEnoughBytes)
+        ));
+    }
+
+    #[test]
+    fn test_niche_round_trip() {
+        for v in [Value::Boolean(true), Value::Boolean(false), Value::Null(())] {
+            let bits = ValueTy::niche_value(&v).unwrap();
+            assert!(bits <= 0b1111);
+            assert_eq!(v.as_ty().from_niche(bits), Some(v.clone()));
+
+            let bytes = v.ser(None).unwrap();
+            assert_eq!(bytes.len(), 1);
+            assert_eq!(Value::deser(&mut Cursor::new(&bytes), None).unwrap(), v);
+        }
+
+        assert_eq!(ValueTy::niche_value(&Value::Integer(7.into())), None);
+        for signed_state in 0..=2 {
+            assert_eq!(ValueTy::Integer.from_niche(signed_state), None);
+        }
+    }
+
+    #[test]
+    fn test_niched_integers() {
+        let unsigned = ValueTy::NICHED_UNSIGNED_INTEGERS.map(Integer::from);
+        let signed = ValueTy::NICHED_SIGNED_INTEGERS.map(Integer::from);
+        for i in unsigned.chain(signed) {
+            let v = Value::Integer(i);
+            let bits = ValueTy::niche_value(&v).unwrap();
+            assert!((3..=0b1111).contains(&bits));
+            assert_eq!(ValueTy::Integer.from_niche(bits), Some(v.clone()));
+
+            let bytes = v.ser(None).unwrap();
+            assert_eq!(bytes.len(), 1, "{i}");
+            let Value::Integer(round_tripped) =
+                Value::deser(&mut Cursor::new(&bytes), None).unwrap()
+            else {
+                panic!("expected an integer");
+            };
+            assert_eq!(round_tripped, i);
+            assert_eq!(round_tripped.signed_state(), i.signed_state(), "{i}");
+        }
+
+        //unsigned and signed integers with the same value get different niches
+        assert_ne!(
+            ValueTy::niche_value(&Value::from(2_u64)),
+            ValueTy::niche_value(&Value::from(2_i64))
+        );
+        //just past either end of the ranges falls back to the full encoding
+        for i in [
+            Integer::from(6_u64),
+            Integer::from(4_i64),
+            Integer::from(-4_i64),
+        ] {
+            let v = Value::Integer(i);
+            assert_eq!(ValueTy::niche_value(&v), None);
+
+            let bytes = v.ser(None).unwrap();
+            assert!(bytes.len() > 1, "{i}");
+            let Value::Integer(round_tripped) =
+                Value::deser(&mut Cursor::new(&bytes), None).unwrap()
+            else {
+                panic!("expected an integer");
+            };
+            assert_eq!(round_tripped.signed_state(), i.signed_state(), "{i}");
+        }
+        for i in [-100_i64, 100, 250, i64::MIN, i64::MAX] {
+            let v = Value::from(i);
+            assert_eq!(ValueTy::niche_value(&v), None);
+
+            let bytes = v.ser(None).unwrap();
+            assert!(bytes.len() > 1, "{i}");
+            assert_eq!(Value::deser(&mut Cursor::new(&bytes), None).unwrap(), v);
+        }
+    }
+
+    #[test]
+    fn test_nested_round_trip() {
+        let mut inner = HashMap::new();
+        inner.insert("empty_map".to_string(), Value::Map(HashMap::new()));
+        inner.insert("empty_array".to_string(), Value::Array(vec![]));
+        inner.insert(
+            "json".to_string(),
+            Value::JSON(serde_json::json!({"a": [1, 2, {"b": null}]})),
+        );
+        inner.insert("tz".to_string(), Value::Timezone(chrono_tz::Europe::London));
+
+        let mut outer = HashMap::new();
+        outer.insert("inner".to_string(), Value::Map(inner));
+        outer.insert(
+            "long_array".to_string(),
+            Value::Array(
+                (0..20)
+                    .map(|i| {
+                        Value::Array(vec![Value::Integer(i.into()), Value::Boolean(i % 2 == 0)])
+                    })
+                    .collect(),
+            ),
+        );
+        let value = Value::Map(outer);
+
+        let bytes = value.ser(None).unwrap();
+        let mut cursor = Cursor::new(&bytes);
+        assert_eq!(Value::deser(&mut cursor, None).unwrap(), value);
+        assert!(cursor.is_finished());
+
+        assert!(matches!(
+            Value::deser_with_max_depth(&mut Cursor::new(&bytes), None, 2),
+            Err(ValueSerError::DepthExceeded)
+        ));
+        assert_eq!(
+            Value::deser_with_max_depth(&mut Cursor::new(&bytes), None, 3).unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn test_eq_as_bytes() {
+        let string = Value::String("hi".to_string());
+        let binary = Value::Binary(b"hi".to_vec());
+
+        assert_ne!(string, binary);
+        assert!(string.eq_as_bytes(&binary));
+        assert!(binary.eq_as_bytes(&string));
+        assert!(string.eq_as_bytes(&string));
+
+        assert!(!string.eq_as_bytes(&Value::Binary(b"hello".to_vec())));
+        assert!(!binary.eq_as_bytes(&Value::Character('h')));
+    }
+
+    #[test]
+    fn test_character_helpers() {
+        let letter = Value::Character('a');
+        let digit = Value::Character('7');
+        let space = Value::Character(' ');
+        let punctuation = Value::Character('!');
+        let not_a_char = Value::String("a".into());
+
+        assert_eq!(letter.is_alphanumeric(), Some(true));
+        assert_eq!(digit.is_alphanumeric(), Some(true));
+        assert_eq!(space.is_alphanumeric(), Some(false));
+        assert_eq!(punctuation.is_alphanumeric(), Some(false));
+        assert_eq!(not_a_char.is_alphanumeric(), None);
+
+        assert_eq!(space.is_whitespace(), Some(true));
+        assert_eq!(Value::Character('\n').is_whitespace(), Some(true));
+        assert_eq!(letter.is_whitespace(), Some(false));
+        assert_eq!(not_a_char.is_whitespace(), None);
+
+        assert_eq!(letter.to_uppercase(), Some(Value::Character('A')));
+        assert_eq!(digit.to_uppercase(), Some(Value::Character('7')));
+        assert_eq!(
+            Value::Character('ß').to_uppercase(),
+            Some(Value::String("SS".into()))
+        );
+        assert_eq!(not_a_char.to_uppercase(), None);
+
+        assert_eq!(
+            Value::Character('Q').to_lowercase(),
+            Some(Value::Character('q'))
+        );
+        assert_eq!(not_a_char.to_lowercase(), None);
+    }
+
+    #[test]
+    fn test_binary_hex() {
+        let value = Value::Binary(vec![0xde, 0xad, 0xbe, 0xef, 0x00, 0x0f]);
+        let hex = value.binary_to_hex().unwrap();
+        assert_eq!(hex, "deadbeef000f");
+        assert_eq!(Value::binary_from_hex(&hex).unwrap(), value);
+        assert_eq!(Value::binary_from_hex("DEADBEEF000F").unwrap(), value);
+
+        assert_eq!(Value::Binary(vec![]).binary_to_hex().unwrap(), "");
+        assert_eq!(Value::binary_from_hex("").unwrap(), Value::Binary(vec![]));
+        assert_eq!(Value::String("de".into()).binary_to_hex(), None);
+
+        assert!(matches!(
+            Value::binary_from_hex("abc"),
+            Err(ValueSerError::OddLengthHex(3))
+        ));
+        assert!(matches!(
+            Value::binary_from_hex("0xab"),
+            Err(ValueSerError::InvalidHexCharacter('x'))
+        ));
+        assert!(matches!(
+            Value::binary_from_hex("éé"),
+            Err(ValueSerError::InvalidHexCharacter('é'))
+        ));
+    }
+
+    #[test]
+    fn test_duration_round_trip() {
+        let timestamp = Value::Timestamp(NaiveDateTime::default());
+
+        for nanos in [
+            0,
+            1,
+            -1,
+            5_400_000_000_000,
+            -90_061_500_000_000,
+            i64::MIN,
+            i64::MAX,
+        ] {
+            let v = Value::duration_from_nanos(nanos);
+
+            let bytes = v.ser(None).unwrap();
+            assert_eq!(bytes[0] & 0b1111_0000, u8::from(ValueTy::Timestamp) << 4);
+            assert_eq!(Value::deser(&mut Cursor::new(&bytes), None).unwrap(), v);
+
+            assert_eq!(
+                Value::convert_from_json(v.clone().convert_to_json().unwrap()),
+                v
+            );
+            assert_ne!(v, timestamp);
+        }
+
+        let bytes = timestamp.ser(None).unwrap();
+        assert_eq!(
+            Value::deser(&mut Cursor::new(&bytes), None).unwrap(),
+            timestamp
+        );
+    }
+
+    #[test]
+    fn test_duration_display() {
+        let cases = [
+            (0, "0s"),
+            (5_400_000_000_000, "1h30m"),
+            (-5_400_000_000_000, "-1h30m"),
+            (90_061_500_000_000, "1d1h1m1.5s"),
+            (-1, "-0.000000001s"),
+            (172_800_000_000_000, "2d"),
+            (30_250_000_000, "30.25s"),
+        ];
+        for (nanos, expected) in cases {
+            assert_eq!(Value::duration_from_nanos(nanos).to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_chars_from_str() {
+        let store = Value::chars_from_str("abc");
+        assert_eq!(
+            store.as_array(),
+            Some(&vec![
+                Value::Character('a'),
+                Value::Character('b'),
+                Value::Character('c')
+            ])
+        );
+
+        //an `e` followed by a combining acute accent, and a family emoji made of 3 people joined by 2 zero-width joiners
+        let multi_scalar = "e\u{301}👩\u{200d}👩\u{200d}👦";
+        let chars = Value::chars_from_str(multi_scalar).to_array().unwrap();
+        assert_eq!(chars.len(), 7);
+        assert_eq!(chars[1], Value::Character('\u{301}'));
+        let joined = chars
+            .into_iter()
+            .map(|c| c.to_char().unwrap())
+            .collect::<String>();
+        assert_eq!(joined, multi_scalar);
+
+        assert_eq!(Value::chars_from_str("").as_array(), Some(&vec![]));
+    }
+
+    #[test]
+    fn test_debug_shows_lengths() {
+        let debug = format!("{:?}", Value::String("héllo".into()));
+        assert!(debug.contains("len: 6"), "{debug}");
+        assert!(debug.contains("chars: 5"), "{debug}");
+        assert!(debug.contains("\"héllo\""), "{debug}");
+
+        let debug = format!("{:?}", Value::Binary(vec![0xAB; 4]));
+        assert!(debug.contains("len: 4"), "{debug}");
+        assert!(debug.contains("[0xAB, 0xAB, 0xAB, 0xAB]"), "{debug}");
+
+        let debug = format!("{:?}", Value::Binary(vec![0xCD; 1_000]));
+        assert!(debug.contains("len: 1000"), "{debug}");
+        assert!(debug.contains("0xCD, ...]"), "{debug}");
+        assert_eq!(debug.matches("0xCD").count(), 32);
+
+        let debug = format!("{:?}", Value::Array(vec![Value::Null(()); 3]));
+        assert!(debug.contains("len: 3"), "{debug}");
+    }
+
+    #[test]
+    fn test_size_class() {
+        assert_eq!(Value::Boolean(true).size_class().unwrap(), SizeClass::Tiny);
+        assert_eq!(
+            Value::from(1_000_u64).size_class().unwrap(),
+            SizeClass::Tiny
+        );
+        assert_eq!(
+            Value::String("a short sentence".into())
+                .size_class()
+                .unwrap(),
+            SizeClass::Small
+        );
+        assert_eq!(
+            Value::Binary(vec![0; 1_000]).size_class().unwrap(),
+            SizeClass::Medium
+        );
+        assert_eq!(
+            Value::Array(vec![Value::String("x".repeat(1_000)); 100])
+                .size_class()
+                .unwrap(),
+            SizeClass::Large
+        );
+
+        assert_eq!(Value::Null(()).serialised_size().unwrap(), 1);
+        assert_eq!(SizeClass::from_size(SizeClass::TINY_MAX), SizeClass::Tiny);
+        assert_eq!(
+            SizeClass::from_size(SizeClass::TINY_MAX + 1),
+            SizeClass::Small
+        );
+        assert_eq!(
+            SizeClass::from_size(SizeClass::MEDIUM_MAX),
+            SizeClass::Medium
+        );
+        assert_eq!(
+            SizeClass::from_size(SizeClass::MEDIUM_MAX + 1),
+            SizeClass::Large
+        );
+        assert!(SizeClass::Tiny < SizeClass::Large);
+    }
+
+    #[test]
+    fn test_try_merge() {
+        assert_eq!(
+            Value::from(40).try_merge(Value::from(2_u8)).unwrap(),
+            Value::from(42)
+        );
+        assert_eq!(
+            Value::from(-50).try_merge(Value::from(8)).unwrap(),
+            Value::from(-42)
+        );
+        assert_eq!(
+            Value::from(u128::MAX).try_merge(Value::from(-1)).unwrap(),
+            Value::from(u128::MAX - 1)
+        );
+        assert!(matches!(
+            Value::from(u128::MAX).try_merge(Value::from(1)),
+            Err(ValueSerError::IntegerSerError(_))
+        ));
+
+        assert_eq!(
+            Value::String("hello, ".into())
+                .try_merge(Value::String("world".into()))
+                .unwrap(),
+            Value::String("hello, world".into())
+        );
+        assert_eq!(
+            Value::Array(vec![Value::from(1)])
+                .try_merge(Value::Array(vec![Value::Null(())]))
+                .unwrap(),
+            Value::Array(vec![Value::from(1), Value::Null(())])
+        );
+
+        let map = |entries: Vec<(&str, Value)>| {
+            Value::Map(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), v))
+                    .collect(),
+            )
+        };
+        let ours = map(vec![("count", Value::from(1)), ("ours", Value::Null(()))]);
+        let theirs = map(vec![("count", Value::from(2)), ("theirs", Value::Null(()))]);
+        assert_eq!(
+            ours.try_merge(theirs).unwrap(),
+            map(vec![
+                ("count", Value::from(3)),
+                ("ours", Value::Null(())),
+                ("theirs", Value::Null(()))
+            ])
+        );
+
+        assert!(matches!(
+            Value::from(1).try_merge(Value::String("1".into())),
+            Err(ValueSerError::CannotMerge(
+                ValueTy::Integer,
+                ValueTy::String
+            ))
+        ));
+        assert!(matches!(
+            Value::Boolean(true).try_merge(Value::Boolean(true)),
+            Err(ValueSerError::CannotMerge(
+                ValueTy::Boolean,
+                ValueTy::Boolean
+            ))
+        ));
+        assert!(matches!(
+            map(vec![("a", Value::from(1))]).try_merge(map(vec![("a", Value::Null(()))])),
+            Err(ValueSerError::CannotMerge(ValueTy::Integer, ValueTy::Null))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_niche() {
+        let bytes = [(u8::from(ValueTy::Boolean) << 4) | 0b10];
+        assert!(matches!(
+            Value::deser(&mut Cursor::new(&bytes), None),
+            Err(ValueSerError::InvalidNiche(ValueTy::Boolean, 0b10))
+        ));
+    }
+
+    #[test]
+    fn test_float_format_shortest() {
+        for f in [0.1, 1.0 / 3.0, 1e300, -2.5e-8, f64::MIN_POSITIVE, 0.0] {
+            let written = FloatFormat::Shortest.format_f64(f);
+            assert_eq!(written.parse::<f64>().unwrap().to_bits(), f.to_bits());
+
+            let json = Value::DoubleFloat(f).convert_to_json().unwrap();
+            assert_eq!(json.as_f64().unwrap().to_bits(), f.to_bits());
+        }
+
+        assert_eq!(FloatFormat::Shortest.format_f64(0.1), "0.1");
+        assert_eq!(Value::DoubleFloat(0.1).to_string(), "0.1");
+        //written as an f32, rather than the digits of the closest f64
+        assert_eq!(
+            Value::SingleFloat(0.1).convert_to_json(),
+            Some(serde_json::json!(0.1))
+        );
+        assert_eq!(FloatFormat::Scientific.format_f64(1234.5), "1.2345e3");
+        assert_eq!(
+            Value::DoubleFloat(1234.5)
+                .display_with(FloatFormat::Scientific)
+                .to_string(),
+            "1.2345e3"
+        );
+
+        assert_eq!(Value::DoubleFloat(f64::NAN).convert_to_json(), None);
+    }
+
+    #[test]
+    fn test_float_format_fixed() {
+        let fixed = FloatFormat::Fixed(2);
+        assert_eq!(fixed.format_f64(1.23456), "1.23");
+        assert_eq!(fixed.format_f64(2.0), "2.00");
+        assert_eq!(fixed.format_f32(0.125), "0.12");
+
+        assert_eq!(
+            Value::Array(vec![Value::DoubleFloat(1.0 / 3.0), Value::SingleFloat(0.5)])
+                .display_with(FloatFormat::Fixed(3))
+                .to_string(),
+            "[0.333, 0.500]"
+        );
+        assert_eq!(
+            Value::DoubleFloat(1.23456).convert_to_json_with(fixed),
+            Some(serde_json::json!(1.23))
+        );
+        assert_eq!(
+            Value::Array(vec![Value::DoubleFloat(1.0 / 3.0)])
+                .convert_to_json_with(FloatFormat::Fixed(1)),
+            Some(serde_json::json!([0.3]))
+        );
+    }
 
     proptest! {
+        #[test]
+        fn test_float_shortest_round_trips (f in any::<f64>()) {
+            if f.is_finite() {
+                let written = FloatFormat::Shortest.format_f64(f);
+                prop_assert_eq!(written.parse::<f64>().unwrap().to_bits(), f.to_bits());
+
+                let json = Value::DoubleFloat(f).convert_to_json().unwrap();
+                prop_assert_eq!(json.as_f64().unwrap().to_bits(), f.to_bits());
+            }
+        }
+
         #[test]
         fn test_ch (c in any::<char>()) {
             let v = Value::Character(c);
@@ -1189,6 +2369,18 @@ mod tests {
             prop_assert_eq!(out, i);
         }
 
+        #[test]
+        fn test_duration (secs in any::<i64>(), nanos in 0..1_000_000_000_u32) {
+            let Some(d) = TimeDelta::new(secs, nanos) else {
+                return Ok(());
+            };
+            let v = Value::Duration(d);
+
+            let bytes = v.ser(None).unwrap();
+            let out_value = Value::deser(&mut Cursor::new(&bytes), None).unwrap();
+            prop_assert_eq!(out_value.to_duration(), Some(d));
+        }
+
         //TODO: more tests :)
     }
 }